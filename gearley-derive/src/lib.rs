@@ -0,0 +1,134 @@
+//! `#[derive(Syntax)]` for typed syntax trees of the gearley parser.
+//!
+//! The derived impl adds grammar rules for the type to `gearley::ast::SyntaxGrammar`,
+//! and builds values of the type from values of their children, which are moved into it.
+//! Structs and variants with braces or parentheses must have at least one field.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, Ident};
+
+#[proc_macro_derive(Syntax)]
+pub fn derive_syntax(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "generic types cannot derive `Syntax`",
+        ));
+    }
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let body = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Unit => quote! {
+                grammar.terminal_type::<Self>(#name_str, |_| {
+                    ::std::boxed::Box::new(#name) as ::gearley::ast::Value
+                })
+            },
+            ref fields => {
+                let rule = rule(&quote!(#name), fields)?;
+                quote! {
+                    let lhs = grammar.nonterminal::<Self>(#name_str);
+                    #rule
+                    lhs
+                }
+            }
+        },
+        Data::Enum(ref data) => {
+            let rules = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let variant_name = &variant.ident;
+                    match variant.fields {
+                        Fields::Unit => {
+                            let terminal_name = format!("{}::{}", name, variant_name);
+                            Ok(quote! {
+                                let terminal = grammar.terminal(#terminal_name, |_| {
+                                    ::std::boxed::Box::new(#name::#variant_name)
+                                        as ::gearley::ast::Value
+                                });
+                                grammar.rule(lhs, &[terminal], |mut args| args.pop());
+                            })
+                        }
+                        ref fields => rule(&quote!(#name::#variant_name), fields),
+                    }
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                let lhs = grammar.nonterminal::<Self>(#name_str);
+                #(#rules)*
+                lhs
+            }
+        }
+        Data::Union(ref data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "unions cannot derive `Syntax`",
+            ));
+        }
+    };
+    Ok(quote! {
+        impl ::gearley::ast::Syntax for #name {
+            fn symbol(grammar: &mut ::gearley::ast::SyntaxGrammar) -> ::gearley::ast::Symbol {
+                if let Some(symbol) = grammar.get::<Self>() {
+                    return symbol;
+                }
+                #body
+            }
+
+            fn from_value(value: ::gearley::ast::Value) -> Option<Self> {
+                ::gearley::ast::downcast::<Self>(value)
+            }
+        }
+    })
+}
+
+// Generates a rule whose RHS consists of the fields, and which builds the given constructor.
+// Empty rules are rejected, because their LHS would be nulling.
+fn rule(constructor: &TokenStream2, fields: &Fields) -> syn::Result<TokenStream2> {
+    if fields.is_empty() {
+        return Err(syn::Error::new(
+            fields.span(),
+            "structs and variants without fields cannot derive `Syntax`, \
+             unless they are unit structs or unit variants",
+        ));
+    }
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let values = types
+        .iter()
+        .map(|ty| quote!(<#ty as ::gearley::ast::Syntax>::from_value(args.next()?)?));
+    let value = match *fields {
+        Fields::Named(ref named) => {
+            let field_names: Vec<&Ident> = named
+                .named
+                .iter()
+                .map(|field| field.ident.as_ref().unwrap())
+                .collect();
+            quote!(#constructor { #(#field_names: #values),* })
+        }
+        _ => quote!(#constructor(#(#values),*)),
+    };
+    Ok(quote! {
+        let rhs = [#(<#types as ::gearley::ast::Syntax>::symbol(grammar)),*];
+        grammar.rule(lhs, &rhs[..], |args| {
+            let mut args = args.into_iter();
+            Some(::std::boxed::Box::new(#value) as ::gearley::ast::Value)
+        });
+    })
+}
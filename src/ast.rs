@@ -0,0 +1,212 @@
+//! Typed syntax trees built from parse forests.
+//!
+//! Types that implement `Syntax` describe their own grammar rules, and know how to build
+//! themselves from values of their children. The trait is usually derived with
+//! `#[derive(Syntax)]` from the `gearley-derive` crate:
+//!
+//! * a unit struct is a terminal,
+//! * a struct with fields is a nonterminal with one rule,
+//! * an enum is a nonterminal with one rule for every variant. A unit variant is a terminal
+//!   of its own, derived from the enum's symbol.
+//!
+//! Fields may be boxed in order to make recursive types. Values of children are moved into
+//! their parents, so types need not implement `Clone`.
+//!
+//! Structs and variants with braces or parentheses must have fields. An empty rule could
+//! only derive a nulling symbol, which has no value:
+//!
+//! ```compile_fail
+//! #[macro_use]
+//! extern crate gearley_derive;
+//! extern crate gearley;
+//!
+//! #[derive(Syntax)]
+//! struct Empty {}
+//!
+//! fn main() {}
+//! ```
+
+use std::any::{Any, TypeId};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::vec;
+
+use cfg::earley::Grammar;
+pub use cfg::Symbol;
+
+use evaluator::EvaluationError;
+use forest::bocage::trees::ParseTree;
+use forest::node_handle::NodeHandle;
+use forest::Bocage;
+use grammar::InternalGrammar;
+
+/// A value of any type that implements `Syntax`.
+pub type Value = Box<dyn Any>;
+
+/// A type with grammar rules.
+pub trait Syntax: Sized + 'static {
+    /// Adds the symbol and rules of this type and of types it depends on, once.
+    /// Returns the symbol of this type.
+    fn symbol(grammar: &mut SyntaxGrammar) -> Symbol;
+
+    /// Extracts this type from a value of a child.
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+/// Builds a value from values of factors of a rule.
+pub type RuleBuilder = fn(Vec<Value>) -> Option<Value>;
+
+/// Builds a value of a terminal from the leaf value that was passed to the forest
+/// along with the token.
+pub type LeafBuilder = fn(u32) -> Value;
+
+/// A grammar that is generated from types, together with builders of values for its rules.
+pub struct SyntaxGrammar {
+    grammar: Grammar,
+    types: BTreeMap<TypeId, Symbol>,
+    names: Vec<(Symbol, &'static str)>,
+    rules: Vec<RuleBuilder>,
+    leaves: BTreeMap<Symbol, LeafBuilder>,
+}
+
+impl SyntaxGrammar {
+    /// Generates the grammar for a type, which becomes the start symbol.
+    pub fn new<T: Syntax>() -> Self {
+        let mut result = SyntaxGrammar {
+            grammar: Grammar::new(),
+            types: BTreeMap::new(),
+            names: vec![],
+            rules: vec![],
+            leaves: BTreeMap::new(),
+        };
+        let start = T::symbol(&mut result);
+        result.grammar.set_start(start);
+        result
+    }
+
+    /// Returns the symbol of a type that was already added.
+    pub fn get<T: Syntax>(&self) -> Option<Symbol> {
+        self.types.get(&TypeId::of::<T>()).cloned()
+    }
+
+    /// Adds a nonterminal symbol for a type. Rules must be added afterwards.
+    pub fn nonterminal<T: Syntax>(&mut self, name: &'static str) -> Symbol {
+        let symbol = self.grammar.sym();
+        self.types.insert(TypeId::of::<T>(), symbol);
+        self.names.push((symbol, name));
+        symbol
+    }
+
+    /// Adds a terminal symbol, which is built by the given function.
+    pub fn terminal(&mut self, name: &'static str, leaf: LeafBuilder) -> Symbol {
+        let symbol = self.grammar.sym();
+        self.names.push((symbol, name));
+        self.leaves.insert(symbol, leaf);
+        symbol
+    }
+
+    /// Adds a terminal symbol for a type.
+    pub fn terminal_type<T: Syntax>(&mut self, name: &'static str, leaf: LeafBuilder) -> Symbol {
+        let symbol = self.terminal(name, leaf);
+        self.types.insert(TypeId::of::<T>(), symbol);
+        symbol
+    }
+
+    /// Adds a rule with a builder of its value. Returns the external rule ID.
+    pub fn rule(&mut self, lhs: Symbol, rhs: &[Symbol], build: RuleBuilder) -> u32 {
+        self.grammar.rule(lhs).rhs(rhs);
+        self.rules.push(build);
+        self.rules.len() as u32 - 1
+    }
+
+    /// Returns the symbol with the given name. Terminals are named after their types,
+    /// or after their enums and variants, such as `Op::Plus`.
+    pub fn symbol_named(&self, name: &str) -> Option<Symbol> {
+        self.names
+            .iter()
+            .find(|&&(_, symbol_name)| symbol_name == name)
+            .map(|&(symbol, _)| symbol)
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    /// Returns the internal grammar with names of all symbols.
+    pub fn to_internal_grammar(&self) -> InternalGrammar {
+        let mut result = InternalGrammar::from_grammar(&self.grammar);
+        for &(symbol, name) in &self.names {
+            result.set_symbol_name(symbol, name);
+        }
+        result
+    }
+
+    /// Builds the value of the first tree under the root. Liveness must be marked
+    /// for the root.
+    pub fn evaluate<T, G>(&self, bocage: &Bocage<G>, root: NodeHandle) -> Result<T, EvaluationError>
+    where
+        T: Syntax,
+        G: Borrow<InternalGrammar>,
+    {
+        let tree = bocage
+            .trees(root)
+            .next()
+            .ok_or(EvaluationError::MissingRoot)?;
+        T::from_value(self.build(tree)?).ok_or(EvaluationError::MissingRoot)
+    }
+
+    // Builds values bottom-up. Values of children are moved into their parent.
+    fn build(&self, tree: ParseTree) -> Result<Value, EvaluationError> {
+        // Rules with their remaining children and values of visited children.
+        let mut stack: Vec<(u32, vec::IntoIter<ParseTree>, Vec<Value>)> = vec![];
+        let mut next = Some(tree);
+        loop {
+            let value = match next.take() {
+                Some(ParseTree::Node {
+                    action, children, ..
+                }) => {
+                    let len = children.len();
+                    stack.push((action, children.into_iter(), Vec::with_capacity(len)));
+                    None
+                }
+                Some(ParseTree::Leaf { symbol, value, .. }) => match self.leaves.get(&symbol) {
+                    Some(leaf) => Some(leaf(value)),
+                    None => return Err(EvaluationError::InvalidLeaf { symbol }),
+                },
+                Some(ParseTree::Nulling { symbol }) => {
+                    return Err(EvaluationError::InvalidNulling { symbol })
+                }
+                None => {
+                    let (rule, _, args) = stack.pop().unwrap();
+                    let build = self.rules.get(rule as usize);
+                    let value = build.and_then(|build| build(args));
+                    Some(value.ok_or(EvaluationError::InvalidRule { rule })?)
+                }
+            };
+            if let Some(value) = value {
+                match stack.last_mut() {
+                    Some(&mut (_, _, ref mut args)) => args.push(value),
+                    None => return Ok(value),
+                }
+            }
+            next = stack
+                .last_mut()
+                .and_then(|&mut (_, ref mut children, _)| children.next());
+        }
+    }
+}
+
+/// Extracts a value of a type by moving it out of a child's value.
+pub fn downcast<T: Any>(value: Value) -> Option<T> {
+    value.downcast::<T>().ok().map(|value| *value)
+}
+
+impl<T: Syntax> Syntax for Box<T> {
+    fn symbol(grammar: &mut SyntaxGrammar) -> Symbol {
+        T::symbol(grammar)
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        T::from_value(value).map(Box::new)
+    }
+}
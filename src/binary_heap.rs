@@ -15,23 +15,25 @@
 #![cfg_attr(feature = "cargo-clippy", allow(nonminimal_bool))]
 
 use std::mem::swap;
-use std::u32;
 
 use forest::Forest;
-use item::{CompletedItem, CompletedItemLinked, Item};
-use recognizer::Recognizer;
+use invariant;
+use item::{CompletedItem, CompletedItemLinked, Item, ItemIndex};
+use policy::PerformancePolicy;
+use recognizer::{OverflowError, Recognizer};
 
-impl<'g, F> Recognizer<'g, F>
+impl<'g, F, P> Recognizer<'g, F, P>
     where F: Forest,
+          P: PerformancePolicy,
 {
     /// Returns the greatest item in the binary heap, or `None` if it is empty.
     #[inline]
     pub fn heap_peek(&self) -> Option<CompletedItem<F::NodeRef>> {
         self.complete.get(0).and_then(|&right_item|
-            self.medial.get(right_item.idx as usize).map(|left_item|
+            self.linked_item(right_item.idx).map(|left_item|
                 CompletedItem {
-                    origin: left_item.origin,
-                    dot: left_item.dot,
+                    origin: left_item.origin(),
+                    dot: left_item.dot(),
                     left_node: left_item.node,
                     right_node: right_item.node,
                 }
@@ -39,9 +41,17 @@ impl<'g, F> Recognizer<'g, F>
         )
     }
 
+    // Returns the medial item that a completed item links to.
     #[inline(always)]
-    fn heap_get(&self, idx_idx: usize) -> Option<&Item<F::NodeRef>> {
-        self.complete.get(idx_idx).and_then(|&item| self.medial.get(item.idx as usize))
+    fn linked_item(&self, idx: P::Index) -> Option<&Item<F::NodeRef, P::Dot, P::Origin>> {
+        let item = self.medial.get(idx.to_usize());
+        invariant::check(item.is_some(), "completed items link to medial items");
+        item
+    }
+
+    #[inline(always)]
+    fn heap_get(&self, idx_idx: usize) -> Option<&Item<F::NodeRef, P::Dot, P::Origin>> {
+        self.complete.get(idx_idx).and_then(|&item| self.linked_item(item.idx))
     }
 
     /// Removes the greatest item from the binary heap and returns it, or `None` if it
@@ -52,10 +62,10 @@ impl<'g, F> Recognizer<'g, F>
                 swap(&mut right_item, &mut self.complete[0]);
                 self.sift_down(0);
             }
-            self.medial.get(right_item.idx as usize).map(|left_item|
+            self.linked_item(right_item.idx).map(|left_item|
                 CompletedItem {
-                    origin: left_item.origin,
-                    dot: left_item.dot,
+                    origin: left_item.origin(),
+                    dot: left_item.dot(),
                     left_node: left_item.node,
                     right_node: right_item.node,
                 }
@@ -66,18 +76,24 @@ impl<'g, F> Recognizer<'g, F>
     /// Pushes an item onto the binary heap.
     pub fn heap_push(&mut self, item: CompletedItem<F::NodeRef>) {
         let old_indices_len = self.complete.len();
-        let old_medial_len = self.medial.len();
-        assert!(old_medial_len as u64 <= u32::MAX.into());
-        self.medial.push(item.into());
+        let idx = match P::Index::from_usize(self.medial.len()) {
+            Some(idx) => idx,
+            None => return self.set_overflow(OverflowError::MedialItems),
+        };
+        let medial_item = match Item::new(item.origin, item.dot, item.left_node) {
+            Some(medial_item) => medial_item,
+            None => return self.set_overflow(OverflowError::EarleySets),
+        };
+        self.medial.push(medial_item);
         self.complete.push(CompletedItemLinked {
-            idx: old_medial_len as u32,
+            idx,
             node: item.right_node,
         });
         self.sift_up(0, old_indices_len);
     }
 
     /// Pushes an item onto the binary heap.
-    pub fn heap_push_linked(&mut self, item: CompletedItemLinked<F::NodeRef>) {
+    pub fn heap_push_linked(&mut self, item: CompletedItemLinked<F::NodeRef, P::Index>) {
         let old_indices_len = self.complete.len();
         self.complete.push(item);
         self.sift_up(0, old_indices_len);
@@ -87,11 +103,11 @@ impl<'g, F> Recognizer<'g, F>
     /// (ascending) order.
     fn sift_up(&mut self, start: usize, mut pos: usize) {
         let element_idx = self.complete[pos];
-        let element = &self.medial[element_idx.idx as usize];
+        let element = &self.medial[element_idx.idx.to_usize()];
         while pos > start {
             let parent = (pos - 1) / 2;
             let parent_idx = self.complete[parent];
-            if *element <= self.medial[parent_idx.idx as usize] {
+            if *element <= self.medial[parent_idx.idx.to_usize()] {
                 break;
             }
             self.complete[pos] = parent_idx;
@@ -104,7 +120,7 @@ impl<'g, F> Recognizer<'g, F>
     /// while its children are larger.
     fn sift_down_range(&mut self, mut pos: usize, end: usize) {
         let element_idx = self.complete[pos];
-        let element = &self.medial[element_idx.idx as usize];
+        let element = &self.medial[element_idx.idx.to_usize()];
         let mut child = 2 * pos + 1;
         while child < end {
             let right = child + 1;
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use cfg::Symbol;
+
+/// An immutable node of a concrete syntax tree. Green nodes know their kind, their children
+/// and the length of their text, but not their positions.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct GreenNode {
+    data: Arc<GreenNodeData>,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash)]
+struct GreenNodeData {
+    kind: Symbol,
+    text_len: u32,
+    children: Vec<GreenElement>,
+}
+
+/// An immutable token with its text.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct GreenToken {
+    data: Arc<GreenTokenData>,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash)]
+struct GreenTokenData {
+    kind: Symbol,
+    text: String,
+}
+
+/// A child of a green node.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GreenElement {
+    Node(GreenNode),
+    Token(GreenToken),
+}
+
+impl GreenNode {
+    pub fn new(kind: Symbol, children: Vec<GreenElement>) -> Self {
+        let text_len = children.iter().map(|child| child.text_len()).sum();
+        GreenNode {
+            data: Arc::new(GreenNodeData {
+                kind,
+                text_len,
+                children,
+            }),
+        }
+    }
+
+    /// Returns the external symbol of this node.
+    pub fn kind(&self) -> Symbol {
+        self.data.kind
+    }
+
+    pub fn text_len(&self) -> u32 {
+        self.data.text_len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.data.children[..]
+    }
+
+    /// Returns the text of all tokens under this node.
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.text_len() as usize);
+        let mut stack = vec![self.children().iter()];
+        while let Some(children) = stack.last_mut() {
+            let child = match children.next() {
+                Some(child) => child,
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            match *child {
+                GreenElement::Node(ref node) => stack.push(node.children().iter()),
+                GreenElement::Token(ref token) => text.push_str(token.text()),
+            }
+        }
+        text
+    }
+}
+
+impl GreenToken {
+    pub fn new(kind: Symbol, text: &str) -> Self {
+        GreenToken {
+            data: Arc::new(GreenTokenData {
+                kind,
+                text: text.to_string(),
+            }),
+        }
+    }
+
+    /// Returns the external symbol of this token.
+    pub fn kind(&self) -> Symbol {
+        self.data.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.data.text[..]
+    }
+
+    pub fn text_len(&self) -> u32 {
+        self.data.text.len() as u32
+    }
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> Symbol {
+        match *self {
+            GreenElement::Node(ref node) => node.kind(),
+            GreenElement::Token(ref token) => token.kind(),
+        }
+    }
+
+    pub fn text_len(&self) -> u32 {
+        match *self {
+            GreenElement::Node(ref node) => node.text_len(),
+            GreenElement::Token(ref token) => token.text_len(),
+        }
+    }
+}
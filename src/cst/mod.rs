@@ -0,0 +1,100 @@
+//! Lossless concrete syntax trees built from parse forests.
+//!
+//! Green trees are immutable and contain token texts. Red trees are built on top of green
+//! trees and know positions of nodes and tokens in the text.
+
+mod green;
+mod red;
+
+pub use self::green::{GreenElement, GreenNode, GreenToken};
+pub use self::red::{
+    Ancestors, SyntaxElement, SyntaxElementChildren, SyntaxNode, SyntaxNodeChildren, SyntaxToken,
+    TextRange,
+};
+
+use std::borrow::Borrow;
+use std::vec;
+
+use cfg::Symbol;
+
+use forest::bocage::trees::ParseTree;
+use forest::node_handle::NodeHandle;
+use forest::Bocage;
+use grammar::InternalGrammar;
+
+/// Builds green trees from a `Bocage`. Kinds of nodes and tokens are external symbols.
+pub struct CstBuilder<F> {
+    token_text: F,
+}
+
+impl<F> CstBuilder<F>
+where
+    F: FnMut(Symbol, u32) -> String,
+{
+    /// Creates a builder with a callback that returns the text of a token, given its
+    /// symbol and leaf value.
+    pub fn new(token_text: F) -> Self {
+        CstBuilder { token_text }
+    }
+
+    /// Builds the tree of live nodes under the root. Liveness must be marked for the root.
+    /// Where the order leaves more than one alternative, the first live alternative is taken.
+    /// Nulling symbols become empty nodes.
+    pub fn build<G>(&mut self, bocage: &Bocage<G>, root: NodeHandle) -> GreenNode
+    where
+        G: Borrow<InternalGrammar>,
+    {
+        let tree = bocage.trees(root).next().unwrap();
+        match self.convert(tree) {
+            GreenElement::Node(node) => node,
+            GreenElement::Token(token) => {
+                GreenNode::new(token.kind(), vec![GreenElement::Token(token)])
+            }
+        }
+    }
+
+    fn convert(&mut self, tree: ParseTree) -> GreenElement {
+        // Nodes with their remaining children and converted children.
+        let mut stack: Vec<(Symbol, vec::IntoIter<ParseTree>, Vec<GreenElement>)> = vec![];
+        let mut element = self.open(tree, &mut stack);
+        loop {
+            if let Some(done) = element {
+                match stack.last_mut() {
+                    Some(&mut (_, _, ref mut converted)) => converted.push(done),
+                    None => return done,
+                }
+            }
+            let next = stack.last_mut().unwrap().1.next();
+            element = match next {
+                Some(child) => self.open(child, &mut stack),
+                None => {
+                    let (kind, _, children) = stack.pop().unwrap();
+                    Some(GreenElement::Node(GreenNode::new(kind, children)))
+                }
+            };
+        }
+    }
+
+    // Converts a leaf, or pushes a node whose children are converted later.
+    fn open(
+        &mut self,
+        tree: ParseTree,
+        stack: &mut Vec<(Symbol, vec::IntoIter<ParseTree>, Vec<GreenElement>)>,
+    ) -> Option<GreenElement> {
+        match tree {
+            ParseTree::Node { lhs, children, .. } => {
+                let len = children.len();
+                stack.push((lhs, children.into_iter(), Vec::with_capacity(len)));
+                None
+            }
+            ParseTree::Leaf { symbol, value, .. } => {
+                let text = (self.token_text)(symbol, value);
+                Some(GreenElement::Token(GreenToken::new(symbol, &text[..])))
+            }
+            // Symbols of parse trees are external, including nulling symbols.
+            ParseTree::Nulling { symbol } => {
+                Some(GreenElement::Node(GreenNode::new(symbol, vec![])))
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use std::rc::Rc;
+
+use cfg::Symbol;
+
+use super::green::{GreenElement, GreenNode, GreenToken};
+
+/// A range of text in bytes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct TextRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// A node of a concrete syntax tree with its position and parent. Red nodes are created
+/// on demand from green nodes.
+#[derive(Clone, Debug)]
+pub struct SyntaxNode {
+    data: Rc<SyntaxNodeData>,
+}
+
+#[derive(Debug)]
+struct SyntaxNodeData {
+    green: GreenNode,
+    parent: Option<SyntaxNode>,
+    // Position among children of the parent.
+    index: usize,
+    offset: u32,
+}
+
+/// A token of a concrete syntax tree with its position and parent.
+#[derive(Clone, Debug)]
+pub struct SyntaxToken {
+    green: GreenToken,
+    parent: SyntaxNode,
+    index: usize,
+    offset: u32,
+}
+
+/// A child of a syntax node.
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl TextRange {
+    pub fn len(&self) -> u32 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: GreenNode) -> Self {
+        SyntaxNode {
+            data: Rc::new(SyntaxNodeData {
+                green,
+                parent: None,
+                index: 0,
+                offset: 0,
+            }),
+        }
+    }
+
+    /// Returns the external symbol of this node.
+    pub fn kind(&self) -> Symbol {
+        self.data.green.kind()
+    }
+
+    pub fn green(&self) -> &GreenNode {
+        &self.data.green
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.data.parent.clone()
+    }
+
+    /// Returns the position of this node among children of its parent, including tokens.
+    pub fn index(&self) -> usize {
+        self.data.index
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        TextRange {
+            start: self.data.offset,
+            end: self.data.offset + self.data.green.text_len(),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.data.green.text()
+    }
+
+    /// Iterates over child nodes and tokens.
+    pub fn children_with_tokens(&self) -> SyntaxElementChildren {
+        SyntaxElementChildren {
+            parent: self.clone(),
+            index: 0,
+            offset: self.data.offset,
+        }
+    }
+
+    /// Iterates over child nodes, skipping tokens.
+    pub fn children(&self) -> SyntaxNodeChildren {
+        SyntaxNodeChildren {
+            inner: self.children_with_tokens(),
+        }
+    }
+
+    /// Iterates over this node and its ancestors.
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors {
+            next: Some(self.clone()),
+        }
+    }
+
+    /// Returns all tokens under this node in the order of their text.
+    pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = vec![];
+        let mut stack = vec![self.children_with_tokens()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(SyntaxElement::Node(node)) => stack.push(node.children_with_tokens()),
+                Some(SyntaxElement::Token(token)) => tokens.push(token),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        tokens
+    }
+}
+
+impl SyntaxToken {
+    /// Returns the external symbol of this token.
+    pub fn kind(&self) -> Symbol {
+        self.green.kind()
+    }
+
+    pub fn green(&self) -> &GreenToken {
+        &self.green
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    /// Returns the position of this token among children of its parent, including nodes.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        TextRange {
+            start: self.offset,
+            end: self.offset + self.green.text_len(),
+        }
+    }
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> Symbol {
+        match *self {
+            SyntaxElement::Node(ref node) => node.kind(),
+            SyntaxElement::Token(ref token) => token.kind(),
+        }
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        match *self {
+            SyntaxElement::Node(ref node) => node.text_range(),
+            SyntaxElement::Token(ref token) => token.text_range(),
+        }
+    }
+}
+
+/// An iterator over child nodes and tokens.
+pub struct SyntaxElementChildren {
+    parent: SyntaxNode,
+    index: usize,
+    offset: u32,
+}
+
+impl Iterator for SyntaxElementChildren {
+    type Item = SyntaxElement;
+
+    fn next(&mut self) -> Option<SyntaxElement> {
+        let element = match *self.parent.green().children().get(self.index)? {
+            GreenElement::Node(ref green) => SyntaxElement::Node(SyntaxNode {
+                data: Rc::new(SyntaxNodeData {
+                    green: green.clone(),
+                    parent: Some(self.parent.clone()),
+                    index: self.index,
+                    offset: self.offset,
+                }),
+            }),
+            GreenElement::Token(ref green) => SyntaxElement::Token(SyntaxToken {
+                green: green.clone(),
+                parent: self.parent.clone(),
+                index: self.index,
+                offset: self.offset,
+            }),
+        };
+        self.index += 1;
+        self.offset = element.text_range().end;
+        Some(element)
+    }
+}
+
+/// An iterator over child nodes.
+pub struct SyntaxNodeChildren {
+    inner: SyntaxElementChildren,
+}
+
+impl Iterator for SyntaxNodeChildren {
+    type Item = SyntaxNode;
+
+    fn next(&mut self) -> Option<SyntaxNode> {
+        self.inner.by_ref().find_map(|element| match element {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+}
+
+/// An iterator over a node and its ancestors.
+pub struct Ancestors {
+    next: Option<SyntaxNode>,
+}
+
+impl Iterator for Ancestors {
+    type Item = SyntaxNode;
+
+    fn next(&mut self) -> Option<SyntaxNode> {
+        let node = self.next.take();
+        self.next = node.as_ref().and_then(|node| node.parent());
+        node
+    }
+}
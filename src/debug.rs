@@ -1,9 +1,10 @@
 use std::fmt;
 
 use forest::Forest;
+use policy::PerformancePolicy;
 use recognizer::Recognizer;
 
-impl<'g, F: Forest> fmt::Debug for Recognizer<'g, F> {
+impl<'g, F: Forest, P: PerformancePolicy> fmt::Debug for Recognizer<'g, F, P> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f,
             "Recognizer {{ grammar: {:?}, \
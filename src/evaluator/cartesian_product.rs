@@ -0,0 +1,86 @@
+/// Iterates over all combinations of values of factors.
+pub struct CartesianProduct<'a, V: 'a> {
+    factors: Vec<&'a [V]>,
+    // Index of the current value of every factor.
+    indices: Vec<usize>,
+    // The current combination.
+    values: Vec<&'a V>,
+}
+
+impl<'a, V> CartesianProduct<'a, V> {
+    pub fn new() -> Self {
+        CartesianProduct {
+            factors: Vec::with_capacity(8),
+            indices: Vec::with_capacity(8),
+            values: Vec::with_capacity(8),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.factors.clear();
+        self.indices.clear();
+        self.values.clear();
+    }
+
+    /// Multiplies the cartesian product by a slice.
+    pub fn push(&mut self, slice: &'a [V]) {
+        self.factors.push(slice);
+        self.indices.push(0);
+        if let Some(first) = slice.first() {
+            self.values.push(first);
+        }
+    }
+
+    /// Multiplies the cartesian product by an iterator.
+    pub fn extend<I>(&mut self, product: I)
+    where
+        I: Iterator<Item = &'a [V]>,
+    {
+        for slice in product {
+            self.push(slice);
+        }
+    }
+
+    /// Checks whether the product has no combinations, which happens when any factor
+    /// has no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.len() < self.factors.len()
+    }
+
+    /// Returns the current combination. Empty if the product has no combinations.
+    pub fn as_slice(&self) -> &[&'a V] {
+        if self.is_empty() {
+            &[]
+        } else {
+            &self.values[..]
+        }
+    }
+
+    /// Moves to the next combination. Returns `false` after the last combination.
+    pub fn advance(&mut self) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        for ((value, index), factor) in self
+            .values
+            .iter_mut()
+            .zip(self.indices.iter_mut())
+            .zip(&self.factors)
+        {
+            *index += 1;
+            if *index < factor.len() {
+                *value = &factor[*index];
+                return true;
+            }
+            *index = 0;
+            *value = &factor[0];
+        }
+        false
+    }
+}
+
+impl<'a, V> Default for CartesianProduct<'a, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,131 @@
+//! Evaluation of bocage nodes on demand.
+//!
+//! Traversal evaluates every live node and stores results in the bocage. Instead, the lazy
+//! evaluator visits only requested nodes and their descendants. Values are memoized per
+//! node, and the bocage is left unchanged.
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+use cfg::Symbol;
+
+use forest::bocage::alternative::Alternative;
+use forest::node_handle::NodeHandle;
+use forest::Bocage;
+use grammar::InternalGrammar;
+
+use super::{evaluate_node, factors, EvaluationError, EvaluationMode};
+
+/// An evaluator that computes values of nodes of a marked `Bocage` on request.
+pub struct LazyEvaluator<'f, G: 'f, V, FLeaf, FRule, FNull> {
+    bocage: &'f Bocage<G>,
+    memo: BTreeMap<NodeHandle, Vec<V>>,
+    leaf: FLeaf,
+    rule: FRule,
+    null: FNull,
+    mode: EvaluationMode,
+}
+
+impl<'f, G, V, FLeaf, FRule, FNull> LazyEvaluator<'f, G, V, FLeaf, FRule, FNull>
+where
+    G: Borrow<InternalGrammar>,
+    FLeaf: FnMut(Symbol, u32) -> Result<V, EvaluationError>,
+    FRule: FnMut(u32, &[&V]) -> Result<V, EvaluationError>,
+    FNull: FnMut(Symbol, &mut Vec<V>) -> Result<(), EvaluationError>,
+{
+    /// Creates an evaluator for a bocage. Liveness must be marked, and nodes must not be
+    /// evaluated by traversal. As with `Evaluator`, the leaf callback is given token values.
+    pub fn new(bocage: &'f Bocage<G>, leaf: FLeaf, rule: FRule, null: FNull) -> Self {
+        Self::with_mode(bocage, leaf, rule, null, EvaluationMode::AllTrees)
+    }
+
+    pub fn with_mode(
+        bocage: &'f Bocage<G>,
+        leaf: FLeaf,
+        rule: FRule,
+        null: FNull,
+        mode: EvaluationMode,
+    ) -> Self {
+        LazyEvaluator {
+            bocage,
+            memo: BTreeMap::new(),
+            leaf,
+            rule,
+            null,
+            mode,
+        }
+    }
+
+    /// Evaluates a live node. Descendants are evaluated unless their values are memoized.
+    /// Fails if the bocage was evaluated by traversal.
+    pub fn evaluate(&mut self, node: NodeHandle) -> Result<&[V], EvaluationError> {
+        if self.bocage.evaluated.get() {
+            return Err(EvaluationError::EvaluatedForest);
+        }
+        if !self.bocage.is_alive(node) {
+            return Err(EvaluationError::MissingRoot);
+        }
+        // Nodes are evaluated after their factors.
+        let mut stack = vec![(node, None)];
+        while let Some((handle, alternatives)) = stack.pop() {
+            if self.memo.contains_key(&handle) {
+                continue;
+            }
+            match alternatives {
+                None => {
+                    let alternatives = self.alternatives(handle);
+                    let factors: Vec<NodeHandle> = factors(&alternatives)
+                        .filter(|factor| !self.memo.contains_key(factor))
+                        .collect();
+                    stack.push((handle, Some(alternatives)));
+                    stack.extend(factors.into_iter().map(|factor| (factor, None)));
+                }
+                Some(alternatives) => {
+                    let memo = &self.memo;
+                    let values = evaluate_node(
+                        self.bocage.graph[handle.usize()].expand(),
+                        &alternatives[..],
+                        |factor| memo.get(&factor),
+                        &mut self.leaf,
+                        &mut self.rule,
+                        &mut self.null,
+                        self.mode,
+                    )?;
+                    self.memo.insert(handle, values);
+                }
+            }
+        }
+        Ok(&self.memo[&node][..])
+    }
+
+    /// Returns memoized values of a node.
+    pub fn values(&self, node: NodeHandle) -> Option<&[V]> {
+        self.memo.get(&node).map(|values| &values[..])
+    }
+
+    /// Returns factors of live alternatives of a node, in order and without duplicates.
+    /// These are roots of subtrees that can be evaluated on their own. Leaves have no
+    /// children.
+    pub fn children(&self, node: NodeHandle) -> Vec<NodeHandle> {
+        let mut result: Vec<NodeHandle> = vec![];
+        for factor in factors(&self.alternatives(node)) {
+            if !result.contains(&factor) {
+                result.push(factor);
+            }
+        }
+        result
+    }
+
+    /// Forgets all memoized values.
+    pub fn clear(&mut self) {
+        self.memo.clear();
+    }
+
+    fn alternatives(&self, node: NodeHandle) -> Vec<Alternative> {
+        let mut alternatives = self.bocage.alternatives(node);
+        if self.mode == EvaluationMode::FirstTree {
+            alternatives.truncate(1);
+        }
+        alternatives
+    }
+}
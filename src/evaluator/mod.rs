@@ -0,0 +1,348 @@
+//! Evaluation of parse forests with closures for leaves, rules and nulling symbols.
+//!
+//! Values are computed bottom-up, in the order of traversal. Every node gets a list of values,
+//! one for each tree in the node's subforest, or only the first one.
+
+mod cartesian_product;
+mod lazy;
+mod parallel;
+
+pub use self::cartesian_product::CartesianProduct;
+pub use self::lazy::LazyEvaluator;
+pub use self::parallel::ParallelEvaluator;
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use cfg::Symbol;
+
+use forest::bocage::alternative::Alternative;
+use forest::bocage::node::Node;
+use forest::bocage::node::Node::*;
+use forest::bocage::traverse as bocage_traverse;
+use forest::compact_bocage::traverse as compact_traverse;
+use forest::node_handle::NodeHandle;
+use grammar::InternalGrammar;
+
+/// Chooses which trees of the forest are evaluated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EvaluationMode {
+    /// Evaluates only the first alternative of every sum, so every node has one value.
+    FirstTree,
+    /// Evaluates every alternative with the cartesian product of its factors' values.
+    AllTrees,
+}
+
+/// An error that stops evaluation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EvaluationError {
+    /// The leaf callback has no value for the given terminal.
+    InvalidLeaf { symbol: Symbol },
+    /// The rule callback has no value for the given external rule.
+    InvalidRule { rule: u32 },
+    /// The null callback has no value for the given nulling symbol.
+    InvalidNulling { symbol: Symbol },
+    /// A factor of a product has no values.
+    MissingFactor { symbol: Symbol },
+    /// The root was not reached by the traversal. Liveness may be marked for another root.
+    MissingRoot,
+    /// Nodes of the bocage were overwritten with results of an evaluation by traversal.
+    EvaluatedForest,
+}
+
+pub struct Evaluator<V, FLeaf, FRule, FNull> {
+    values: Vec<V>,
+    evaluated: Vec<Vec<V>>,
+    // Positions in `evaluated` for nodes of the compact bocage.
+    compact_evaluated: BTreeMap<NodeHandle, usize>,
+    leaf: FLeaf,
+    rule: FRule,
+    null: FNull,
+    mode: EvaluationMode,
+}
+
+impl<V, FLeaf, FRule, FNull> Evaluator<V, FLeaf, FRule, FNull>
+where
+    FLeaf: FnMut(Symbol, u32) -> Result<V, EvaluationError>,
+    FRule: FnMut(u32, &[&V]) -> Result<V, EvaluationError>,
+    FNull: FnMut(Symbol, &mut Vec<V>) -> Result<(), EvaluationError>,
+{
+    /// Creates an evaluator. The leaf callback is given a terminal and the value that
+    /// was passed to the forest along with the token.
+    pub fn new(leaf: FLeaf, rule: FRule, null: FNull) -> Self {
+        Self::with_mode(leaf, rule, null, EvaluationMode::AllTrees)
+    }
+
+    pub fn with_mode(leaf: FLeaf, rule: FRule, null: FNull, mode: EvaluationMode) -> Self {
+        Evaluator {
+            values: vec![],
+            evaluated: vec![],
+            compact_evaluated: BTreeMap::new(),
+            leaf,
+            rule,
+            null,
+            mode,
+        }
+    }
+
+    /// Evaluates live nodes of a `Bocage`. Returns values of the root.
+    pub fn evaluate<'f, G>(
+        &mut self,
+        traverse: &mut bocage_traverse::Traverse<'f, G>,
+        root: NodeHandle,
+    ) -> Result<Vec<V>, EvaluationError>
+    where
+        G: Borrow<InternalGrammar>,
+    {
+        self.clear();
+        let root_values = self.evaluate_nodes(traverse, root);
+        self.take_root(root_values)
+    }
+
+    /// Evaluates live nodes of a `CompactBocage`. Returns values of the root.
+    pub fn evaluate_compact<'f, G>(
+        &mut self,
+        traverse: &mut compact_traverse::Traverse<'f, G>,
+        root: NodeHandle,
+    ) -> Result<Vec<V>, EvaluationError>
+    where
+        G: Borrow<InternalGrammar>,
+    {
+        self.clear();
+        let root_values = self.evaluate_compact_nodes(traverse, root);
+        self.take_root(root_values)
+    }
+
+    // Returns the index of values of the root, if it was reached.
+    fn evaluate_nodes<'f, G>(
+        &mut self,
+        traverse: &mut bocage_traverse::Traverse<'f, G>,
+        root: NodeHandle,
+    ) -> Result<Option<usize>, EvaluationError>
+    where
+        G: Borrow<InternalGrammar>,
+    {
+        use forest::bocage::traverse::{LeafHandle, NullingHandle, SumHandle};
+
+        let mut root_values = None;
+        while let Some(mut item) = traverse.next_node() {
+            match item.item {
+                SumHandle(ref mut products) => {
+                    while let Some(product) = products.next_product() {
+                        let mut factors = CartesianProduct::new();
+                        for &(symbol, values_idx) in product.factors {
+                            let values = self.evaluated.get(values_idx as usize);
+                            factors.push(non_empty(values, symbol)?);
+                        }
+                        evaluate_product(
+                            &mut self.rule,
+                            product.action,
+                            &mut factors,
+                            self.mode,
+                            &mut self.values,
+                        )?;
+                        if self.mode == EvaluationMode::FirstTree {
+                            break;
+                        }
+                    }
+                }
+                NullingHandle => {
+                    self.nulling(item.symbol)?;
+                }
+                LeafHandle(value) => {
+                    let value = (self.leaf)(item.symbol, value)?;
+                    self.values.push(value);
+                }
+            }
+            let result = self.evaluated.len();
+            self.evaluated.push(mem::take(&mut self.values));
+            if item.handle() == root {
+                root_values = Some(result);
+            }
+            item.set_evaluation_result(result as u32);
+        }
+        Ok(root_values)
+    }
+
+    // Returns the index of values of the root, if it was reached.
+    fn evaluate_compact_nodes<'f, G>(
+        &mut self,
+        traverse: &mut compact_traverse::Traverse<'f, G>,
+        root: NodeHandle,
+    ) -> Result<Option<usize>, EvaluationError>
+    where
+        G: Borrow<InternalGrammar>,
+    {
+        use forest::compact_bocage::traverse::{LeafHandle, NullingHandle, SumHandle};
+
+        while let Some(mut item) = traverse.next_node() {
+            match item.item {
+                SumHandle(ref mut products) => {
+                    while let Some(product) = products.next_product() {
+                        let mut factors = CartesianProduct::new();
+                        for &(symbol, handle) in product.factors {
+                            let evaluated = &self.evaluated;
+                            let values = self
+                                .compact_evaluated
+                                .get(&handle)
+                                .and_then(|&idx| evaluated.get(idx));
+                            factors.push(non_empty(values, symbol)?);
+                        }
+                        evaluate_product(
+                            &mut self.rule,
+                            product.action,
+                            &mut factors,
+                            self.mode,
+                            &mut self.values,
+                        )?;
+                        if self.mode == EvaluationMode::FirstTree {
+                            break;
+                        }
+                    }
+                }
+                NullingHandle => {
+                    self.nulling(item.symbol)?;
+                }
+                LeafHandle(value) => {
+                    let value = (self.leaf)(item.symbol, value)?;
+                    self.values.push(value);
+                }
+            }
+            self.compact_evaluated
+                .insert(item.handle(), self.evaluated.len());
+            self.evaluated.push(mem::take(&mut self.values));
+            item.end_evaluation();
+        }
+        Ok(self.compact_evaluated.get(&root).cloned())
+    }
+
+    fn nulling(&mut self, symbol: Symbol) -> Result<(), EvaluationError> {
+        (self.null)(symbol, &mut self.values)?;
+        if self.mode == EvaluationMode::FirstTree {
+            self.values.truncate(1);
+        }
+        Ok(())
+    }
+
+    // Takes values of the root. Values of other nodes are dropped, even if evaluation
+    // stopped with an error.
+    fn take_root(
+        &mut self,
+        root_values: Result<Option<usize>, EvaluationError>,
+    ) -> Result<Vec<V>, EvaluationError> {
+        let result = root_values
+            .and_then(|idx| idx.ok_or(EvaluationError::MissingRoot))
+            .map(|idx| mem::take(&mut self.evaluated[idx]));
+        self.clear();
+        result
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.evaluated.clear();
+        self.compact_evaluated.clear();
+    }
+}
+
+fn evaluate_product<V, FRule>(
+    rule: &mut FRule,
+    action: u32,
+    factors: &mut CartesianProduct<V>,
+    mode: EvaluationMode,
+    values: &mut Vec<V>,
+) -> Result<(), EvaluationError>
+where
+    FRule: FnMut(u32, &[&V]) -> Result<V, EvaluationError>,
+{
+    loop {
+        values.push(rule(action, factors.as_slice())?);
+        if mode == EvaluationMode::FirstTree || !factors.advance() {
+            return Ok(());
+        }
+    }
+}
+
+// Computes values of a node of a bocage. Sums and products are valued by their live
+// alternatives, whose factors are valued already.
+fn evaluate_node<'v, V: 'v, FLeaf, FRule, FNull, FValues>(
+    node: Node,
+    alternatives: &[Alternative],
+    factor_values: FValues,
+    leaf: &mut FLeaf,
+    rule: &mut FRule,
+    null: &mut FNull,
+    mode: EvaluationMode,
+) -> Result<Vec<V>, EvaluationError>
+where
+    FLeaf: FnMut(Symbol, u32) -> Result<V, EvaluationError>,
+    FRule: FnMut(u32, &[&V]) -> Result<V, EvaluationError>,
+    FNull: FnMut(Symbol, &mut Vec<V>) -> Result<(), EvaluationError>,
+    FValues: Fn(NodeHandle) -> Option<&'v Vec<V>>,
+{
+    let mut values = vec![];
+    match node {
+        Sum { .. } | Product { .. } => {
+            for alternative in alternatives {
+                let mut factors = CartesianProduct::new();
+                for &(symbol, factor) in &alternative.factors {
+                    factors.push(non_empty(factor_values(factor), symbol)?);
+                }
+                evaluate_product(rule, alternative.action, &mut factors, mode, &mut values)?;
+            }
+        }
+        NullingLeaf { symbol } => {
+            null(symbol, &mut values)?;
+            if mode == EvaluationMode::FirstTree {
+                values.truncate(1);
+            }
+        }
+        Evaluated {
+            symbol,
+            values: value,
+        } => {
+            values.push(leaf(symbol, value)?);
+        }
+    }
+    Ok(values)
+}
+
+// Lists factors of alternatives, in order.
+fn factors<'a>(alternatives: &'a [Alternative]) -> impl Iterator<Item = NodeHandle> + 'a {
+    alternatives
+        .iter()
+        .flat_map(|alternative| alternative.factors.iter().map(|&(_, factor)| factor))
+}
+
+// Returns values of a factor, if there are any.
+fn non_empty<V>(values: Option<&Vec<V>>, symbol: Symbol) -> Result<&[V], EvaluationError> {
+    match values {
+        Some(values) if !values.is_empty() => Ok(&values[..]),
+        _ => Err(EvaluationError::MissingFactor { symbol }),
+    }
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EvaluationError::InvalidLeaf { symbol } => {
+                write!(f, "no value for terminal {}", symbol.usize())
+            }
+            EvaluationError::InvalidRule { rule } => write!(f, "no value for rule {}", rule),
+            EvaluationError::InvalidNulling { symbol } => {
+                write!(f, "no value for nulling symbol {}", symbol.usize())
+            }
+            EvaluationError::MissingFactor { symbol } => {
+                write!(f, "factor {} has no values", symbol.usize())
+            }
+            EvaluationError::MissingRoot => write!(f, "the root was not evaluated"),
+            EvaluationError::EvaluatedForest => {
+                write!(f, "the forest was already evaluated by traversal")
+            }
+        }
+    }
+}
+
+impl Error for EvaluationError {}
@@ -0,0 +1,171 @@
+//! Evaluation of a frozen bocage on multiple threads.
+//!
+//! Nodes are grouped by their height above the leaves. Nodes of the same height do not
+//! depend on each other, so every group is split between threads.
+
+use std::borrow::Borrow;
+use std::panic;
+use std::thread;
+
+use cfg::Symbol;
+
+use forest::bocage::alternative::Alternative;
+use forest::node_handle::NodeHandle;
+use forest::FrozenBocage;
+use grammar::InternalGrammar;
+
+use super::{evaluate_node, factors, EvaluationError, EvaluationMode};
+
+// Groups smaller than this are evaluated on the calling thread.
+const MIN_CHUNK_LEN: usize = 64;
+
+/// An evaluator that shares a `FrozenBocage` between threads. Closures are called from
+/// many threads at once. As with `Evaluator`, the leaf callback is given token values.
+pub struct ParallelEvaluator<FLeaf, FRule, FNull> {
+    leaf: FLeaf,
+    rule: FRule,
+    null: FNull,
+    mode: EvaluationMode,
+    threads: usize,
+}
+
+// A node together with its live alternatives.
+type Plan = (NodeHandle, Vec<Alternative>);
+
+impl<FLeaf, FRule, FNull> ParallelEvaluator<FLeaf, FRule, FNull> {
+    pub fn new(leaf: FLeaf, rule: FRule, null: FNull) -> Self {
+        Self::with_mode(leaf, rule, null, EvaluationMode::AllTrees)
+    }
+
+    pub fn with_mode(leaf: FLeaf, rule: FRule, null: FNull, mode: EvaluationMode) -> Self {
+        ParallelEvaluator {
+            leaf,
+            rule,
+            null,
+            mode,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Sets the maximal number of threads. By default, it is the available parallelism.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Evaluates nodes that are reachable from the root. Returns values of the root.
+    pub fn evaluate<V, G>(
+        &self,
+        bocage: &FrozenBocage<G>,
+        root: NodeHandle,
+    ) -> Result<Vec<V>, EvaluationError>
+    where
+        FLeaf: Fn(Symbol, u32) -> Result<V, EvaluationError> + Sync,
+        FRule: Fn(u32, &[&V]) -> Result<V, EvaluationError> + Sync,
+        FNull: Fn(Symbol, &mut Vec<V>) -> Result<(), EvaluationError> + Sync,
+        V: Send + Sync,
+        G: Borrow<InternalGrammar> + Sync,
+    {
+        if !bocage.is_alive(root) {
+            return Err(EvaluationError::MissingRoot);
+        }
+        let mut values: Vec<Vec<V>> = (0..bocage.len()).map(|_| vec![]).collect();
+        for level in self.levels(bocage, root) {
+            let chunk_len = (level.len() / self.threads + 1).max(MIN_CHUNK_LEN);
+            let evaluated = &values;
+            let results = if level.len() <= chunk_len {
+                vec![self.evaluate_chunk(bocage, &level[..], evaluated)]
+            } else {
+                thread::scope(|scope| {
+                    let threads: Vec<_> = level
+                        .chunks(chunk_len)
+                        .map(|chunk| {
+                            scope.spawn(move || self.evaluate_chunk(bocage, chunk, evaluated))
+                        })
+                        .collect();
+                    threads
+                        .into_iter()
+                        .map(|thread| {
+                            thread
+                                .join()
+                                .unwrap_or_else(|err| panic::resume_unwind(err))
+                        })
+                        .collect()
+                })
+            };
+            for (chunk, result) in level.chunks(chunk_len).zip(results) {
+                for (&(node, _), node_values) in chunk.iter().zip(result?) {
+                    values[node.usize()] = node_values;
+                }
+            }
+        }
+        Ok(values.swap_remove(root.usize()))
+    }
+
+    // Groups nodes that are reachable from the root by their height.
+    fn levels<G>(&self, bocage: &FrozenBocage<G>, root: NodeHandle) -> Vec<Vec<Plan>>
+    where
+        G: Borrow<InternalGrammar>,
+    {
+        let mut levels: Vec<Vec<Plan>> = vec![];
+        let mut heights: Vec<Option<usize>> = vec![None; bocage.len()];
+        // Nodes are placed after their factors.
+        let mut stack = vec![(root, None)];
+        while let Some((node, alternatives)) = stack.pop() {
+            if heights[node.usize()].is_some() {
+                continue;
+            }
+            match alternatives {
+                None => {
+                    let mut alternatives = bocage.alternatives(node);
+                    if self.mode == EvaluationMode::FirstTree {
+                        alternatives.truncate(1);
+                    }
+                    let factors: Vec<NodeHandle> = factors(&alternatives)
+                        .filter(|factor| heights[factor.usize()].is_none())
+                        .collect();
+                    stack.push((node, Some(alternatives)));
+                    stack.extend(factors.into_iter().map(|factor| (factor, None)));
+                }
+                Some(alternatives) => {
+                    let height = factors(&alternatives)
+                        .map(|factor| heights[factor.usize()].unwrap() + 1)
+                        .max()
+                        .unwrap_or(0);
+                    heights[node.usize()] = Some(height);
+                    if levels.len() <= height {
+                        levels.resize_with(height + 1, Vec::new);
+                    }
+                    levels[height].push((node, alternatives));
+                }
+            }
+        }
+        levels
+    }
+
+    fn evaluate_chunk<V, G>(
+        &self,
+        bocage: &FrozenBocage<G>,
+        chunk: &[Plan],
+        evaluated: &[Vec<V>],
+    ) -> Result<Vec<Vec<V>>, EvaluationError>
+    where
+        FLeaf: Fn(Symbol, u32) -> Result<V, EvaluationError>,
+        FRule: Fn(u32, &[&V]) -> Result<V, EvaluationError>,
+        FNull: Fn(Symbol, &mut Vec<V>) -> Result<(), EvaluationError>,
+        G: Borrow<InternalGrammar>,
+    {
+        let mut result = Vec::with_capacity(chunk.len());
+        for &(node, ref alternatives) in chunk {
+            result.push(evaluate_node(
+                bocage.node(node),
+                &alternatives[..],
+                |factor| evaluated.get(factor.usize()),
+                &mut &self.leaf,
+                &mut &self.rule,
+                &mut &self.null,
+                self.mode,
+            )?);
+        }
+        Ok(result)
+    }
+}
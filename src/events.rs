@@ -6,18 +6,20 @@ use cfg::symbol::Symbol;
 
 use forest::Forest;
 use grammar::{ExternalDottedRule, Event};
-use item::Item;
+use item::{Dot, Item, ItemIndex, Origin};
+use policy::PerformancePolicy;
 use recognizer::Recognizer;
 
 type IterPredictionBitfield<'a> = bit_matrix::row::Iter<'a>;
+type MedialTrace<'a, N, D, O> = Medial<'a, Option<ExternalDottedRule>, N, D, O>;
 
 pub struct PredictedSymbols<'a> {
     pub(in super) iter: IterPredictionBitfield<'a>,
     pub(in super) idx: usize,
 }
 
-pub struct MedialItems<'a, N: 'a> {
-    pub(in super) iter: slice::Iter<'a, Item<N>>,
+pub struct MedialItems<'a, N: 'a, D: 'a = Dot, O: 'a = Origin> {
+    pub(in super) iter: slice::Iter<'a, Item<N, D, O>>,
 }
 
 pub struct Prediction<'a, T: 'a> {
@@ -25,34 +27,34 @@ pub struct Prediction<'a, T: 'a> {
     origin: usize,
 }
 
-pub struct Medial<'a, T: 'a, N: 'a> {
+pub struct Medial<'a, T: 'a, N: 'a, D: 'a = Dot, O: 'a = Origin> {
     events: &'a [T],
-    items: MedialItems<'a, N>,
+    items: MedialItems<'a, N, D, O>,
 }
 
-pub struct Events<'a, N: 'a> {
+pub struct Events<'a, N: 'a, D: 'a = Dot, O: 'a = Origin> {
     iter: Chain<
         Prediction<'a, Event>,
-        Medial<'a, Event, N>
+        Medial<'a, Event, N, D, O>
     >
 }
 
-pub struct Distances<'a, N: 'a> {
+pub struct Distances<'a, N: 'a, D: 'a = Dot, O: 'a = Origin> {
     iter: Chain<
         Prediction<'a, Event>,
-        Medial<'a, Event, N>
+        Medial<'a, Event, N, D, O>
     >
 }
 
-pub struct Trace<'a, N: 'a> {
+pub struct Trace<'a, N: 'a, D: 'a = Dot, O: 'a = Origin> {
     iter: Chain<
         Prediction<'a, Option<ExternalDottedRule>>,
-        Medial<'a, Option<ExternalDottedRule>, N>
+        MedialTrace<'a, N, D, O>
     >
 }
 
-pub struct ExpectedTerminals<'a, N: 'a> {
-    prev_scan_iter: MedialItems<'a, N>,
+pub struct ExpectedTerminals<'a, N: 'a, D: 'a = Dot, O: 'a = Origin> {
+    prev_scan_iter: MedialItems<'a, N, D, O>,
     rhs1: &'a [Option<Symbol>],
 }
 
@@ -71,8 +73,8 @@ impl<'a> Iterator for PredictedSymbols<'a> {
     }
 }
 
-impl<'a, N> Iterator for MedialItems<'a, N> {
-    type Item = &'a Item<N>;
+impl<'a, N, D, O> Iterator for MedialItems<'a, N, D, O> {
+    type Item = &'a Item<N, D, O>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.iter.next()
@@ -92,18 +94,24 @@ impl<'a, T> Iterator for Prediction<'a, T> {
     }
 }
 
-impl<'a, T, L> Iterator for Medial<'a, T, L> {
+impl<'a, T, L, D, O> Iterator for Medial<'a, T, L, D, O>
+    where D: ItemIndex,
+          O: ItemIndex,
+{
     type Item = (&'a T, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
         let events = &self.events;
         self.items.next().map(|ei| {
-            (&events[ei.dot as usize], ei.origin as usize)
+            (&events[ei.dot() as usize], ei.origin() as usize)
         })
     }
 }
 
-impl<'a, L> Iterator for Events<'a, L> {
+impl<'a, L, D, O> Iterator for Events<'a, L, D, O>
+    where D: ItemIndex,
+          O: ItemIndex,
+{
     type Item = u32;
 
     fn next(&mut self) -> Option<u32> {
@@ -116,7 +124,10 @@ impl<'a, L> Iterator for Events<'a, L> {
     }
 }
 
-impl<'a, L> Iterator for Distances<'a, L> {
+impl<'a, L, D, O> Iterator for Distances<'a, L, D, O>
+    where D: ItemIndex,
+          O: ItemIndex,
+{
     type Item = u32;
 
     fn next(&mut self) -> Option<u32> {
@@ -129,7 +140,10 @@ impl<'a, L> Iterator for Distances<'a, L> {
     }
 }
 
-impl<'a, N> Iterator for Trace<'a, N> {
+impl<'a, N, D, O> Iterator for Trace<'a, N, D, O>
+    where D: ItemIndex,
+          O: ItemIndex,
+{
     type Item = (ExternalDottedRule, usize);
 
     fn next(&mut self) -> Option<(ExternalDottedRule, usize)> {
@@ -142,20 +156,24 @@ impl<'a, N> Iterator for Trace<'a, N> {
     }
 }
 
-impl<'a, N> Iterator for ExpectedTerminals<'a, N> {
+impl<'a, N, D, O> Iterator for ExpectedTerminals<'a, N, D, O>
+    where D: ItemIndex,
+          O: ItemIndex,
+{
     type Item = Symbol;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.prev_scan_iter.next().map(|item| {
-            self.rhs1[item.dot as usize].unwrap()
+            self.rhs1[item.dot() as usize].unwrap()
         })
     }
 }
 
-impl<'g, F> Recognizer<'g, F>
+impl<'g, F, P> Recognizer<'g, F, P>
     where F: Forest,
+          P: PerformancePolicy,
 {
-    pub fn trace(&self) -> Trace<F::NodeRef> {
+    pub fn trace(&self) -> Trace<F::NodeRef, P::Dot, P::Origin> {
         let trace = self.grammar.trace();
         let prediction = Prediction {
             iter: self.predicted_symbols().iter.zip(trace[0].iter()),
@@ -170,7 +188,7 @@ impl<'g, F> Recognizer<'g, F>
         }
     }
 
-    pub fn events(&self) -> Events<F::NodeRef> {
+    pub fn events(&self) -> Events<F::NodeRef, P::Dot, P::Origin> {
         let (events_predict, events_flat) = self.grammar.events();
         let prediction = Prediction {
             iter: self.predicted_symbols().iter.zip(events_predict.iter()),
@@ -185,13 +203,13 @@ impl<'g, F> Recognizer<'g, F>
         }
     }
 
-    pub fn minimal_distances(&self) -> Distances<F::NodeRef> {
+    pub fn minimal_distances(&self) -> Distances<F::NodeRef, P::Dot, P::Origin> {
         Distances {
             iter: self.events().iter,
         }
     }
 
-    pub fn expected_terminals(&self) -> ExpectedTerminals<F::NodeRef> {
+    pub fn expected_terminals(&self) -> ExpectedTerminals<F::NodeRef, P::Dot, P::Origin> {
         ExpectedTerminals {
             prev_scan_iter: self.medial_items(),
             rhs1: self.grammar.rhs1(),
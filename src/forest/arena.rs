@@ -0,0 +1,331 @@
+//! A forest that stores nodes in typed arenas, with a value of any type for every leaf.
+//!
+//! Unlike the bocage, this forest is not traversed in place. Nodes are looked up through
+//! `NodeId`, and alternatives of a node are given in terms of external rules.
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use cfg::symbol::Symbol;
+
+use forest::Forest;
+use grammar::InternalGrammar;
+use item::{CompletedItem, Origin, Position};
+
+/// A reference to a node of an `ArenaForest`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum NodeId {
+    Sum(Position),
+    Product(Position),
+    Leaf(Position),
+    Nulling(Symbol),
+}
+
+struct SumNode {
+    nonterminal: Symbol,
+    // Summands are products that were created together.
+    products: Range<Position>,
+}
+
+#[derive(Copy, Clone)]
+struct ProductNode {
+    action: u32,
+    left_factor: NodeId,
+    right_factor: Option<NodeId>,
+}
+
+struct LeafNode<T> {
+    symbol: Symbol,
+    value: T,
+}
+
+/// A view of a node in the forest. All of its symbols are external.
+#[derive(Debug)]
+pub enum ArenaNode<'a, T: 'a> {
+    /// Alternatives of an ambiguous node, which are products.
+    Sum {
+        nonterminal: Symbol,
+        summands: Summands,
+    },
+    /// A product of the internal grammar. Its factors are not unfolded.
+    Product {
+        /// The external rule of the product, or `None` when the product stands for
+        /// a part of an external rule.
+        rule: Option<u32>,
+        left_factor: NodeId,
+        right_factor: Option<NodeId>,
+    },
+    Leaf {
+        symbol: Symbol,
+        value: &'a T,
+    },
+    NullingLeaf {
+        symbol: Symbol,
+    },
+}
+
+/// An iterator over summands of a sum.
+#[derive(Clone, Debug)]
+pub struct Summands {
+    products: Range<Position>,
+}
+
+/// An alternative of a node, which is an external rule together with its factors.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Alternative {
+    /// The external rule.
+    pub rule: u32,
+    /// Nodes for symbols on the rule's RHS.
+    pub factors: Vec<NodeId>,
+}
+
+/// A forest whose leaves hold values of type `T`, such as token text. Sums, products
+/// and leaves are stored in arenas of their own.
+pub struct ArenaForest<T, G> {
+    grammar: G,
+    sums: Vec<SumNode>,
+    products: Vec<ProductNode>,
+    leaves: Vec<LeafNode<T>>,
+    summand_count: Position,
+    // Factors of eliminated nulling symbols.
+    nulling_intermediate: BTreeMap<Symbol, (Symbol, Symbol)>,
+}
+
+impl<T, G> ArenaForest<T, G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    pub fn new(grammar: G) -> Self {
+        let nulling_intermediate = grammar
+            .borrow()
+            .eliminated_nulling_intermediate()
+            .iter()
+            .map(|&(lhs, rhs0, rhs1)| (lhs, (rhs0, rhs1)))
+            .collect();
+        ArenaForest {
+            grammar,
+            sums: vec![],
+            products: vec![],
+            leaves: vec![],
+            summand_count: 0,
+            nulling_intermediate,
+        }
+    }
+
+    /// Returns a view of a node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not belong to this forest.
+    pub fn node(&self, node: NodeId) -> ArenaNode<'_, T> {
+        match node {
+            NodeId::Sum(idx) => {
+                let sum = &self.sums[idx as usize];
+                ArenaNode::Sum {
+                    nonterminal: self.grammar.borrow().to_external(sum.nonterminal),
+                    summands: Summands {
+                        products: sum.products.clone(),
+                    },
+                }
+            }
+            NodeId::Product(idx) => {
+                let product = self.products[idx as usize];
+                ArenaNode::Product {
+                    rule: self.grammar.borrow().external_origin(product.action),
+                    left_factor: product.left_factor,
+                    right_factor: product.right_factor,
+                }
+            }
+            NodeId::Leaf(idx) => {
+                let leaf = &self.leaves[idx as usize];
+                ArenaNode::Leaf {
+                    symbol: leaf.symbol,
+                    value: &leaf.value,
+                }
+            }
+            NodeId::Nulling(symbol) => ArenaNode::NullingLeaf { symbol },
+        }
+    }
+
+    /// Returns the external symbol of a node.
+    pub fn symbol(&self, node: NodeId) -> Symbol {
+        let grammar = self.grammar.borrow();
+        match node {
+            NodeId::Sum(idx) => grammar.to_external(self.sums[idx as usize].nonterminal),
+            NodeId::Product(idx) => {
+                let action = self.products[idx as usize].action;
+                grammar.to_external(grammar.get_lhs(action))
+            }
+            NodeId::Leaf(idx) => self.leaves[idx as usize].symbol,
+            NodeId::Nulling(symbol) => symbol,
+        }
+    }
+
+    /// Returns the value of a leaf.
+    pub fn leaf_value(&self, node: NodeId) -> Option<&T> {
+        match node {
+            NodeId::Leaf(idx) => Some(&self.leaves[idx as usize].value),
+            _ => None,
+        }
+    }
+
+    /// Returns alternatives of a node in terms of external rules. Rules of the internal
+    /// grammar are unfolded, so factors are sums and products of external rules, leaves
+    /// and nulling leaves. Factors of a product that are ambiguous within a rule, such as
+    /// a part of a long RHS, give one alternative for every combination.
+    ///
+    /// Leaves have no alternatives.
+    pub fn alternatives(&self, node: NodeId) -> Vec<Alternative> {
+        let products = match node {
+            NodeId::Sum(idx) => self.sums[idx as usize].products.clone(),
+            NodeId::Product(idx) => idx..idx + 1,
+            NodeId::Leaf(_) | NodeId::Nulling(_) => return vec![],
+        };
+        let mut result = vec![];
+        for idx in products {
+            let action = self.products[idx as usize].action;
+            if let Some(rule) = self.grammar.borrow().external_origin(action) {
+                for factors in self.product_factors(idx) {
+                    result.push(Alternative { rule, factors });
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the number of nodes in the forest, without nulling leaves.
+    pub fn len(&self) -> usize {
+        self.sums.len() + self.products.len() + self.leaves.len()
+    }
+
+    /// Checks whether the forest has no nodes, except nulling leaves.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Lists of factors of a product, with nulling symbols that were eliminated from its rule.
+    fn product_factors(&self, idx: Position) -> Vec<Vec<NodeId>> {
+        let product = self.products[idx as usize];
+        let (left, right) = match (
+            product.right_factor,
+            self.grammar.borrow().nulling(product.action),
+        ) {
+            (None, Some((symbol, true))) => (product.left_factor, Some(NodeId::Nulling(symbol))),
+            (None, Some((symbol, false))) => (NodeId::Nulling(symbol), Some(product.left_factor)),
+            (right, _) => (product.left_factor, right),
+        };
+        let left = self.unfold(left);
+        match right {
+            Some(right) => concat(left, self.unfold(right)),
+            None => left,
+        }
+    }
+
+    // Lists of external factors that a factor of an internal rule stands for.
+    fn unfold(&self, node: NodeId) -> Vec<Vec<NodeId>> {
+        match node {
+            NodeId::Product(idx) if self.is_transparent(idx) => self.product_factors(idx),
+            NodeId::Sum(idx) => {
+                let products = self.sums[idx as usize].products.clone();
+                if self.is_transparent(products.start) {
+                    products.flat_map(|idx| self.product_factors(idx)).collect()
+                } else {
+                    vec![vec![node]]
+                }
+            }
+            NodeId::Nulling(symbol) => match self.nulling_intermediate.get(&symbol) {
+                Some(&(rhs0, rhs1)) => concat(
+                    self.unfold(NodeId::Nulling(rhs0)),
+                    self.unfold(NodeId::Nulling(rhs1)),
+                ),
+                None => vec![vec![node]],
+            },
+            _ => vec![vec![node]],
+        }
+    }
+
+    fn is_transparent(&self, product: Position) -> bool {
+        let action = self.products[product as usize].action;
+        self.grammar.borrow().external_origin(action).is_none()
+    }
+}
+
+// Joins every list on the left with every list on the right.
+fn concat(left: Vec<Vec<NodeId>>, right: Vec<Vec<NodeId>>) -> Vec<Vec<NodeId>> {
+    let mut result = Vec::with_capacity(left.len() * right.len());
+    for l in &left {
+        for r in &right {
+            let mut factors = l.clone();
+            factors.extend(r.iter().cloned());
+            result.push(factors);
+        }
+    }
+    result
+}
+
+impl Iterator for Summands {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        self.products.next().map(NodeId::Product)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.products.size_hint()
+    }
+}
+
+impl<T, G> Forest for ArenaForest<T, G> {
+    type NodeRef = NodeId;
+    type LeafValue = T;
+
+    const FOREST_BYTES_PER_RECOGNIZER_BYTE: usize = 4;
+
+    #[inline]
+    fn begin_sum(&mut self) {
+        // nothing to do
+    }
+
+    #[inline]
+    fn push_summand(&mut self, item: CompletedItem<Self::NodeRef>) {
+        self.products.push(ProductNode {
+            action: item.dot,
+            left_factor: item.left_node,
+            right_factor: item.right_node,
+        });
+        self.summand_count += 1;
+    }
+
+    #[inline]
+    fn sum(&mut self, lhs_sym: Symbol, _origin: Origin) -> Self::NodeRef {
+        let end = self.products.len() as Position;
+        let result = match self.summand_count {
+            0 => unreachable!("a sum without summands"),
+            1 => NodeId::Product(end - 1),
+            count => {
+                self.sums.push(SumNode {
+                    nonterminal: lhs_sym,
+                    products: end - count..end,
+                });
+                NodeId::Sum(self.sums.len() as Position - 1)
+            }
+        };
+        self.summand_count = 0;
+        result
+    }
+
+    #[inline]
+    fn leaf(&mut self, token: Symbol, _pos: Origin, value: Self::LeafValue) -> Self::NodeRef {
+        self.leaves.push(LeafNode {
+            symbol: token,
+            value,
+        });
+        NodeId::Leaf(self.leaves.len() as Position - 1)
+    }
+
+    #[inline]
+    fn nulling(&self, token: Symbol) -> Self::NodeRef {
+        NodeId::Nulling(token)
+    }
+}
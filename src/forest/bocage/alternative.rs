@@ -0,0 +1,186 @@
+//! Alternatives of live nodes, as traversal visits them.
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+use bit_vec::BitVec;
+use cfg::symbol::Symbol;
+
+use forest::bocage::node::Node::*;
+use forest::bocage::node::{Node, NULL_ACTION};
+use forest::node_handle::NodeHandle;
+use forest::unfold::{unfold, Step};
+use forest::Bocage;
+use grammar::InternalGrammar;
+use item::Position;
+
+// A live product of an external rule, with its factors after unfolding.
+pub(crate) struct Alternative {
+    pub(crate) action: u32,
+    pub(crate) factors: Vec<(Symbol, NodeHandle)>,
+}
+
+// Nodes of a marked bocage, together with what the order selected.
+pub(crate) struct MarkedNodes<'a, F> {
+    pub(crate) expand: F,
+    pub(crate) liveness: &'a BitVec,
+    pub(crate) selections: &'a BTreeMap<NodeHandle, Vec<usize>>,
+    pub(crate) grammar: &'a InternalGrammar,
+}
+
+impl<'a, F> MarkedNodes<'a, F>
+where
+    F: Fn(NodeHandle) -> Node,
+{
+    pub(crate) fn is_alive(&self, node: NodeHandle) -> bool {
+        self.liveness.get(node.usize()).unwrap_or(false)
+    }
+
+    // Lists live alternatives of a sum or a product. Leaves have no alternatives.
+    pub(crate) fn alternatives(&self, node: NodeHandle) -> Vec<Alternative> {
+        let summands = match (self.expand)(node) {
+            Sum { count, .. } => (node.usize() + 1)..(node.usize() + 1 + count as usize),
+            Product { .. } => node.usize()..(node.usize() + 1),
+            NullingLeaf { .. } | Evaluated { .. } => return vec![],
+        };
+        let mut result = vec![];
+        for i in summands {
+            let handle = NodeHandle(i as Position);
+            if !self.is_alive(handle) {
+                continue;
+            }
+            let (action, left_factor, right_factor) = match (self.expand)(handle) {
+                Product {
+                    action,
+                    left_factor,
+                    right_factor,
+                } => (action, left_factor, right_factor),
+                _ => unreachable!(),
+            };
+            if let Some(action) = self.grammar.external_origin(action) {
+                for factors in self.selected_unfoldings(handle, left_factor, right_factor) {
+                    result.push(Alternative { action, factors });
+                }
+            }
+        }
+        result
+    }
+
+    // Flattens factors of a product in every way, and keeps factors that the order selected.
+    pub(crate) fn selected_unfoldings(
+        &self,
+        product: NodeHandle,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+    ) -> Vec<Vec<(Symbol, NodeHandle)>> {
+        let unfoldings = self.unfoldings(left, right, &mut vec![]);
+        match self.selections.get(&product) {
+            Some(selection) => unfoldings
+                .into_iter()
+                .map(|factors| selection.iter().map(|&idx| factors[idx]).collect())
+                .collect(),
+            None => unfoldings,
+        }
+    }
+
+    // Flattens factors of transparent products and of live summands of transparent sums,
+    // in every way. Handles of unfolded nodes are pushed to `unfolded`.
+    pub(crate) fn unfoldings(
+        &self,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+        unfolded: &mut Vec<NodeHandle>,
+    ) -> Vec<Vec<(Symbol, NodeHandle)>> {
+        unfold(left, right, |handle| {
+            let node = (self.expand)(handle);
+            let summands = match node {
+                Sum { count, .. } => (handle.usize() + 1)..(handle.usize() + 1 + count as usize),
+                _ => handle.usize()..(handle.usize() + 1),
+            };
+            if !self.is_transparent_node(handle) {
+                return Step::Factor(self.factor_symbol(node));
+            }
+            unfolded.push(handle);
+            let mut pairs = vec![];
+            for i in summands {
+                let summand = NodeHandle(i as Position);
+                if summand != handle && !self.is_alive(summand) {
+                    continue;
+                }
+                if let Product {
+                    left_factor,
+                    right_factor,
+                    ..
+                } = (self.expand)(summand)
+                {
+                    unfolded.push(summand);
+                    pairs.push((left_factor, right_factor));
+                }
+            }
+            Step::Unfold(pairs)
+        })
+    }
+
+    // Checks whether factors of a node are unfolded in place of the node. A sum is
+    // transparent when its summands are.
+    fn is_transparent_node(&self, node: NodeHandle) -> bool {
+        let summand = match (self.expand)(node) {
+            Sum { .. } => (self.expand)(NodeHandle(node.0 + 1)),
+            other => other,
+        };
+        match summand {
+            Product { action, .. } => self.is_transparent(action),
+            _ => false,
+        }
+    }
+
+    fn is_transparent(&self, action: u32) -> bool {
+        action == NULL_ACTION || self.grammar.external_origin(action).is_none()
+    }
+
+    fn factor_symbol(&self, node: Node) -> Symbol {
+        match node {
+            Sum { nonterminal, .. } => nonterminal,
+            Product { action, .. } => self.grammar.get_lhs(action),
+            NullingLeaf { symbol } | Evaluated { symbol, .. } => symbol,
+        }
+    }
+}
+
+impl<G> Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    pub(crate) fn is_alive(&self, node: NodeHandle) -> bool {
+        self.gc.liveness.get(node.usize()).unwrap_or(false)
+    }
+
+    // Lists live alternatives of a sum or a product, in the same way as traversal does.
+    pub(crate) fn alternatives(&self, node: NodeHandle) -> Vec<Alternative> {
+        self.marked_nodes(&self.gc.liveness).alternatives(node)
+    }
+
+    // Flattens factors of a product in every way and keeps factors that the order
+    // selected, in the same way as traversal does.
+    pub(crate) fn selected_unfoldings(
+        &self,
+        product: NodeHandle,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+    ) -> Vec<Vec<(Symbol, NodeHandle)>> {
+        self.marked_nodes(&self.gc.liveness)
+            .selected_unfoldings(product, left, right)
+    }
+
+    pub(crate) fn marked_nodes<'a>(
+        &'a self,
+        liveness: &'a BitVec,
+    ) -> MarkedNodes<'a, impl Fn(NodeHandle) -> Node + 'a> {
+        MarkedNodes {
+            expand: move |handle: NodeHandle| self.graph[handle.usize()].expand(),
+            liveness,
+            selections: &self.gc.selections,
+            grammar: self.grammar.borrow(),
+        }
+    }
+}
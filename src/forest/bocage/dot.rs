@@ -0,0 +1,79 @@
+use std::borrow::Borrow;
+use std::io::{self, Write};
+
+use bit_vec::BitVec;
+
+use forest::bocage::node::Node::*;
+use forest::dot::{symbol_label, DotWriter};
+use forest::node_handle::NodeHandle;
+use forest::Bocage;
+use grammar::InternalGrammar;
+use item::Position;
+
+impl<G> Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Writes the live part of the forest under a node as a DOT graph. Liveness must
+    /// be marked for this node. Sums are labeled with symbol names, products with
+    /// external rule IDs and leaves with symbol names and values.
+    pub fn write_dot<W: Write>(&self, root: NodeHandle, out: &mut W) -> io::Result<()> {
+        let grammar = self.grammar.borrow();
+        let is_alive = |i: usize| self.gc.liveness.get(i).unwrap_or(false);
+        let mut writer = DotWriter::new(out)?;
+        let mut visited = BitVec::from_elem(self.graph.len(), false);
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if visited[node.usize()] {
+                continue;
+            }
+            visited.set(node.usize(), true);
+            let summands = match self.graph[node.usize()].expand() {
+                Sum { nonterminal, count } => {
+                    let start = node.usize() + 1;
+                    let alive: Vec<usize> = (start..start + count as usize)
+                        .filter(|&i| is_alive(i))
+                        .collect();
+                    let label = symbol_label(grammar, grammar.to_external(nonterminal));
+                    writer.sum(node, &label, alive.len() > 1)?;
+                    for &i in &alive {
+                        writer.summand_edge(node, NodeHandle(i as Position))?;
+                    }
+                    alive
+                }
+                _ => vec![node.usize()],
+            };
+            for i in summands {
+                let summand = NodeHandle(i as Position);
+                match self.graph[i].expand() {
+                    Product {
+                        action,
+                        left_factor,
+                        right_factor,
+                    } => {
+                        let rule = if self.is_transparent(action) {
+                            None
+                        } else {
+                            grammar.external_origin(action)
+                        };
+                        writer.product(summand, rule)?;
+                        for factor in Some(left_factor).into_iter().chain(right_factor) {
+                            writer.factor_edge(summand, factor)?;
+                            stack.push(factor);
+                        }
+                    }
+                    Evaluated { symbol, values } => {
+                        let label = format!("{} = {}", symbol_label(grammar, symbol), values);
+                        writer.leaf(summand, &label)?;
+                    }
+                    NullingLeaf { symbol } => {
+                        let label = format!("{} = ()", symbol_label(grammar, symbol));
+                        writer.leaf(summand, &label)?;
+                    }
+                    Sum { .. } => unreachable!(),
+                }
+            }
+        }
+        writer.finish()
+    }
+}
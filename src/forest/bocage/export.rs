@@ -0,0 +1,289 @@
+use std::borrow::Borrow;
+use std::io::{self, Write};
+use std::slice;
+
+use bit_vec::BitVec;
+use cfg::symbol::Symbol;
+
+use forest::bocage::node::Node::*;
+use forest::bocage::trees::ParseTree;
+use forest::node_handle::NodeHandle;
+use forest::{Bocage, Span};
+use grammar::InternalGrammar;
+use item::Position;
+
+impl<G> Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Writes a parse tree as JSON. Nodes are objects with external rule IDs, LHS symbols
+    /// and children. Leaves are objects with symbols and values. Symbol names are written
+    /// where the grammar has them, and spans where they are recorded.
+    pub fn write_tree_json<W: Write>(&self, tree: &ParseTree, out: &mut W) -> io::Result<()> {
+        let mut stack: Vec<(slice::Iter<ParseTree>, bool)> = vec![];
+        self.open_json(tree, out, &mut stack)?;
+        while let Some(&mut (ref mut children, ref mut first)) = stack.last_mut() {
+            match children.next() {
+                Some(child) => {
+                    if !*first {
+                        write!(out, ",")?;
+                    }
+                    *first = false;
+                    self.open_json(child, out, &mut stack)?;
+                }
+                None => {
+                    stack.pop();
+                    write!(out, "]}}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Writes a leaf, or the beginning of a node whose children are pushed on the stack.
+    fn open_json<'t, W: Write>(
+        &self,
+        tree: &'t ParseTree,
+        out: &mut W,
+        stack: &mut Vec<(slice::Iter<'t, ParseTree>, bool)>,
+    ) -> io::Result<()> {
+        match *tree {
+            ParseTree::Node {
+                action,
+                lhs,
+                span,
+                ref children,
+            } => {
+                write!(out, "{{\"rule\":{},", action)?;
+                self.write_json_symbol(lhs, out)?;
+                write_json_span(span, out)?;
+                write!(out, ",\"children\":[")?;
+                stack.push((children.iter(), true));
+            }
+            ParseTree::Leaf {
+                symbol,
+                value,
+                span,
+            } => {
+                write!(out, "{{")?;
+                self.write_json_symbol(symbol, out)?;
+                write!(out, ",\"value\":{}", value)?;
+                write_json_span(span, out)?;
+                write!(out, "}}")?;
+            }
+            ParseTree::Nulling { symbol } => {
+                write!(out, "{{")?;
+                self.write_json_symbol(symbol, out)?;
+                write!(out, ",\"nulling\":true}}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a parse tree as an S-expression. Nodes are written as
+    /// `(lhs :rule 0 :span (0 3) children...)`, leaves as `(symbol :value 1 :span (1 2))`
+    /// and nulling symbols as `(symbol)`. Symbols are written with their names where
+    /// the grammar has them.
+    pub fn write_tree_sexp<W: Write>(&self, tree: &ParseTree, out: &mut W) -> io::Result<()> {
+        let mut stack: Vec<slice::Iter<ParseTree>> = vec![];
+        self.open_sexp(tree, out, &mut stack)?;
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(child) => {
+                    write!(out, " ")?;
+                    self.open_sexp(child, out, &mut stack)?;
+                }
+                None => {
+                    stack.pop();
+                    write!(out, ")")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn open_sexp<'t, W: Write>(
+        &self,
+        tree: &'t ParseTree,
+        out: &mut W,
+        stack: &mut Vec<slice::Iter<'t, ParseTree>>,
+    ) -> io::Result<()> {
+        match *tree {
+            ParseTree::Node {
+                action,
+                lhs,
+                span,
+                ref children,
+            } => {
+                write!(out, "(")?;
+                self.write_sexp_symbol(lhs, out)?;
+                write!(out, " :rule {}", action)?;
+                write_sexp_span(span, out)?;
+                stack.push(children.iter());
+            }
+            ParseTree::Leaf {
+                symbol,
+                value,
+                span,
+            } => {
+                write!(out, "(")?;
+                self.write_sexp_symbol(symbol, out)?;
+                write!(out, " :value {}", value)?;
+                write_sexp_span(span, out)?;
+                write!(out, ")")?;
+            }
+            ParseTree::Nulling { symbol } => {
+                write!(out, "(")?;
+                self.write_sexp_symbol(symbol, out)?;
+                write!(out, ")")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the live part of the shared forest under a node as JSON. The forest is
+    /// an object with the root ID and a list of nodes, which refer to each other by IDs.
+    /// Liveness must be marked for this node.
+    pub fn write_forest_json<W: Write>(&self, root: NodeHandle, out: &mut W) -> io::Result<()> {
+        let grammar = self.grammar.borrow();
+        let is_alive = |i: usize| self.gc.liveness.get(i).unwrap_or(false);
+        let mut visited = BitVec::from_elem(self.graph.len(), false);
+        let mut stack = vec![root];
+        let mut first = true;
+        write!(out, "{{\"root\":{},\"nodes\":[", root.0)?;
+        while let Some(node) = stack.pop() {
+            if visited[node.usize()] {
+                continue;
+            }
+            visited.set(node.usize(), true);
+            let summands = match self.graph[node.usize()].expand() {
+                Sum { nonterminal, count } => {
+                    let start = node.usize() + 1;
+                    let alive: Vec<usize> = (start..start + count as usize)
+                        .filter(|&i| is_alive(i))
+                        .collect();
+                    if !first {
+                        write!(out, ",")?;
+                    }
+                    write!(out, "{{\"id\":{},\"kind\":\"sum\",", node.0)?;
+                    self.write_json_symbol(grammar.to_external(nonterminal), out)?;
+                    write_json_span(self.span(node), out)?;
+                    write!(out, ",\"summands\":")?;
+                    write_json_list(alive.iter().cloned(), out)?;
+                    write!(out, "}}")?;
+                    first = false;
+                    alive
+                }
+                _ => vec![node.usize()],
+            };
+            for i in summands {
+                if !first {
+                    write!(out, ",")?;
+                }
+                first = false;
+                write!(out, "{{\"id\":{},", i)?;
+                match self.graph[i].expand() {
+                    Product {
+                        action,
+                        left_factor,
+                        right_factor,
+                    } => {
+                        write!(out, "\"kind\":\"product\",\"rule\":")?;
+                        match grammar.external_origin(action) {
+                            Some(rule) if !self.is_transparent(action) => write!(out, "{}", rule)?,
+                            _ => write!(out, "null")?,
+                        }
+                        write_json_span(self.span(NodeHandle(i as Position)), out)?;
+                        write!(out, ",\"factors\":")?;
+                        let factors = Some(left_factor).into_iter().chain(right_factor);
+                        write_json_list(factors.clone().map(|factor| factor.usize()), out)?;
+                        stack.extend(factors);
+                    }
+                    Evaluated { symbol, values } => {
+                        write!(out, "\"kind\":\"leaf\",")?;
+                        self.write_json_symbol(symbol, out)?;
+                        write!(out, ",\"value\":{}", values)?;
+                        write_json_span(self.span(NodeHandle(i as Position)), out)?;
+                    }
+                    NullingLeaf { symbol } => {
+                        write!(out, "\"kind\":\"nulling\",")?;
+                        self.write_json_symbol(symbol, out)?;
+                    }
+                    Sum { .. } => unreachable!(),
+                }
+                write!(out, "}}")?;
+            }
+        }
+        write!(out, "]}}")
+    }
+
+    fn write_json_symbol<W: Write>(&self, symbol: Symbol, out: &mut W) -> io::Result<()> {
+        write!(out, "\"symbol\":{}", symbol.usize())?;
+        if let Some(name) = self.grammar.borrow().symbol_name(symbol) {
+            write!(out, ",\"name\":")?;
+            write_json_string(name, out)?;
+        }
+        Ok(())
+    }
+
+    // Names that are not plain atoms are quoted, as well as IDs of symbols without names.
+    fn write_sexp_symbol<W: Write>(&self, symbol: Symbol, out: &mut W) -> io::Result<()> {
+        match self.grammar.borrow().symbol_name(symbol) {
+            Some(name) if is_sexp_atom(name) => write!(out, "{}", name),
+            Some(name) => write_json_string(name, out),
+            None => write!(out, "\"#{}\"", symbol.usize()),
+        }
+    }
+}
+
+fn write_json_span<W: Write>(span: Option<Span>, out: &mut W) -> io::Result<()> {
+    match span {
+        Some(span) => write!(out, ",\"span\":[{},{}]", span.start, span.end),
+        None => Ok(()),
+    }
+}
+
+fn write_sexp_span<W: Write>(span: Option<Span>, out: &mut W) -> io::Result<()> {
+    match span {
+        Some(span) => write!(out, " :span ({} {})", span.start, span.end),
+        None => Ok(()),
+    }
+}
+
+fn write_json_list<I, W>(ids: I, out: &mut W) -> io::Result<()>
+where
+    I: Iterator<Item = usize>,
+    W: Write,
+{
+    write!(out, "[")?;
+    for (i, id) in ids.enumerate() {
+        if i != 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "{}", id)?;
+    }
+    write!(out, "]")
+}
+
+fn write_json_string<W: Write>(string: &str, out: &mut W) -> io::Result<()> {
+    write!(out, "\"")?;
+    for ch in string.chars() {
+        match ch {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32)?,
+            ch => write!(out, "{}", ch)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+fn is_sexp_atom(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || "_-+*/<>=!?.".contains(ch))
+}
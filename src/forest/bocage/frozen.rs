@@ -0,0 +1,94 @@
+//! An immutable bocage that can be shared between threads.
+//!
+//! Nodes of a `Bocage` are kept in cells, because they are updated during evaluation.
+//! After liveness is marked, the bocage may be frozen. Its nodes are expanded, and the
+//! frozen bocage is `Send + Sync` for any grammar reference that is.
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+use bit_vec::BitVec;
+
+use forest::bocage::alternative::{Alternative, MarkedNodes};
+use forest::bocage::node::Node;
+use forest::node_handle::NodeHandle;
+use forest::span::Spans;
+use forest::{Bocage, Span};
+use grammar::InternalGrammar;
+
+/// A bocage whose nodes can no longer change.
+pub struct FrozenBocage<G> {
+    nodes: Vec<Node>,
+    liveness: BitVec,
+    // Factors selected by the order for products.
+    selections: BTreeMap<NodeHandle, Vec<usize>>,
+    spans: Option<Spans>,
+    grammar: G,
+}
+
+impl<G> Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Turns this bocage into an immutable forest. Liveness must be marked, and nodes
+    /// must not be evaluated yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if liveness is not marked.
+    pub fn freeze(self) -> FrozenBocage<G> {
+        assert_eq!(
+            self.gc.liveness.len(),
+            self.graph.len(),
+            "liveness must be marked before freezing"
+        );
+        FrozenBocage {
+            nodes: self.graph.iter().map(|node| node.expand()).collect(),
+            liveness: self.gc.liveness,
+            selections: self.gc.selections,
+            spans: self.spans,
+            grammar: self.grammar,
+        }
+    }
+}
+
+impl<G> FrozenBocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Returns a node.
+    pub fn node(&self, node: NodeHandle) -> Node {
+        self.nodes[node.usize()]
+    }
+
+    /// Checks whether a node was marked alive before freezing.
+    pub fn is_alive(&self, node: NodeHandle) -> bool {
+        self.liveness.get(node.usize()).unwrap_or(false)
+    }
+
+    /// Returns the input range covered by a node, if its span was recorded.
+    pub fn span(&self, node: NodeHandle) -> Option<Span> {
+        self.spans.as_ref().and_then(|spans| spans.get(node))
+    }
+
+    /// Returns the number of nodes, including nulling leaves.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Checks whether there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Lists live alternatives of a sum or a product, in the same way as traversal does.
+    pub(crate) fn alternatives(&self, node: NodeHandle) -> Vec<Alternative> {
+        MarkedNodes {
+            expand: |handle: NodeHandle| self.nodes[handle.usize()],
+            liveness: &self.liveness,
+            selections: &self.selections,
+            grammar: self.grammar.borrow(),
+        }
+        .alternatives(node)
+    }
+}
@@ -1,18 +1,32 @@
+pub(crate) mod alternative;
+pub mod dot;
+pub mod export;
+pub mod frozen;
 pub mod node;
 pub mod order;
+pub mod prune;
+#[cfg(feature = "sample")]
+pub mod sample;
 pub mod traverse;
+pub mod trees;
 
 use std::borrow::Borrow;
-use std::hint;
+use std::cell::Cell;
+use std::collections::BTreeMap;
 
 use bit_vec::BitVec;
 use cfg::symbol::Symbol;
 use ref_slice::ref_slice;
 
-use forest::node_handle::NodeHandle;
-use forest::Forest;
+use forest::node_handle::{NodeHandle, NULL_HANDLE};
+use forest::order::{check_selection, is_selected};
+use forest::span::{Span, Spans};
+use forest::tree_count::{count_nulling, nulling_count, TreeCount};
+use forest::unfold::{unfold, Step};
+use forest::{CollectGarbage, Forest};
 use grammar::InternalGrammar;
-use item::CompletedItem;
+use invariant;
+use item::{CompletedItem, Origin, Position};
 
 use self::node::Node::*;
 use self::node::{CompactNode, Node, NULL_ACTION};
@@ -23,12 +37,19 @@ pub struct Bocage<G> {
     pub(crate) gc: MarkAndSweep,
     pub(crate) grammar: G,
     pub(crate) summand_count: u32,
+    pub(crate) spans: Option<Spans>,
+    // Set once traversal overwrites a node with its evaluation result.
+    pub(crate) evaluated: Cell<bool>,
 }
 
 pub(crate) struct MarkAndSweep {
     pub(crate) liveness: BitVec,
-    // List for DFS and/or maybe relocation of stuff in the future.
+    // List for DFS.
     pub(crate) dfs: Vec<NodeHandle>,
+    // New locations of nodes kept by the latest sweep.
+    pub(crate) relocation: Vec<NodeHandle>,
+    // Factors selected by the order for products.
+    pub(crate) selections: BTreeMap<NodeHandle, Vec<usize>>,
 }
 
 impl<G> Bocage<G>
@@ -45,9 +66,13 @@ where
             gc: MarkAndSweep {
                 liveness: BitVec::with_capacity(graph_cap),
                 dfs: Vec::with_capacity(dfs_cap),
+                relocation: vec![],
+                selections: BTreeMap::new(),
             },
             grammar,
             summand_count: 0,
+            spans: None,
+            evaluated: Cell::new(false),
         };
         result.initialize_nulling();
         result
@@ -81,36 +106,304 @@ where
         self.grammar.borrow().max_nulling_symbol().unwrap_or(0)
     }
 
+    /// Returns the number of nodes, including leaves for nulling symbols.
+    pub fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Checks whether there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.graph.is_empty()
+    }
+
+    /// Records spans of sum and leaf nodes that are created from now on.
+    pub fn enable_spans(&mut self) {
+        if self.spans.is_none() {
+            self.spans = Some(Spans::new());
+        }
+    }
+
+    /// Returns the input range covered by a node, if its span was recorded.
+    pub fn span(&self, node: NodeHandle) -> Option<Span> {
+        self.spans.as_ref().and_then(|spans| spans.get(node))
+    }
+
+    /// Counts parse trees that are derived from the given node. All alternatives are
+    /// counted, regardless of the order, and so are derivations of nulling symbols. Nodes
+    /// must not be evaluated yet.
+    ///
+    /// Returns `None` if a nulling symbol has infinitely many derivations through cyclic
+    /// rules, and `C` has no count for them. Counts of type `u64` saturate instead.
+    pub fn count_trees<C: TreeCount>(&self, root: NodeHandle) -> Option<C> {
+        let mut counts: Vec<Option<C>> = vec![None; self.graph.len()];
+        // Nodes are counted after their factors.
+        let nulling: Vec<Option<C>> = count_nulling(self.grammar.borrow());
+        let mut stack = vec![(root, false)];
+        while let Some((node, factors_counted)) = stack.pop() {
+            if counts[node.usize()].is_some() {
+                continue;
+            }
+            if factors_counted {
+                let mut count = C::zero();
+                for summand in Bocage::<G>::summands(&self.graph, node) {
+                    let product = match summand.expand() {
+                        Product {
+                            left_factor,
+                            right_factor,
+                            action,
+                        } => {
+                            let left = counts[left_factor.usize()].as_ref().unwrap();
+                            match right_factor {
+                                Some(right) => left.mul(counts[right.usize()].as_ref().unwrap()),
+                                // The omitted nulling symbol is added when marking.
+                                None => match self.grammar.borrow().nulling(action) {
+                                    Some((symbol, _)) => {
+                                        left.mul(&nulling_count(&nulling[..], symbol)?)
+                                    }
+                                    None => left.clone(),
+                                },
+                            }
+                        }
+                        NullingLeaf { symbol } => nulling_count(&nulling[..], symbol)?,
+                        // A token has a single derivation.
+                        _ => C::one(),
+                    };
+                    count = count.add(&product);
+                }
+                counts[node.usize()] = Some(count);
+            } else {
+                stack.push((node, true));
+                for summand in Bocage::<G>::summands(&self.graph, node) {
+                    if let Product {
+                        left_factor,
+                        right_factor,
+                        ..
+                    } = summand.expand()
+                    {
+                        for factor in Some(left_factor).into_iter().chain(right_factor) {
+                            if counts[factor.usize()].is_none() {
+                                stack.push((factor, false));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        counts[root.usize()].take()
+    }
+
+    /// Appends all nodes of another bocage, which must be built for the same grammar.
+    /// Returns the handle of `root` relocated into this bocage.
+    ///
+    /// If both bocages record spans, spans of the other bocage are shifted by `offset`,
+    /// which is the location where its input starts.
+    pub fn append<H>(
+        &mut self,
+        other: &Bocage<H>,
+        root: NodeHandle,
+        offset: Position,
+    ) -> NodeHandle {
+        // Leaves for nulling symbols are shared by both bocages.
+        let nulling_end = self.nulling_symbol_count() + 1;
+        let base = self.graph.len() - nulling_end;
+        let relocate = |handle: NodeHandle| {
+            if handle.usize() < nulling_end {
+                handle
+            } else {
+                NodeHandle((handle.usize() + base) as Position)
+            }
+        };
+        for node in &other.graph[nulling_end..] {
+            let node = match node.expand() {
+                Product {
+                    action,
+                    left_factor,
+                    right_factor,
+                } => Product {
+                    action,
+                    left_factor: relocate(left_factor),
+                    right_factor: right_factor.map(relocate),
+                },
+                other => other,
+            };
+            self.graph.push(node.compact());
+        }
+        if let (Some(spans), Some(other_spans)) = (self.spans.as_mut(), other.spans.as_ref()) {
+            spans.append(other_spans, relocate, offset);
+        }
+        relocate(root)
+    }
+
     #[inline]
     pub fn mark_alive<O: Order>(&mut self, root: NodeHandle, mut order: O) {
         self.gc.liveness.clear();
         self.gc.liveness.grow(self.graph.len(), false);
+        self.gc.selections.clear();
         self.gc.dfs.push(root);
         while let Some(node) = self.gc.dfs.pop() {
             self.gc.liveness.set(node.usize(), true);
-            let summands = Bocage::<G>::summands(&self.graph, node);
-            let summands = order.sum(summands);
-            for summand in summands {
-                self.postprocess_product_tree_node(summand);
-                // TODO: use order for products.
-                self.gc.dfs_queue_factors(summand);
+            let selection = self.select_summands(node, &mut order);
+            let first_summand = self.first_summand(node);
+            let count = Bocage::<G>::summands(&self.graph, node).len();
+            for idx in 0..count {
+                // Summands that are left out by the order stay dead.
+                if !is_selected(selection.as_deref(), idx) {
+                    continue;
+                }
+                let handle = NodeHandle(first_summand.0 + idx as Position);
+                self.gc.liveness.set(handle.usize(), true);
+                match self.graph[handle.usize()].expand() {
+                    Product {
+                        action,
+                        left_factor,
+                        right_factor,
+                    } if O::SELECTS_FACTORS && !self.is_transparent(action) => {
+                        self.mark_factors(handle, action, left_factor, right_factor, &mut order);
+                    }
+                    _ => {
+                        self.gc.dfs_queue_factors(&self.graph[handle.usize()]);
+                    }
+                }
             }
         }
     }
 
+    // Applies the order to summands of a node. Returns indices of summands that are
+    // kept, or `None` when all are kept.
+    fn select_summands<O: Order>(&self, node: NodeHandle, order: &mut O) -> Option<Vec<usize>> {
+        let summands = Bocage::<G>::summands(&self.graph, node);
+        for summand in summands {
+            self.postprocess_product_tree_node(summand);
+        }
+        if !O::SELECTS_SUMMANDS {
+            return None;
+        }
+        let first_factor_len = |idx: usize| self.first_factor_len(summands[idx].expand());
+        let selection = order.sum(summands, &first_factor_len);
+        if let Some(ref selection) = selection {
+            check_selection(&selection[..], summands.len());
+        }
+        selection
+    }
+
+    #[inline]
+    fn first_summand(&self, node: NodeHandle) -> NodeHandle {
+        match self.graph[node.usize()].expand() {
+            Sum { .. } => NodeHandle(node.0 + 1),
+            _ => node,
+        }
+    }
+
+    // Returns how much input the first factor of a summand spans.
+    fn first_factor_len(&self, summand: Node) -> Option<Position> {
+        let spans = self.spans.as_ref()?;
+        let factor = match summand {
+            Product { left_factor, .. } => left_factor,
+            _ => return Some(0),
+        };
+        match self.graph[factor.usize()].expand() {
+            NullingLeaf { .. } | Product { action: NULL_ACTION, .. } => Some(0),
+            _ => spans.get(factor).map(|span| span.end - span.start),
+        }
+    }
+
+    // Applies the order to factors of a product of an external rule, and queues factors
+    // that it keeps.
+    fn mark_factors<O: Order>(
+        &mut self,
+        product: NodeHandle,
+        action: u32,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+        order: &mut O,
+    ) {
+        let mut transparent = vec![];
+        let unfoldings = unfold(left, right, |handle| {
+            let node = &self.graph[handle.usize()];
+            self.postprocess_product_tree_node(node);
+            if !self.is_transparent_node(handle) {
+                return Step::Factor(self.factor_symbol(node.expand()));
+            }
+            transparent.push(handle);
+            let selection = self.select_summands(handle, order);
+            let first_summand = self.first_summand(handle);
+            let mut pairs = vec![];
+            for (idx, node) in Bocage::<G>::summands(&self.graph, handle).iter().enumerate() {
+                if !is_selected(selection.as_deref(), idx) {
+                    continue;
+                }
+                transparent.push(NodeHandle(first_summand.0 + idx as Position));
+                if let Product {
+                    left_factor,
+                    right_factor,
+                    ..
+                } = node.expand()
+                {
+                    pairs.push((left_factor, right_factor));
+                }
+            }
+            Step::Unfold(pairs)
+        });
+        for handle in transparent {
+            self.gc.liveness.set(handle.usize(), true);
+        }
+        let action = self.grammar.borrow().external_origin(action).unwrap();
+        let selection = match unfoldings.first() {
+            Some(factors) => order.product(action, &factors[..]),
+            None => None,
+        };
+        for factors in &unfoldings {
+            if let Some(ref selection) = selection {
+                check_selection(&selection[..], factors.len());
+                for &idx in selection.iter().rev() {
+                    self.gc.dfs_queue(factors[idx].1);
+                }
+            } else {
+                for &(_, factor) in factors.iter().rev() {
+                    self.gc.dfs_queue(factor);
+                }
+            }
+        }
+        if let Some(selection) = selection {
+            self.gc.selections.insert(product, selection);
+        }
+    }
+
+    // Checks whether factors of a node are unfolded in place of the node. A sum is
+    // transparent when its summands are.
+    pub(super) fn is_transparent_node(&self, node: NodeHandle) -> bool {
+        match Bocage::<G>::summands(&self.graph, node)[0].expand() {
+            Product { action, .. } => self.is_transparent(action),
+            _ => false,
+        }
+    }
+
+    #[inline]
+    pub(super) fn factor_symbol(&self, node: Node) -> Symbol {
+        match node {
+            Sum { nonterminal, .. } => nonterminal,
+            Product { action, .. } => self.grammar.borrow().get_lhs(action),
+            NullingLeaf { symbol } | Evaluated { symbol, .. } => symbol,
+        }
+    }
+
     #[inline]
     fn summands(graph: &Vec<CompactNode>, node: NodeHandle) -> &[CompactNode] {
         unsafe {
-            match graph.get_unchecked(node.usize()).expand() {
+            match invariant::get(graph, node.usize(), "node handles are within the bocage").expand() {
                 Sum { count, .. } => {
                     // back
                     // let start = node.usize() - count as usize - 1;
                     // let end = node.usize() - 1;
                     let start = node.usize() + 1;
                     let end = node.usize() + count as usize + 1;
-                    graph.get_unchecked(start..end)
+                    invariant::get(graph, start..end, "summands follow their sum")
                 }
-                _ => ref_slice(graph.get_unchecked(node.usize())),
+                _ => ref_slice(invariant::get(
+                    graph,
+                    node.usize(),
+                    "node handles are within the bocage",
+                )),
             }
         }
     }
@@ -149,34 +442,94 @@ where
         action == NULL_ACTION || self.grammar.borrow().external_origin(action).is_none()
     }
 
-    // fn mark_and_sweep(&mut self, root: NodeHandle) {
-    //     self.mark_alive(root);
-    //     self.sweep_garbage();
-    //     self.update_nulling_leaf_count();
-    // }
-
-    // fn sweep_garbage(&mut self) {
-    //     let count = self.relocate_marked();
-    //     self.graph.truncate(count);
-    // }
-
-    // fn update_nulling_leaf_count(&mut self) {
-    //     let prev_count = self.nulling_leaf_count;
-    //     self.nulling_leaf_count = self.gc.liveness.iter().take(prev_count).filter(|x| x).count();
-    // }
-
-    // fn relocate_marked(&mut self) -> usize {
-    //     let mut destination = self.graph.iter();
-    //     let mut count = 0;
-    //     // ... TODO: relocate
-    //     for (alive, source) in self.gc.liveness.iter().zip(self.graph.iter()) {
-    //         if alive {
-    //             destination.next().unwrap().cell.set(*source);
-    //             count += 1;
-    //         }
-    //     }
-    //     count
-    // }
+    /// Keeps nodes that are reachable from the given roots, and discards all other nodes.
+    /// The graph is compacted. Handles of kept nodes must be updated with `relocate`.
+    pub fn mark_and_sweep<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = NodeHandle>,
+    {
+        debug_assert_eq!(self.summand_count, 0, "collection within a sum");
+        self.mark_reachable(roots);
+        self.sweep_garbage();
+    }
+
+    /// Returns the new handle of a node that was kept by the latest collection.
+    #[inline]
+    pub fn relocate(&self, node: NodeHandle) -> Option<NodeHandle> {
+        self.gc
+            .relocation
+            .get(node.usize())
+            .and_then(|handle| handle.to_option())
+    }
+
+    fn mark_reachable<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = NodeHandle>,
+    {
+        self.gc.liveness.clear();
+        self.gc.liveness.grow(self.graph.len(), false);
+        self.gc.dfs.extend(roots);
+        while let Some(node) = self.gc.dfs.pop() {
+            self.gc.liveness.set(node.usize(), true);
+            for summand in Bocage::<G>::summands(&self.graph, node) {
+                self.gc.dfs_queue_factors(summand);
+            }
+        }
+    }
+
+    fn sweep_garbage(&mut self) {
+        let count = self.relocate_marked();
+        self.graph.truncate(count);
+        if let Some(ref mut spans) = self.spans {
+            spans.relocate(&self.gc.relocation[..]);
+        }
+        // Liveness must be marked again before traversal.
+        self.gc.liveness.truncate(0);
+        self.gc.selections.clear();
+    }
+
+    fn relocate_marked(&mut self) -> usize {
+        // Leaves for nulling symbols are kept in place.
+        let nulling_end = self.nulling_symbol_count() + 1;
+        let relocation = &mut self.gc.relocation;
+        relocation.clear();
+        relocation.resize(self.graph.len(), NULL_HANDLE);
+        for (i, handle) in relocation[..nulling_end].iter_mut().enumerate() {
+            *handle = NodeHandle(i as Position);
+        }
+        let mut destination = nulling_end;
+        let mut source = nulling_end;
+        while source < self.graph.len() {
+            let block_len = match self.graph[source].expand() {
+                Sum { count, .. } => count as usize + 1,
+                _ => 1,
+            };
+            if self.gc.liveness[source] {
+                // A sum is moved together with its summands.
+                for _ in 0..block_len {
+                    let node = match self.graph[source].expand() {
+                        Product {
+                            action,
+                            left_factor,
+                            right_factor,
+                        } => Product {
+                            action,
+                            left_factor: relocation[left_factor.usize()],
+                            right_factor: right_factor.map(|factor| relocation[factor.usize()]),
+                        },
+                        other => other,
+                    };
+                    self.graph[destination].set(node);
+                    relocation[source] = NodeHandle(destination as Position);
+                    source += 1;
+                    destination += 1;
+                }
+            } else {
+                source += block_len;
+            }
+        }
+        destination
+    }
 }
 
 impl MarkAndSweep {
@@ -189,18 +542,21 @@ impl MarkAndSweep {
                 ..
             } => {
                 if let Some(factor) = right_factor {
-                    if let Some(false) = self.liveness.get(factor.usize()) {
-                        self.dfs.push(factor);
-                    }
-                }
-                if let Some(false) = self.liveness.get(left_factor.usize()) {
-                    self.dfs.push(left_factor);
+                    self.dfs_queue(factor);
                 }
+                self.dfs_queue(left_factor);
             }
             NullingLeaf { .. } | Evaluated { .. } => {}
             Sum { .. } => unreachable!(),
         }
     }
+
+    #[inline]
+    fn dfs_queue(&mut self, node: NodeHandle) {
+        if let Some(false) = self.liveness.get(node.usize()) {
+            self.dfs.push(node);
+        }
+    }
 }
 
 impl<G> Forest for Bocage<G> {
@@ -228,32 +584,47 @@ impl<G> Forest for Bocage<G> {
     }
 
     #[inline]
-    fn sum(&mut self, lhs_sym: Symbol, _origin: u32) -> Self::NodeRef {
+    fn sum(&mut self, lhs_sym: Symbol, origin: Origin) -> Self::NodeRef {
         let result = unsafe {
             match self.summand_count {
-                0 => hint::unreachable_unchecked(),
-                1 => NodeHandle(self.graph.len() as u32 - 1),
+                0 => invariant::unreachable("a sum has summands"),
+                1 => NodeHandle(self.graph.len() as Position - 1),
                 summand_count => {
                     // Slower case: ambiguous node.
                     let first_summand_idx = self.graph.len() - summand_count as usize;
-                    let first_summand = self.graph.get_unchecked(first_summand_idx).clone();
+                    let first_summand = invariant::get(
+                        &self.graph,
+                        first_summand_idx,
+                        "summands are at the end of the bocage",
+                    )
+                    .clone();
                     self.graph.push(first_summand);
-                    *self.graph.get_unchecked_mut(first_summand_idx) = Sum {
+                    *invariant::get_mut(
+                        &mut self.graph,
+                        first_summand_idx,
+                        "summands are at the end of the bocage",
+                    ) = Sum {
                         nonterminal: lhs_sym,
                         count: self.summand_count as u32,
                     }
                     .compact();
-                    NodeHandle(first_summand_idx as u32)
+                    NodeHandle(first_summand_idx as Position)
                 }
             }
         };
         self.summand_count = 0;
+        if let Some(ref mut spans) = self.spans {
+            spans.sum(result, origin);
+        }
         result
     }
 
     #[inline]
-    fn leaf(&mut self, token: Symbol, _pos: u32, value: Self::LeafValue) -> Self::NodeRef {
-        let result = NodeHandle(self.graph.len() as u32);
+    fn leaf(&mut self, token: Symbol, pos: Origin, value: Self::LeafValue) -> Self::NodeRef {
+        let result = NodeHandle(self.graph.len() as Position);
+        if let Some(ref mut spans) = self.spans {
+            spans.leaf(result, pos);
+        }
         self.graph.push(
             Evaluated {
                 symbol: token,
@@ -269,3 +640,21 @@ impl<G> Forest for Bocage<G> {
         NodeHandle::nulling(token)
     }
 }
+
+impl<G> CollectGarbage for Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    #[inline]
+    fn mark_and_sweep<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = NodeHandle>,
+    {
+        Bocage::mark_and_sweep(self, roots)
+    }
+
+    #[inline]
+    fn relocate(&self, node: NodeHandle) -> Option<NodeHandle> {
+        Bocage::relocate(self, node)
+    }
+}
@@ -1,11 +1,11 @@
 use std::cell::Cell;
-use std::hint;
 
 use cfg::symbol::Symbol;
 
 pub use self::Node::*;
 use self::Tag::*;
 use forest::node_handle::{NodeHandle, NULL_HANDLE};
+use invariant;
 
 // Node variants `Sum`/`Product` are better known in literature as `OR`/`AND`.
 #[derive(Copy, Clone, Debug)]
@@ -159,7 +159,7 @@ impl CompactNode {
     }
 
     #[inline]
-    pub(super) fn expand(&self) -> Node {
+    pub fn expand(&self) -> Node {
         let mut fields = self.cell.get();
         unsafe {
             let tag = get_and_erase_tag(&mut fields);
@@ -190,14 +190,6 @@ impl CompactNode {
     }
 }
 
-#[inline]
-unsafe fn unwrap_unchecked<T>(opt: Option<T>) -> T {
-    match opt {
-        Some(val) => val,
-        None => hint::unreachable_unchecked(),
-    }
-}
-
 #[inline]
 unsafe fn set_tag(fields: &mut [CompactField; 3], tag: Tag) {
     fields[0].tag |= tag.to_u32();
@@ -208,5 +200,5 @@ unsafe fn get_and_erase_tag(fields: &mut [CompactField; 3]) -> Tag {
     let &mut CompactField { ref mut tag } = &mut fields[0];
     let extract_tag = *tag;
     *tag = *tag & !TAG_MASK;
-    unwrap_unchecked(Tag::from_u32(extract_tag))
+    invariant::unwrap(Tag::from_u32(extract_tag), "nodes have a valid tag")
 }
@@ -1,15 +1,45 @@
 use cfg::symbol::Symbol;
 
+use forest::node_handle::NodeHandle;
+pub use forest::order::{AlternativeOrder, MatchOrder, RuleRankOrder};
+use item::Position;
+
 use super::node::CompactNode;
 
 pub trait Order {
+    /// Whether `sum` may select alternatives. Unless it does, all alternatives are
+    /// traversed, and `sum` is not called.
+    const SELECTS_SUMMANDS: bool = true;
+
+    /// Whether `product` may select factors. Unless it does, factors of products are
+    /// not unfolded while marking.
+    const SELECTS_FACTORS: bool = true;
+
     /// Apply the order to sum node alternatives.
-    fn sum<'b>(&mut self, alternatives: &'b [CompactNode]) -> &'b [CompactNode] {
-        alternatives
+    ///
+    /// Returns indices of alternatives that the traversal will pass on, in increasing
+    /// order. Alternatives that are left out are not traversed. `None` keeps all
+    /// alternatives.
+    ///
+    /// `first_factor_len` returns how much input the first factor of an alternative
+    /// spans, or `None` when the forest does not record spans.
+    fn sum(
+        &mut self,
+        _alternatives: &[CompactNode],
+        _first_factor_len: &dyn Fn(usize) -> Option<Position>,
+    ) -> Option<Vec<usize>> {
+        None
     }
 
-    /// Apply the order to product node factors.
-    fn product(&mut self, _factors: &[(Symbol, u32)]) -> Option<usize> {
+    /// Apply the order to product node factors. The factors of the given external rule
+    /// are flattened, so there is one factor for every RHS symbol.
+    ///
+    /// Returns indices of factors that the traversal will pass on, in their new order.
+    /// Factors that are left out are not traversed. `None` keeps all factors in place.
+    ///
+    /// A product that is unfolded in more than one way through ambiguous internal nodes
+    /// gets a single selection, which is made for its first unfolding.
+    fn product(&mut self, _action: u32, _factors: &[(Symbol, NodeHandle)]) -> Option<Vec<usize>> {
         None
     }
 }
@@ -17,10 +47,49 @@ pub trait Order {
 #[derive(Default)]
 pub struct NullOrder;
 
-impl Order for NullOrder {}
+impl Order for NullOrder {
+    const SELECTS_SUMMANDS: bool = false;
+    const SELECTS_FACTORS: bool = false;
+}
 
 impl NullOrder {
     pub fn new() -> Self {
         NullOrder
     }
 }
+
+impl<'g> Order for RuleRankOrder<'g> {
+    const SELECTS_FACTORS: bool = false;
+
+    fn sum(
+        &mut self,
+        alternatives: &[CompactNode],
+        _first_factor_len: &dyn Fn(usize) -> Option<Position>,
+    ) -> Option<Vec<usize>> {
+        self.select(alternatives)
+    }
+}
+
+impl Order for MatchOrder {
+    const SELECTS_FACTORS: bool = false;
+
+    fn sum(
+        &mut self,
+        alternatives: &[CompactNode],
+        first_factor_len: &dyn Fn(usize) -> Option<Position>,
+    ) -> Option<Vec<usize>> {
+        self.select(alternatives.len(), first_factor_len)
+    }
+}
+
+impl Order for AlternativeOrder {
+    const SELECTS_FACTORS: bool = false;
+
+    fn sum(
+        &mut self,
+        alternatives: &[CompactNode],
+        _first_factor_len: &dyn Fn(usize) -> Option<Position>,
+    ) -> Option<Vec<usize>> {
+        self.select(alternatives)
+    }
+}
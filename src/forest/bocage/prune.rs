@@ -0,0 +1,170 @@
+use std::borrow::Borrow;
+
+use bit_vec::BitVec;
+use cfg::symbol::Symbol;
+
+use forest::bocage::node::Node::*;
+use forest::node_handle::NodeHandle;
+use forest::{Bocage, Span};
+use grammar::InternalGrammar;
+use item::Position;
+
+impl<G> Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Removes live alternatives that the predicate rejects, then marks liveness again
+    /// for the root. Liveness must be marked for the root. Returns `false` if the root
+    /// has no derivations left.
+    ///
+    /// Live nodes are evaluated bottom-up, once. The predicate is given the external rule
+    /// of an alternative, the span of its node and values of its factors. It returns the
+    /// value of the alternative, or `None` to reject it. Factors do not contain rejected
+    /// alternatives. Where a factor is still ambiguous, the value of its first kept
+    /// alternative is given, and its first unfolding is taken.
+    pub fn prune<V, FLeaf, FNull, F>(
+        &mut self,
+        root: NodeHandle,
+        mut leaf: FLeaf,
+        mut null: FNull,
+        mut predicate: F,
+    ) -> bool
+    where
+        FLeaf: FnMut(Symbol, u32) -> V,
+        FNull: FnMut(Symbol) -> V,
+        F: FnMut(u32, Option<Span>, &[&V]) -> Option<V>,
+    {
+        // For summands, whether they are derivable and kept.
+        let mut derivable = BitVec::from_elem(self.graph.len(), false);
+        // Values of live nodes and of their kept summands.
+        let mut values: Vec<Option<V>> = (0..self.graph.len()).map(|_| None).collect();
+        let mut i = 0;
+        while i < self.graph.len() {
+            let node = NodeHandle(i as Position);
+            let summands = match self.graph[i].expand() {
+                Sum { count, .. } => (i + 1)..(i + 1 + count as usize),
+                _ => i..(i + 1),
+            };
+            let is_alive = self.gc.liveness.get(i).unwrap_or(false);
+            let mut any_kept = false;
+            for s in summands.clone() {
+                let summand_alive = self.gc.liveness.get(s).unwrap_or(false);
+                let keep = match self.graph[s].expand() {
+                    Product {
+                        action,
+                        left_factor,
+                        right_factor,
+                    } => {
+                        let factors_derivable = derivable[left_factor.usize()]
+                            && right_factor.iter().all(|right| derivable[right.usize()]);
+                        if !is_alive {
+                            factors_derivable
+                        } else if !factors_derivable || !summand_alive {
+                            false
+                        } else if self.is_transparent(action) {
+                            true
+                        } else {
+                            let handle = NodeHandle(s as Position);
+                            let unfoldings =
+                                self.selected_unfoldings(handle, left_factor, right_factor);
+                            let args: Vec<&V> = unfoldings[0]
+                                .iter()
+                                .map(|&(_, factor)| {
+                                    values[factor.usize()]
+                                        .as_ref()
+                                        .expect("a kept factor is evaluated")
+                                })
+                                .collect();
+                            let rule = self.grammar.borrow().external_origin(action).unwrap();
+                            let value = predicate(rule, self.span(node), &args[..]);
+                            let keep = value.is_some();
+                            values[s] = value;
+                            keep
+                        }
+                    }
+                    Evaluated {
+                        symbol,
+                        values: value,
+                    } => {
+                        if is_alive {
+                            values[s] = Some(leaf(symbol, value));
+                        }
+                        true
+                    }
+                    NullingLeaf { symbol } => {
+                        if is_alive {
+                            values[s] = Some(null(symbol));
+                        }
+                        true
+                    }
+                    Sum { .. } => unreachable!(),
+                };
+                derivable.set(s, keep);
+                if is_alive && !keep {
+                    // Later alternatives must not see rejected ones.
+                    self.gc.liveness.set(s, false);
+                }
+                if keep && !any_kept && s != i {
+                    // A sum has the value of its first kept summand.
+                    values[i] = values[s].take();
+                }
+                any_kept |= keep;
+            }
+            derivable.set(i, any_kept);
+            i = summands.end;
+        }
+        self.mark_kept(root, &derivable);
+        derivable[root.usize()]
+    }
+
+    // Marks liveness for kept alternatives, which keep factors that are selected by
+    // the order.
+    fn mark_kept(&mut self, root: NodeHandle, kept: &BitVec) {
+        self.gc.liveness.clear();
+        self.gc.liveness.grow(self.graph.len(), false);
+        if !kept[root.usize()] {
+            return;
+        }
+        let mut unfolded = vec![];
+        self.gc.dfs.push(root);
+        while let Some(node) = self.gc.dfs.pop() {
+            self.gc.liveness.set(node.usize(), true);
+            let summands = match self.graph[node.usize()].expand() {
+                Sum { count, .. } => (node.usize() + 1)..(node.usize() + 1 + count as usize),
+                _ => node.usize()..(node.usize() + 1),
+            };
+            for s in summands.filter(|&s| kept[s]) {
+                self.gc.liveness.set(s, true);
+                let summand = self.graph[s].clone();
+                let selection = self.gc.selections.get(&NodeHandle(s as Position)).cloned();
+                match (summand.expand(), selection) {
+                    (
+                        Product {
+                            left_factor,
+                            right_factor,
+                            ..
+                        },
+                        Some(selection),
+                    ) => {
+                        let unfoldings = self.marked_nodes(kept).unfoldings(
+                            left_factor,
+                            right_factor,
+                            &mut unfolded,
+                        );
+                        for handle in unfolded.drain(..) {
+                            self.gc.liveness.set(handle.usize(), true);
+                        }
+                        for factors in unfoldings {
+                            for &idx in selection.iter().rev() {
+                                self.gc.dfs_queue(factors[idx].1);
+                            }
+                        }
+                    }
+                    _ => {
+                        self.gc.dfs_queue_factors(&summand);
+                    }
+                }
+            }
+        }
+    }
+}
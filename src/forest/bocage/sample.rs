@@ -0,0 +1,195 @@
+use std::borrow::Borrow;
+
+use cfg::symbol::Symbol;
+use rand::Rng;
+
+use forest::bocage::node::Node::*;
+use forest::bocage::trees::{Choice, ParseTree, TreeBuilder};
+use forest::node_handle::NodeHandle;
+use forest::tree_count::{count_nulling, nulling_count};
+use forest::Bocage;
+use grammar::InternalGrammar;
+use item::Position;
+
+impl<G> Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Returns a sampler of derivation trees of a node, which draws every derivation
+    /// with equal probability. Liveness must be marked for this node.
+    ///
+    /// Derivations are those that `count_trees` counts, so a nulling leaf is drawn as
+    /// often as its symbol has derivations.
+    pub fn sampler(&self, root: NodeHandle) -> Sampler<'_, G> {
+        self.weighted_sampler(root, &[])
+    }
+
+    /// Returns a sampler of derivation trees of a node, which draws a derivation with
+    /// probability proportional to the product of weights of its rules. Weights are
+    /// indexed by external rule IDs, and rules without a weight have the weight 1.
+    /// Every derivation of a nulling symbol has the weight 1.
+    pub fn weighted_sampler(&self, root: NodeHandle, rule_weights: &[f64]) -> Sampler<'_, G> {
+        Sampler {
+            builder: TreeBuilder::new(self),
+            root,
+            weights: self.inside_weights(root, rule_weights),
+        }
+    }
+
+    // Computes total weights of derivations for live sums, summands and leaves
+    // reachable from the root. Factors are unfolded and selected as in traversal.
+    fn inside_weights(&self, root: NodeHandle, rule_weights: &[f64]) -> Vec<f64> {
+        let grammar = self.grammar.borrow();
+        let mut weights: Vec<Option<f64>> = vec![None; self.graph.len()];
+        let nulling: Vec<Option<u64>> = count_nulling(grammar);
+        let is_alive = |i: usize| self.gc.liveness.get(i).unwrap_or(false);
+        // Nodes are weighed after their factors.
+        let mut stack = vec![(root, false)];
+        while let Some((node, factors_weighed)) = stack.pop() {
+            if weights[node.usize()].is_some() {
+                continue;
+            }
+            let summands: Vec<_> = match self.graph[node.usize()].expand() {
+                Sum { count, .. } => {
+                    let start = node.usize() + 1;
+                    (start..start + count as usize)
+                        .filter(|&i| is_alive(i))
+                        .map(|i| self.summand_unfoldings(NodeHandle(i as Position)))
+                        .collect()
+                }
+                _ => vec![self.summand_unfoldings(node)],
+            };
+            if factors_weighed {
+                let mut total = 0.0;
+                for (summand, unfoldings) in summands {
+                    let weight = match self.graph[summand.usize()].expand() {
+                        Product { action, .. } => {
+                            let origin = grammar.external_origin(action).unwrap();
+                            let rule_weight =
+                                rule_weights.get(origin as usize).cloned().unwrap_or(1.0);
+                            let factors_weight: f64 = unfoldings
+                                .iter()
+                                .map(|factors| {
+                                    unfolding_weight(factors, |factor| {
+                                        weights[factor.usize()].unwrap()
+                                    })
+                                })
+                                .sum();
+                            rule_weight * factors_weight
+                        }
+                        NullingLeaf { symbol } => {
+                            nulling_count(&nulling[..], symbol).unwrap_or(u64::MAX) as f64
+                        }
+                        // A token has a single derivation.
+                        _ => 1.0,
+                    };
+                    weights[summand.usize()] = Some(weight);
+                    total += weight;
+                }
+                weights[node.usize()] = Some(total);
+            } else {
+                stack.push((node, true));
+                for (_, unfoldings) in summands {
+                    for &(_, factor) in unfoldings.iter().flatten() {
+                        if weights[factor.usize()].is_none() {
+                            stack.push((factor, false));
+                        }
+                    }
+                }
+            }
+        }
+        weights
+            .into_iter()
+            .map(|weight| weight.unwrap_or(0.0))
+            .collect()
+    }
+
+    // Lists selected factors of a summand in every unfolding. Leaves have no factors.
+    fn summand_unfoldings(
+        &self,
+        summand: NodeHandle,
+    ) -> (NodeHandle, Vec<Vec<(Symbol, NodeHandle)>>) {
+        let unfoldings = match self.graph[summand.usize()].expand() {
+            Product {
+                left_factor,
+                right_factor,
+                ..
+            } => self.selected_unfoldings(summand, left_factor, right_factor),
+            _ => vec![],
+        };
+        (summand, unfoldings)
+    }
+}
+
+// The weight of an unfolding is the product of weights of its factors.
+fn unfolding_weight<F: Fn(NodeHandle) -> f64>(factors: &[(Symbol, NodeHandle)], weight: F) -> f64 {
+    factors.iter().map(|&(_, factor)| weight(factor)).product()
+}
+
+/// A sampler of derivation trees. Alternatives are drawn with probabilities proportional
+/// to total weights of their derivations.
+pub struct Sampler<'f, G: 'f> {
+    builder: TreeBuilder<'f, G>,
+    root: NodeHandle,
+    weights: Vec<f64>,
+}
+
+struct WeightedChoice<'a, R: 'a + ?Sized> {
+    weights: &'a [f64],
+    rng: &'a mut R,
+}
+
+impl<'f, G> Sampler<'f, G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Draws a single derivation tree. Use a seeded RNG for reproducible samples.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ParseTree {
+        let mut choice = WeightedChoice {
+            weights: &self.weights[..],
+            rng,
+        };
+        self.builder.build(self.root, &mut choice)
+    }
+
+    /// Returns the total weight of derivations of the root. For a uniform sampler,
+    /// this is the number of derivations.
+    pub fn total_weight(&self) -> f64 {
+        self.weights[self.root.usize()]
+    }
+}
+
+impl<'a, R: Rng + ?Sized> WeightedChoice<'a, R> {
+    // Draws an index with probability proportional to its weight.
+    fn draw(&mut self, weights: &[f64]) -> usize {
+        let total: f64 = weights.iter().sum();
+        let mut point = self.rng.gen::<f64>() * total;
+        for (i, &weight) in weights.iter().enumerate() {
+            if point < weight {
+                return i;
+            }
+            point -= weight;
+        }
+        // Rounding errors may leave a small remainder.
+        weights
+            .iter()
+            .rposition(|&weight| weight > 0.0)
+            .unwrap_or(0)
+    }
+}
+
+impl<'a, R: Rng + ?Sized> Choice for WeightedChoice<'a, R> {
+    fn choose(&mut self, summands: &[NodeHandle]) -> usize {
+        let weights: Vec<f64> = summands.iter().map(|s| self.weights[s.usize()]).collect();
+        self.draw(&weights[..])
+    }
+
+    fn choose_unfolding(&mut self, unfoldings: &[Vec<(Symbol, NodeHandle)>]) -> usize {
+        let all_weights = self.weights;
+        let weights: Vec<f64> = unfoldings
+            .iter()
+            .map(|factors| unfolding_weight(factors, |factor| all_weights[factor.usize()]))
+            .collect();
+        self.draw(&weights[..])
+    }
+}
@@ -1,4 +1,6 @@
 use std::borrow::Borrow;
+use std::cell::Cell;
+use std::iter;
 use std::slice;
 
 use bit_vec;
@@ -6,10 +8,12 @@ use cfg::symbol::Symbol;
 use ref_slice::ref_slice;
 
 use forest::bocage::node::Node::*;
-use forest::bocage::node::{CompactNode, Node};
+use forest::bocage::node::CompactNode;
 use forest::node_handle::NodeHandle;
+use forest::span::Span;
 use forest::Bocage;
 use grammar::InternalGrammar;
+use item::Position;
 
 pub use self::HandleVariant::*;
 
@@ -21,7 +25,7 @@ impl<G> Bocage<G> {
             graph_iter: self.graph.iter(),
             liveness_iter: self.gc.liveness.iter(),
             factor_stack: vec![],
-            factor_traversal: vec![],
+            unfoldings: vec![],
         }
     }
 }
@@ -33,8 +37,8 @@ pub struct Traverse<'f, G> {
     liveness_iter: bit_vec::Iter<'f>,
     // Space for unrolling factors
     factor_stack: Vec<(Symbol, u32)>,
-    // Scratch space for traversal
-    factor_traversal: Vec<NodeHandle>,
+    // Unfoldings of the current product that are not passed on yet, in reverse order
+    unfoldings: Vec<Vec<(Symbol, u32)>>,
 }
 
 impl<'f, G> Traverse<'f, G>
@@ -46,6 +50,8 @@ where
             if !alive {
                 continue;
             }
+            let handle =
+                NodeHandle((self.bocage.graph.len() - self.graph_iter.len() - 1) as Position);
             match node.expand() {
                 Product { action, .. } => {
                     if self.bocage.is_transparent(action) {
@@ -53,9 +59,14 @@ where
                     }
                     return Some(TraversalHandle {
                         node,
+                        handle,
+                        evaluated: &self.bocage.evaluated,
+                        span: self.bocage.span(handle),
                         symbol: self.bocage.grammar.borrow().get_lhs(action),
                         item: SumHandle(Products {
                             products: ref_slice(node).iter(),
+                            handle,
+                            action: 0,
                             traverse: self,
                         }),
                     });
@@ -69,11 +80,20 @@ where
                         self.graph_iter.next();
                         self.liveness_iter.next();
                     }
+                    if self.bocage.is_transparent_node(handle) {
+                        // Its summands are unfolded into products of external rules.
+                        continue;
+                    }
                     return Some(TraversalHandle {
                         node,
+                        handle,
+                        evaluated: &self.bocage.evaluated,
+                        span: self.bocage.span(handle),
                         symbol,
                         item: SumHandle(Products {
                             products,
+                            handle: NodeHandle(handle.0 + 1),
+                            action: 0,
                             traverse: self,
                         }),
                     });
@@ -81,6 +101,9 @@ where
                 NullingLeaf { symbol } => {
                     return Some(TraversalHandle {
                         node,
+                        handle,
+                        evaluated: &self.bocage.evaluated,
+                        span: self.bocage.span(handle),
                         symbol,
                         item: NullingHandle,
                     });
@@ -88,6 +111,9 @@ where
                 Evaluated { symbol, values } => {
                     return Some(TraversalHandle {
                         node,
+                        handle,
+                        evaluated: &self.bocage.evaluated,
+                        span: self.bocage.span(handle),
                         symbol,
                         item: LeafHandle(values),
                     });
@@ -97,43 +123,61 @@ where
         None
     }
 
-    fn unfold_factors(&mut self, left: NodeHandle, right: Option<NodeHandle>) {
+    // Passes factors of a product on in place, unless the product has a selection or
+    // transparent factors. Returns whether the factors were passed on.
+    fn direct_factors(
+        &mut self,
+        product: NodeHandle,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+    ) -> bool {
+        let bocage = self.bocage;
+        if bocage.gc.selections.contains_key(&product)
+            || bocage.is_transparent_node(left)
+            || matches!(right, Some(right) if bocage.is_transparent_node(right))
+        {
+            return false;
+        }
         self.factor_stack.clear();
-        self.enqueue_for_unfold(left, right);
-        while let Some(node) = self.pop_for_unfold() {
-            match node {
-                Product {
-                    left_factor,
-                    right_factor,
-                    ..
-                } => {
-                    self.enqueue_for_unfold(left_factor, right_factor);
-                }
-                Evaluated { symbol, values } => {
-                    self.factor_stack.push((symbol, values));
-                }
-                _ => unreachable!(),
-            }
+        for handle in iter::once(left).chain(right) {
+            let node = bocage.graph[handle.usize()].expand();
+            let values = match node {
+                Evaluated { values, .. } => values,
+                _ => !0,
+            };
+            self.factor_stack.push((bocage.factor_symbol(node), values));
         }
+        true
     }
 
-    fn enqueue_for_unfold(&mut self, left: NodeHandle, right: Option<NodeHandle>) {
-        if let Some(right) = right {
-            self.factor_traversal.push(right);
+    fn unfold_factors(
+        &mut self,
+        product: NodeHandle,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+    ) {
+        let graph = &self.bocage.graph;
+        let unfoldings = self.bocage.selected_unfoldings(product, left, right);
+        for factors in unfoldings.into_iter().rev() {
+            let values = factors
+                .into_iter()
+                .map(|(symbol, handle)| match graph[handle.usize()].expand() {
+                    Evaluated { values, .. } => (symbol, values),
+                    // This factor was left out by the order, so it is not evaluated.
+                    _ => (symbol, !0),
+                })
+                .collect();
+            self.unfoldings.push(values);
         }
-        self.factor_traversal.push(left);
-    }
-
-    fn pop_for_unfold(&mut self) -> Option<Node> {
-        self.factor_traversal.pop().map(|handle| {
-            let node = self.bocage.graph[handle.usize()].clone();
-            node.expand()
-        })
     }
 }
 
 pub struct TraversalHandle<'f, 't, G> {
     pub node: &'f CompactNode,
+    pub(crate) handle: NodeHandle,
+    evaluated: &'f Cell<bool>,
+    /// The input range covered by this node, if spans are recorded.
+    pub span: Option<Span>,
     pub symbol: Symbol,
     pub item: HandleVariant<'f, 't, G>,
 }
@@ -146,6 +190,10 @@ pub enum HandleVariant<'f, 't, G> {
 
 pub struct Products<'f, 't, G> {
     products: slice::Iter<'f, CompactNode>,
+    // Handle of the next product
+    handle: NodeHandle,
+    // Action of the product that is unfolded
+    action: u32,
     traverse: &'t mut Traverse<'f, G>,
 }
 
@@ -159,7 +207,21 @@ where
     G: Borrow<InternalGrammar>,
 {
     pub fn next_product<'p>(&'p mut self) -> Option<ProductHandle> {
-        while let Some(node) = self.products.next() {
+        loop {
+            // A product may be unfolded in more than one way.
+            if let Some(factors) = self.traverse.unfoldings.pop() {
+                self.traverse.factor_stack = factors;
+                return Some(ProductHandle {
+                    action: self.action,
+                    factors: &self.traverse.factor_stack[..],
+                });
+            }
+            let node = self.products.next()?;
+            let handle = self.handle;
+            self.handle.0 += 1;
+            if !self.traverse.bocage.gc.liveness[handle.usize()] {
+                continue;
+            }
             match node.expand() {
                 Product {
                     left_factor,
@@ -173,25 +235,33 @@ where
                         .borrow()
                         .external_origin(action);
                     if let Some(action) = origin {
-                        self.traverse.unfold_factors(left_factor, right_factor);
-                        return Some(ProductHandle {
-                            action,
-                            factors: &self.traverse.factor_stack[..],
-                        });
+                        self.action = action;
+                        if self.traverse.direct_factors(handle, left_factor, right_factor) {
+                            return Some(ProductHandle {
+                                action,
+                                factors: &self.traverse.factor_stack[..],
+                            });
+                        }
+                        self.traverse
+                            .unfold_factors(handle, left_factor, right_factor);
                     }
                 }
                 _ => unreachable!(),
             }
         }
-        None
     }
 }
 
 impl<'f, 't, G> TraversalHandle<'f, 't, G> {
     pub fn set_evaluation_result(&self, values: u32) {
+        self.evaluated.set(true);
         self.node.set(Evaluated {
             symbol: self.symbol,
             values,
         });
     }
+
+    pub fn handle(&self) -> NodeHandle {
+        self.handle
+    }
 }
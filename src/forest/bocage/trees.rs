@@ -0,0 +1,257 @@
+use std::borrow::Borrow;
+
+use cfg::symbol::Symbol;
+
+use forest::bocage::node::Node;
+use forest::bocage::node::Node::*;
+use forest::node_handle::NodeHandle;
+use forest::{Bocage, Span};
+use grammar::InternalGrammar;
+use item::Position;
+
+/// A single derivation tree. All of its symbols are external.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseTree {
+    /// A derivation of an external rule with its LHS symbol.
+    Node {
+        action: u32,
+        lhs: Symbol,
+        span: Option<Span>,
+        children: Vec<ParseTree>,
+    },
+    /// A token with its leaf value.
+    Leaf {
+        symbol: Symbol,
+        value: u32,
+        span: Option<Span>,
+    },
+    /// A nulling symbol.
+    Nulling { symbol: Symbol },
+}
+
+impl<G> Bocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Iterates over derivation trees of a node. Liveness must be marked for this node,
+    /// and only alternatives and factors that are selected by the order are visited.
+    /// Leaf values are available until the bocage is evaluated.
+    pub fn trees(&self, root: NodeHandle) -> Trees<'_, G> {
+        Trees {
+            builder: TreeBuilder::new(self),
+            root,
+            odometer: Odometer {
+                choices: vec![],
+                position: 0,
+            },
+            finished: false,
+        }
+    }
+}
+
+/// An iterator over derivation trees, which builds one tree at a time.
+pub struct Trees<'f, G: 'f> {
+    builder: TreeBuilder<'f, G>,
+    root: NodeHandle,
+    odometer: Odometer,
+    finished: bool,
+}
+
+// Chooses an alternative among live summands of a sum, and among ways of unfolding
+// factors of a product.
+pub(super) trait Choice {
+    fn choose(&mut self, summands: &[NodeHandle]) -> usize;
+
+    fn choose_unfolding(&mut self, unfoldings: &[Vec<(Symbol, NodeHandle)>]) -> usize;
+}
+
+// Chosen alternatives and counts of alternatives for sums in the order of their visits.
+// Advanced like an odometer.
+struct Odometer {
+    choices: Vec<(usize, usize)>,
+    // Number of sums visited while building the current tree.
+    position: usize,
+}
+
+// Builds derivation trees of live nodes.
+pub(super) struct TreeBuilder<'f, G: 'f> {
+    bocage: &'f Bocage<G>,
+}
+
+enum Expansion {
+    Leaf(ParseTree),
+    Product(Frame),
+}
+
+// A product whose factors are visited.
+struct Frame {
+    action: u32,
+    lhs: Symbol,
+    span: Option<Span>,
+    factors: Vec<NodeHandle>,
+    children: Vec<ParseTree>,
+}
+
+impl<'f, G> TreeBuilder<'f, G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    pub(super) fn new(bocage: &'f Bocage<G>) -> Self {
+        TreeBuilder { bocage }
+    }
+
+    pub(super) fn build<C: Choice>(&self, root: NodeHandle, choice: &mut C) -> ParseTree {
+        let mut stack: Vec<Frame> = vec![];
+        let mut node = root;
+        loop {
+            let mut tree = match self.expand(node, choice) {
+                Expansion::Leaf(tree) => tree,
+                Expansion::Product(frame) => match frame.factors.first() {
+                    Some(&first) => {
+                        node = first;
+                        stack.push(frame);
+                        continue;
+                    }
+                    None => frame.finish(),
+                },
+            };
+            // Go up until there is a factor that is not visited yet.
+            loop {
+                match stack.last_mut() {
+                    Some(parent) => {
+                        parent.children.push(tree);
+                        if let Some(&next) = parent.factors.get(parent.children.len()) {
+                            node = next;
+                            break;
+                        }
+                    }
+                    None => return tree,
+                }
+                tree = stack.pop().unwrap().finish();
+            }
+        }
+    }
+
+    fn expand<C: Choice>(&self, node: NodeHandle, choice: &mut C) -> Expansion {
+        let (handle, summand) = self.choose_summand(node, choice);
+        match summand {
+            Product {
+                action,
+                left_factor,
+                right_factor,
+            } => {
+                let grammar = self.bocage.grammar.borrow();
+                let lhs = grammar.to_external(grammar.get_lhs(action));
+                let action = grammar.external_origin(action).unwrap();
+                let mut unfoldings =
+                    self.bocage
+                        .selected_unfoldings(handle, left_factor, right_factor);
+                let chosen = if unfoldings.len() == 1 {
+                    0
+                } else {
+                    choice.choose_unfolding(&unfoldings[..])
+                };
+                let factors = unfoldings
+                    .swap_remove(chosen)
+                    .into_iter()
+                    .map(|(_, factor)| factor)
+                    .collect();
+                Expansion::Product(Frame {
+                    action,
+                    lhs,
+                    span: self.bocage.span(node),
+                    factors,
+                    children: vec![],
+                })
+            }
+            Evaluated { symbol, values } => Expansion::Leaf(ParseTree::Leaf {
+                symbol,
+                value: values,
+                span: self.bocage.span(node),
+            }),
+            NullingLeaf { symbol } => Expansion::Leaf(ParseTree::Nulling { symbol }),
+            Sum { .. } => unreachable!(),
+        }
+    }
+
+    fn choose_summand<C: Choice>(&self, node: NodeHandle, choice: &mut C) -> (NodeHandle, Node) {
+        let graph = &self.bocage.graph;
+        let liveness = &self.bocage.gc.liveness;
+        match graph[node.usize()].expand() {
+            Sum { count, .. } => {
+                let start = node.usize() + 1;
+                let alive: Vec<NodeHandle> = (start..start + count as usize)
+                    .filter(|&i| liveness.get(i).unwrap_or(false))
+                    .map(|i| NodeHandle(i as Position))
+                    .collect();
+                let handle = if alive.len() == 1 {
+                    alive[0]
+                } else {
+                    alive[choice.choose(&alive[..])]
+                };
+                (handle, graph[handle.usize()].expand())
+            }
+            other => (node, other),
+        }
+    }
+}
+
+impl Choice for Odometer {
+    fn choose(&mut self, summands: &[NodeHandle]) -> usize {
+        self.next_choice(summands.len())
+    }
+
+    fn choose_unfolding(&mut self, unfoldings: &[Vec<(Symbol, NodeHandle)>]) -> usize {
+        self.next_choice(unfoldings.len())
+    }
+}
+
+impl Odometer {
+    fn next_choice(&mut self, count: usize) -> usize {
+        if self.position == self.choices.len() {
+            self.choices.push((0, count));
+        }
+        let (choice, _) = self.choices[self.position];
+        self.position += 1;
+        choice
+    }
+
+    // Moves to the next combination of choices.
+    fn advance(&mut self) -> bool {
+        self.position = 0;
+        while let Some((choice, count)) = self.choices.pop() {
+            if choice + 1 < count {
+                self.choices.push((choice + 1, count));
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl Frame {
+    fn finish(self) -> ParseTree {
+        ParseTree::Node {
+            action: self.action,
+            lhs: self.lhs,
+            span: self.span,
+            children: self.children,
+        }
+    }
+}
+
+impl<'f, G> Iterator for Trees<'f, G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    type Item = ParseTree;
+
+    fn next(&mut self) -> Option<ParseTree> {
+        if self.finished {
+            return None;
+        }
+        let tree = self.builder.build(self.root, &mut self.odometer);
+        self.finished = !self.odometer.advance();
+        Some(tree)
+    }
+}
@@ -0,0 +1,76 @@
+use std::borrow::Borrow;
+use std::io::{self, Write};
+
+use bit_vec::BitVec;
+
+use forest::compact_bocage::node::Node::*;
+use forest::dot::{symbol_label, DotWriter};
+use forest::node_handle::NodeHandle;
+use forest::CompactBocage;
+use grammar::InternalGrammar;
+
+impl<G> CompactBocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    /// Writes the live part of the forest under a node as a DOT graph. Liveness must
+    /// be marked for this node. Sums are labeled with symbol names, products with
+    /// external rule IDs and leaves with symbol names. Leaf values are not stored
+    /// in this forest.
+    pub fn write_dot<W: Write>(&self, root: NodeHandle, out: &mut W) -> io::Result<()> {
+        let grammar = self.grammar.borrow();
+        let is_alive = |handle: NodeHandle| self.gc.liveness.get(handle.usize()).unwrap_or(false);
+        let mut writer = DotWriter::new(out)?;
+        let mut visited = BitVec::from_elem(self.graph.vec.len(), false);
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if visited[node.usize()] {
+                continue;
+            }
+            visited.set(node.usize(), true);
+            let summands: Vec<_> = match self.graph.get(node) {
+                Sum { nonterminal, .. } => {
+                    let alive: Vec<_> = CompactBocage::<G>::summands(&self.graph, node)
+                        .filter(|&(handle, _)| is_alive(handle))
+                        .collect();
+                    let label = symbol_label(grammar, grammar.to_external(nonterminal));
+                    writer.sum(node, &label, alive.len() > 1)?;
+                    for &(handle, _) in &alive {
+                        writer.summand_edge(node, handle)?;
+                    }
+                    alive
+                }
+                other => vec![(node, other)],
+            };
+            for (summand, node) in summands {
+                match node {
+                    Product {
+                        action,
+                        left_factor,
+                        right_factor,
+                    } => {
+                        let rule = if self.is_transparent(action) {
+                            None
+                        } else {
+                            grammar.external_origin(action)
+                        };
+                        writer.product(summand, rule)?;
+                        for factor in Some(left_factor).into_iter().chain(right_factor) {
+                            writer.factor_edge(summand, factor)?;
+                            stack.push(factor);
+                        }
+                    }
+                    Evaluated { symbol } => {
+                        writer.leaf(summand, &symbol_label(grammar, symbol))?;
+                    }
+                    NullingLeaf { symbol } => {
+                        let label = format!("{} = ()", symbol_label(grammar, symbol));
+                        writer.leaf(summand, &label)?;
+                    }
+                    Sum { .. } => unreachable!(),
+                }
+            }
+        }
+        writer.finish()
+    }
+}
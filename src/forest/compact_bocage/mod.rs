@@ -1,20 +1,26 @@
+pub mod dot;
 pub mod node;
 pub mod order;
 pub mod traverse;
 
 use std::borrow::Borrow;
-use std::hint;
+use std::collections::BTreeMap;
 
 use bit_vec::BitVec;
 use cfg::symbol::Symbol;
 
-use forest::node_handle::NodeHandle;
-use forest::Forest;
+use forest::node_handle::{NodeHandle, NULL_HANDLE};
+use forest::order::{check_selection, is_selected};
+use forest::span::{Span, Spans};
+use forest::tree_count::{count_nulling, nulling_count, TreeCount};
+use forest::unfold::{unfold, Step};
+use forest::{CollectGarbage, Forest};
 use grammar::InternalGrammar;
-use item::CompletedItem;
+use invariant;
+use item::{CompletedItem, Origin, Position};
 
 use self::node::Node::*;
-use self::node::{Graph, Node, NULL_ACTION};
+use self::node::{Graph, Node, Tag, NULL_ACTION};
 use self::order::Order;
 
 pub struct CompactBocage<G> {
@@ -23,12 +29,22 @@ pub struct CompactBocage<G> {
     pub(crate) grammar: G,
     pub(crate) first_summand: NodeHandle,
     pub(crate) summand_count: u32,
+    pub(crate) spans: Option<Spans>,
+    // Values of leaves. Sorted by node, because nodes are created in order.
+    pub(crate) leaf_values: Vec<(NodeHandle, u32)>,
+    // Handles of nodes for nulling symbols, indexed by symbol. Nodes differ in size, so
+    // handles are not the same as symbols.
+    pub(crate) nulling_nodes: Vec<NodeHandle>,
 }
 
 pub(crate) struct MarkAndSweep {
     pub(crate) liveness: BitVec,
-    // List for DFS and/or maybe relocation of stuff in the future.
+    // List for DFS.
     pub(crate) dfs: Vec<NodeHandle>,
+    // New locations of nodes kept by the latest sweep.
+    pub(crate) relocation: Vec<NodeHandle>,
+    // Factors selected by the order for products.
+    pub(crate) selections: BTreeMap<NodeHandle, Vec<usize>>,
 }
 
 impl<G> CompactBocage<G>
@@ -45,10 +61,15 @@ where
             gc: MarkAndSweep {
                 liveness: BitVec::with_capacity(graph_cap),
                 dfs: Vec::with_capacity(dfs_cap),
+                relocation: vec![],
+                selections: BTreeMap::new(),
             },
             grammar,
             summand_count: 0,
             first_summand: NodeHandle(0),
+            spans: None,
+            leaf_values: vec![],
+            nulling_nodes: vec![],
         };
         result.initialize_nulling();
         result
@@ -76,8 +97,15 @@ where
         let mut relocation = vec![];
         for node in &graph {
             relocation.push(NodeHandle(pos));
-            pos += node.classify(pos).size() as u32;
+            // Factors are not relocated yet, so products are sized without them. Their
+            // `NULL_ACTION` only fits in the wide product anyway.
+            let tag = match *node {
+                Product { .. } => Tag::ProductTag,
+                _ => node.classify(pos),
+            };
+            pos += tag.size() as Position;
         }
+        self.nulling_nodes = relocation.clone();
         for node in graph {
             match node {
                 Product {
@@ -96,6 +124,7 @@ where
                 }
             }
         }
+        debug_assert_eq!(self.graph.vec.len(), pos as usize, "nulling nodes keep their sizes");
     }
 
     fn nulling_symbol_count(&self) -> usize {
@@ -106,31 +135,290 @@ where
             .map_or(1, |m| m + 1)
     }
 
+    /// Returns the size of the graph in cells. A node takes one or more cells.
+    pub fn len(&self) -> usize {
+        self.graph.vec.len()
+    }
+
+    /// Checks whether the graph is empty.
+    pub fn is_empty(&self) -> bool {
+        self.graph.vec.is_empty()
+    }
+
+    /// Records spans of sum and leaf nodes that are created from now on.
+    pub fn enable_spans(&mut self) {
+        if self.spans.is_none() {
+            self.spans = Some(Spans::new());
+        }
+    }
+
+    /// Returns the input range covered by a node, if its span was recorded.
+    pub fn span(&self, node: NodeHandle) -> Option<Span> {
+        self.spans.as_ref().and_then(|spans| spans.get(node))
+    }
+
+    /// Returns the value of a leaf for a token.
+    pub fn leaf_value(&self, node: NodeHandle) -> Option<u32> {
+        self.leaf_values
+            .binary_search_by_key(&node, |&(handle, _)| handle)
+            .ok()
+            .map(|idx| self.leaf_values[idx].1)
+    }
+
+    /// Counts parse trees that are derived from the given node. All alternatives are
+    /// counted, regardless of the order, and so are derivations of nulling symbols. Nodes
+    /// must not be evaluated yet.
+    ///
+    /// Returns `None` if a nulling symbol has infinitely many derivations through cyclic
+    /// rules, and `C` has no count for them. Counts of type `u64` saturate instead.
+    pub fn count_trees<C: TreeCount>(&self, root: NodeHandle) -> Option<C> {
+        let mut counts: Vec<Option<C>> = vec![None; self.graph.vec.len()];
+        // Nodes are counted after their factors.
+        let nulling: Vec<Option<C>> = count_nulling(self.grammar.borrow());
+        let mut stack = vec![(root, false)];
+        while let Some((node, factors_counted)) = stack.pop() {
+            if counts[node.usize()].is_some() {
+                continue;
+            }
+            if factors_counted {
+                let mut count = C::zero();
+                for (_, summand) in CompactBocage::<G>::summands(&self.graph, node) {
+                    let product = match summand {
+                        Product {
+                            left_factor,
+                            right_factor,
+                            ..
+                        } => {
+                            let left = counts[left_factor.usize()].as_ref().unwrap();
+                            match right_factor {
+                                Some(right) => left.mul(counts[right.usize()].as_ref().unwrap()),
+                                None => left.clone(),
+                            }
+                        }
+                        NullingLeaf { symbol } => nulling_count(&nulling[..], symbol)?,
+                        // A token has a single derivation.
+                        _ => C::one(),
+                    };
+                    count = count.add(&product);
+                }
+                counts[node.usize()] = Some(count);
+            } else {
+                stack.push((node, true));
+                for (_, summand) in CompactBocage::<G>::summands(&self.graph, node) {
+                    if let Product {
+                        left_factor,
+                        right_factor,
+                        ..
+                    } = summand
+                    {
+                        for factor in Some(left_factor).into_iter().chain(right_factor) {
+                            if counts[factor.usize()].is_none() {
+                                stack.push((factor, false));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        counts[root.usize()].take()
+    }
+
     #[inline]
-    pub fn mark_alive<O: Order>(&mut self, root: NodeHandle, _order: O) {
+    pub fn mark_alive<O: Order>(&mut self, root: NodeHandle, mut order: O) {
         self.gc.liveness.clear();
         self.gc.liveness.grow(self.graph.vec.len(), false);
+        self.gc.selections.clear();
         self.gc.dfs.push(root);
         while let Some(node) = self.gc.dfs.pop() {
             self.gc.liveness.set(node.usize(), true);
-            let summands = CompactBocage::<G>::summands(&self.graph, node);
-            // let summands = order.sum(summands);
-            for summand in summands {
-                // TODO: use order for products.
-                self.gc.dfs_queue_factors(summand);
+            let selection = self.select_summands(node, &mut order);
+            let (mut next, count) = self.first_summand(node);
+            for idx in 0..count {
+                let mut iter = self.graph.iter_from(next);
+                let (handle, summand) = iter.next_with_handle().unwrap();
+                next = iter.handle;
+                // Summands that are left out by the order stay dead.
+                if !is_selected(selection.as_deref(), idx) {
+                    continue;
+                }
+                self.gc.liveness.set(handle.usize(), true);
+                match summand {
+                    Product {
+                        action,
+                        left_factor,
+                        right_factor,
+                    } if O::SELECTS_FACTORS && !self.is_transparent(action) => {
+                        self.mark_factors(handle, action, left_factor, right_factor, &mut order);
+                    }
+                    _ => {
+                        self.gc.dfs_queue_factors(summand);
+                    }
+                }
+            }
+        }
+    }
+
+    // Applies the order to summands of a node. Returns indices of summands that are
+    // kept, or `None` when all are kept.
+    fn select_summands<O: Order>(&self, node: NodeHandle, order: &mut O) -> Option<Vec<usize>> {
+        if !O::SELECTS_SUMMANDS {
+            return None;
+        }
+        let summands: Vec<Node> = CompactBocage::<G>::summands(&self.graph, node)
+            .map(|(_, summand)| summand)
+            .collect();
+        let first_factor_len = |idx: usize| self.first_factor_len(summands[idx]);
+        let selection = order.sum(&summands[..], &first_factor_len);
+        if let Some(ref selection) = selection {
+            check_selection(&selection[..], summands.len());
+        }
+        selection
+    }
+
+    // Returns the handle of the first summand of a node, and the number of summands.
+    #[inline]
+    fn first_summand(&self, node: NodeHandle) -> (NodeHandle, usize) {
+        let mut iter = self.graph.iter_from(node);
+        match iter.next() {
+            Some(Sum { count, .. }) => (iter.handle, count as usize),
+            _ => (node, 1),
+        }
+    }
+
+    // Returns how much input the first factor of a summand spans.
+    fn first_factor_len(&self, summand: Node) -> Option<Position> {
+        let spans = self.spans.as_ref()?;
+        let factor = match summand {
+            Product { left_factor, .. } => left_factor,
+            _ => return Some(0),
+        };
+        match self.graph.get(factor) {
+            NullingLeaf { .. } | Product { action: NULL_ACTION, .. } => Some(0),
+            _ => spans.get(factor).map(|span| span.end - span.start),
+        }
+    }
+
+    // Applies the order to factors of a product of an external rule, and queues factors
+    // that it keeps.
+    fn mark_factors<O: Order>(
+        &mut self,
+        product: NodeHandle,
+        action: u32,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+        order: &mut O,
+    ) {
+        let mut transparent = vec![];
+        let unfoldings = unfold(left, right, |handle| {
+            if !self.is_transparent_node(handle) {
+                return Step::Factor(self.factor_symbol(self.graph.get(handle)));
+            }
+            transparent.push(handle);
+            let selection = self.select_summands(handle, order);
+            let mut pairs = vec![];
+            let summands = CompactBocage::<G>::summands(&self.graph, handle);
+            for (idx, (summand, node)) in summands.enumerate() {
+                if !is_selected(selection.as_deref(), idx) {
+                    continue;
+                }
+                transparent.push(summand);
+                if let Product {
+                    left_factor,
+                    right_factor,
+                    ..
+                } = node
+                {
+                    pairs.push((left_factor, right_factor));
+                }
+            }
+            Step::Unfold(pairs)
+        });
+        for handle in transparent {
+            self.gc.liveness.set(handle.usize(), true);
+        }
+        let action = self.grammar.borrow().external_origin(action).unwrap();
+        let selection = match unfoldings.first() {
+            Some(factors) => order.product(action, &factors[..]),
+            None => None,
+        };
+        for factors in &unfoldings {
+            if let Some(ref selection) = selection {
+                check_selection(&selection[..], factors.len());
+                for &idx in selection.iter().rev() {
+                    self.gc.dfs_queue(factors[idx].1);
+                }
+            } else {
+                for &(_, factor) in factors.iter().rev() {
+                    self.gc.dfs_queue(factor);
+                }
+            }
+        }
+        if let Some(selection) = selection {
+            self.gc.selections.insert(product, selection);
+        }
+    }
+
+    // Flattens factors of a product in every way. Only live summands of transparent sums
+    // are unfolded.
+    pub(super) fn live_unfoldings(
+        &self,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+    ) -> Vec<Vec<(Symbol, NodeHandle)>> {
+        unfold(left, right, |handle| {
+            if !self.is_transparent_node(handle) {
+                return Step::Factor(self.factor_symbol(self.graph.get(handle)));
+            }
+            let is_sum = matches!(self.graph.get(handle), Sum { .. });
+            let mut pairs = vec![];
+            for (summand, node) in CompactBocage::<G>::summands(&self.graph, handle) {
+                if is_sum && !self.gc.liveness.get(summand.usize()).unwrap_or(false) {
+                    continue;
+                }
+                if let Product {
+                    left_factor,
+                    right_factor,
+                    ..
+                } = node
+                {
+                    pairs.push((left_factor, right_factor));
+                }
             }
+            Step::Unfold(pairs)
+        })
+    }
+
+    // Checks whether factors of a node are unfolded in place of the node. A sum is
+    // transparent when its summands are.
+    pub(super) fn is_transparent_node(&self, node: NodeHandle) -> bool {
+        match CompactBocage::<G>::summands(&self.graph, node).next() {
+            Some((_, Product { action, .. })) => self.is_transparent(action),
+            _ => false,
         }
     }
 
     #[inline]
-    fn summands<'a>(graph: &'a Graph, node: NodeHandle) -> impl Iterator<Item = Node> + 'a {
+    fn summands<'a>(
+        graph: &'a Graph,
+        node: NodeHandle,
+    ) -> impl Iterator<Item = (NodeHandle, Node)> + 'a {
         let mut iter = graph.iter_from(node);
-        match iter.peek() {
+        let count = match iter.peek() {
             Some(Sum { count, .. }) => {
                 iter.next();
-                iter.take(count as usize)
+                count
             }
-            _ => iter.take(1),
+            _ => 1,
+        };
+        (0..count).map(move |_| iter.next_with_handle().unwrap())
+    }
+
+    #[inline]
+    pub(super) fn factor_symbol(&self, node: Node) -> Symbol {
+        match node {
+            Sum { nonterminal, .. } => nonterminal,
+            Product { action, .. } => self.grammar.borrow().get_lhs(action),
+            NullingLeaf { symbol } | Evaluated { symbol } => symbol,
         }
     }
 
@@ -146,9 +434,9 @@ where
                     // Add omitted phantom syms here.
                     if let Some((sym, dir)) = self.grammar.borrow().nulling(action) {
                         let (left, right) = if dir {
-                            (*left_factor, NodeHandle::nulling(sym))
+                            (*left_factor, self.nulling_node(sym))
                         } else {
-                            (NodeHandle::nulling(sym), *left_factor)
+                            (self.nulling_node(sym), *left_factor)
                         };
                         *left_factor = left;
                         *right_factor = Some(right);
@@ -160,39 +448,121 @@ where
         node
     }
 
+    #[inline]
+    fn nulling_node(&self, symbol: Symbol) -> NodeHandle {
+        self.nulling_nodes[symbol.usize()]
+    }
+
     #[inline]
     pub(super) fn is_transparent(&self, action: u32) -> bool {
         action == NULL_ACTION || self.grammar.borrow().external_origin(action).is_none()
     }
 
-    // fn mark_and_sweep(&mut self, root: NodeHandle) {
-    //     self.mark_alive(root);
-    //     self.sweep_garbage();
-    //     self.update_nulling_leaf_count();
-    // }
-
-    // fn sweep_garbage(&mut self) {
-    //     let count = self.relocate_marked();
-    //     self.graph.truncate(count);
-    // }
-
-    // fn update_nulling_leaf_count(&mut self) {
-    //     let prev_count = self.nulling_leaf_count;
-    //     self.nulling_leaf_count = self.gc.liveness.iter().take(prev_count).filter(|x| x).count();
-    // }
-
-    // fn relocate_marked(&mut self) -> usize {
-    //     let mut destination = self.graph.iter();
-    //     let mut count = 0;
-    //     // ... TODO: relocate
-    //     for (alive, source) in self.gc.liveness.iter().zip(self.graph.iter()) {
-    //         if alive {
-    //             destination.next().unwrap().cell.set(*source);
-    //             count += 1;
-    //         }
-    //     }
-    //     count
-    // }
+    /// Keeps nodes that are reachable from the given roots, and discards all other nodes.
+    /// The graph is compacted. Handles of kept nodes must be updated with `relocate`.
+    pub fn mark_and_sweep<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = NodeHandle>,
+    {
+        debug_assert_eq!(self.summand_count, 0, "collection within a sum");
+        self.mark_reachable(roots);
+        self.sweep_garbage();
+    }
+
+    /// Returns the new handle of a node that was kept by the latest collection.
+    #[inline]
+    pub fn relocate(&self, node: NodeHandle) -> Option<NodeHandle> {
+        self.gc
+            .relocation
+            .get(node.usize())
+            .and_then(|handle| handle.to_option())
+    }
+
+    fn mark_reachable<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = NodeHandle>,
+    {
+        self.gc.liveness.clear();
+        self.gc.liveness.grow(self.graph.vec.len(), false);
+        self.gc.dfs.extend(roots);
+        while let Some(node) = self.gc.dfs.pop() {
+            self.gc.liveness.set(node.usize(), true);
+            for (_, summand) in CompactBocage::<G>::summands(&self.graph, node) {
+                self.gc.dfs_queue_factors(summand);
+            }
+        }
+    }
+
+    fn sweep_garbage(&mut self) {
+        let len = self.relocate_marked();
+        self.graph.vec.truncate(len);
+        if let Some(ref mut spans) = self.spans {
+            spans.relocate(&self.gc.relocation[..]);
+        }
+        let relocation = &self.gc.relocation;
+        self.leaf_values.retain(|&(node, _)| relocation[node.usize()].to_option().is_some());
+        for &mut (ref mut node, _) in &mut self.leaf_values {
+            *node = relocation[node.usize()];
+        }
+        // Liveness must be marked again before traversal.
+        self.gc.liveness.truncate(0);
+        self.gc.selections.clear();
+    }
+
+    fn relocate_marked(&mut self) -> usize {
+        // Leaves for nulling symbols are kept in place.
+        let nulling_count = self.nulling_symbol_count();
+        let relocation = &mut self.gc.relocation;
+        relocation.clear();
+        relocation.resize(self.graph.vec.len(), NULL_HANDLE);
+        // Nodes are moved towards the beginning. Distances between nodes can only shrink,
+        // so each node fits within the space of nodes that were already read.
+        let mut destination = 0;
+        let mut count = 0;
+        let mut kept_summands = 0;
+        let mut dropped_summands = 0;
+        let mut iter = self.graph.iter_from(NodeHandle(0));
+        while let Some((source, node)) = iter.next_with_handle() {
+            let keep = if count < nulling_count {
+                true
+            } else if kept_summands > 0 {
+                kept_summands -= 1;
+                true
+            } else if dropped_summands > 0 {
+                dropped_summands -= 1;
+                false
+            } else {
+                // A sum is moved together with its summands.
+                let alive = self.gc.liveness[source.usize()];
+                if let Sum { count, .. } = node {
+                    if alive {
+                        kept_summands = count;
+                    } else {
+                        dropped_summands = count;
+                    }
+                }
+                alive
+            };
+            count += 1;
+            if keep {
+                let node = match node {
+                    Product {
+                        action,
+                        left_factor,
+                        right_factor,
+                    } => Product {
+                        action,
+                        left_factor: relocation[left_factor.usize()],
+                        right_factor: right_factor.map(|factor| relocation[factor.usize()]),
+                    },
+                    other => other,
+                };
+                relocation[source.usize()] = NodeHandle(destination as Position);
+                destination += self.graph.write(NodeHandle(destination as Position), node);
+            }
+        }
+        destination
+    }
 }
 
 impl MarkAndSweep {
@@ -205,18 +575,21 @@ impl MarkAndSweep {
                 ..
             } => {
                 if let Some(factor) = right_factor {
-                    if let Some(false) = self.liveness.get(factor.usize()) {
-                        self.dfs.push(factor);
-                    }
-                }
-                if let Some(false) = self.liveness.get(left_factor.usize()) {
-                    self.dfs.push(left_factor);
+                    self.dfs_queue(factor);
                 }
+                self.dfs_queue(left_factor);
             }
             NullingLeaf { .. } | Evaluated { .. } => {}
             Sum { .. } => unreachable!(),
         }
     }
+
+    #[inline]
+    fn dfs_queue(&mut self, node: NodeHandle) {
+        if let Some(false) = self.liveness.get(node.usize()) {
+            self.dfs.push(node);
+        }
+    }
 }
 
 impl<G> Forest for CompactBocage<G>
@@ -230,7 +603,7 @@ where
 
     #[inline]
     fn begin_sum(&mut self) {
-        self.first_summand = NodeHandle(self.graph.vec.len() as u32);
+        self.first_summand = NodeHandle(self.graph.vec.len() as Position);
     }
 
     #[inline]
@@ -244,10 +617,10 @@ where
     }
 
     #[inline]
-    fn sum(&mut self, lhs_sym: Symbol, _origin: u32) -> Self::NodeRef {
+    fn sum(&mut self, lhs_sym: Symbol, origin: Origin) -> Self::NodeRef {
         unsafe {
             match self.summand_count {
-                0 => hint::unreachable_unchecked(),
+                0 => invariant::unreachable("a sum has summands"),
                 1 => {}
                 summand_count => {
                     // Slower case: ambiguous node.
@@ -261,16 +634,42 @@ where
         };
         let result = self.first_summand;
         self.summand_count = 0;
+        if let Some(ref mut spans) = self.spans {
+            spans.sum(result, origin);
+        }
         result
     }
 
     #[inline]
-    fn leaf(&mut self, token: Symbol, _pos: u32, _value: Self::LeafValue) -> Self::NodeRef {
-        self.graph.push(Evaluated { symbol: token })
+    fn leaf(&mut self, token: Symbol, pos: Origin, value: Self::LeafValue) -> Self::NodeRef {
+        let result = self.graph.push(Evaluated { symbol: token });
+        self.leaf_values.push((result, value));
+        if let Some(ref mut spans) = self.spans {
+            spans.leaf(result, pos);
+        }
+        result
     }
 
     #[inline]
     fn nulling(&self, token: Symbol) -> Self::NodeRef {
-        NodeHandle::nulling(token)
+        self.nulling_node(token)
+    }
+}
+
+impl<G> CollectGarbage for CompactBocage<G>
+where
+    G: Borrow<InternalGrammar>,
+{
+    #[inline]
+    fn mark_and_sweep<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = NodeHandle>,
+    {
+        CompactBocage::mark_and_sweep(self, roots)
+    }
+
+    #[inline]
+    fn relocate(&self, node: NodeHandle) -> Option<NodeHandle> {
+        CompactBocage::relocate(self, node)
     }
 }
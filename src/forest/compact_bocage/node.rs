@@ -1,11 +1,13 @@
 use std::cell::Cell;
-use std::hint;
+use std::mem;
 
 use cfg::symbol::Symbol;
 
 pub use self::Node::*;
 use self::Tag::*;
 use forest::node_handle::{NodeHandle, NULL_HANDLE};
+use invariant;
+use item::Position;
 
 pub struct Graph {
     pub(crate) vec: Vec<Cell<u16>>,
@@ -19,7 +21,7 @@ impl Graph {
     }
 
     pub(crate) fn push(&mut self, node: Node) -> NodeHandle {
-        let position = self.vec.len() as u32;
+        let position = self.vec.len() as Position;
         let (node_repr, size) = node.to_repr(position);
         unsafe {
             self.vec
@@ -34,20 +36,32 @@ impl Graph {
         while current_handle.usize() < handle.usize() + size {
             let current_node = self.get(current_handle);
             self.push(current_node);
-            current_handle.0 += current_node.classify(current_handle.0).size() as u32;
+            current_handle.0 += current_node.classify(current_handle.0).size() as Position;
         }
         for i in 0..size {
             unsafe {
                 self.vec[handle.usize() + i].set(node_repr.fields[i]);
             }
         }
-        handle.0 += size as u32;
+        handle.0 += size as Position;
         while handle.0 < current_handle.0 {
             self.vec[handle.usize()].set(NopTag.to_u16());
             handle.0 += 1;
         }
     }
 
+    /// Overwrites cells at the given position. The node must fit within the cells of nodes
+    /// that are no longer needed. Returns the size of the written node.
+    pub(crate) fn write(&self, handle: NodeHandle, node: Node) -> usize {
+        let (node_repr, size) = node.to_repr(handle.0);
+        for i in 0..size {
+            unsafe {
+                self.vec[handle.usize() + i].set(node_repr.fields[i]);
+            }
+        }
+        size
+    }
+
     pub(crate) fn get(&self, handle: NodeHandle) -> Node {
         self.iter_from(handle).next().unwrap()
     }
@@ -81,14 +95,16 @@ impl<'a> Iterator for Iter<'a> {
                 self.handle.0 += 1;
                 self.next()
             } else {
-                let mut node_repr = NodeRepr { fields: [0; 6] };
+                let mut node_repr = NodeRepr {
+                    fields: [0; REPR_LEN],
+                };
                 node_repr.fields[0] = head;
                 let slice = &self.vec[self.handle.usize() + 1..self.handle.usize() + tag.size()];
                 for (i, val) in slice.iter().enumerate() {
                     node_repr.fields[1 + i] = val.get();
                 }
                 let result = node_repr.expand(tag, self.handle.0);
-                self.handle.0 += tag.size() as u32;
+                self.handle.0 += tag.size() as Position;
                 Some(result)
             }
         }
@@ -100,6 +116,19 @@ impl<'a> Iter<'a> {
     pub(crate) fn peek(&mut self) -> Option<Node> {
         self.clone().next()
     }
+
+    /// Skips padding and returns the next node together with its handle.
+    #[inline]
+    pub(crate) fn next_with_handle(&mut self) -> Option<(NodeHandle, Node)> {
+        while let Some(cell) = self.vec.get(self.handle.usize()) {
+            if cell.get() != NopTag.to_u16() {
+                break;
+            }
+            self.handle.0 += 1;
+        }
+        let handle = self.handle;
+        self.next().map(|node| (handle, node))
+    }
 }
 
 // Node variants `Sum`/`Product` are better known in literature as `OR`/`AND`.
@@ -130,7 +159,7 @@ pub enum Node {
 
 #[derive(Clone, Copy)]
 union NodeRepr {
-    fields: [u16; 6],
+    fields: [u16; REPR_LEN],
     small_sum: SmallSumRepr,
     small_link: SmallLinkRepr,
     medium_link: MediumLinkRepr,
@@ -150,8 +179,12 @@ struct SmallSumRepr {
     count: u8,
 }
 
+// Wide nodes begin with a header cell that holds only the tag, so that their values
+// never overlap the tag.
 #[derive(Clone, Copy)]
+#[repr(C, packed)]
 struct SumRepr {
+    header: u16,
     count: u32,
     nonterminal: Symbol,
 }
@@ -178,10 +211,10 @@ struct SmallProductRepr {
 }
 
 #[derive(Clone, Copy)]
-#[repr(packed)]
+#[repr(C, packed)]
 struct ProductRepr {
-    upper_action: u16,
-    lower_action: u16,
+    header: u16,
+    action: u32,
     left_factor: NodeHandle,
     right_factor: NodeHandle,
 }
@@ -192,7 +225,9 @@ struct SmallNullingLeafRepr {
 }
 
 #[derive(Clone, Copy)]
+#[repr(C, packed)]
 struct LeafRepr {
+    header: u16,
     symbol: Symbol,
 }
 
@@ -298,21 +333,26 @@ impl Tag {
             SmallProductTag => 2,
             SmallLeafTag => 1,
             SmallNullingLeafTag => 1,
-            LeafTag => 4,
-            SumTag => 4,
-            ProductTag => 6,
+            LeafTag => 3,
+            SumTag => 5,
+            ProductTag => REPR_LEN,
             NopTag => 1,
         }
     }
 }
 
 const TAG_BIT: usize = 5 + 8;
+// The number of cells of the largest node, which is a product with a header, an action
+// and two handles.
+const REPR_LEN: usize = 3 + mem::size_of::<NodeHandle>();
 const TAG_MASK: u16 = 0b111 << TAG_BIT;
 const SMALL_LEAF_TAG_MASK: u16 = 0b1111 << (TAG_BIT - 1);
+// Wide leaves share a tag. Their header tells nulling leaves apart.
+const NULLING_LEAF_FLAG: u16 = 1;
 pub(super) const NULL_ACTION: u32 = !((TAG_MASK as u32) << 16);
 
 impl NodeRepr {
-    fn expand(self, tag: Tag, position: u32) -> Node {
+    fn expand(self, tag: Tag, position: Position) -> Node {
         unsafe {
             match (self, tag) {
                 (
@@ -326,7 +366,10 @@ impl NodeRepr {
                 },
                 (
                     NodeRepr {
-                        sum: SumRepr { nonterminal, count },
+                        sum:
+                            SumRepr {
+                                nonterminal, count, ..
+                            },
                     },
                     SumTag,
                 ) => Sum { nonterminal, count },
@@ -337,7 +380,7 @@ impl NodeRepr {
                     SmallLinkTag,
                 ) => Product {
                     action: action as u32,
-                    left_factor: NodeHandle(position - distance as u32),
+                    left_factor: NodeHandle(position - distance as Position),
                     right_factor: None,
                 },
                 (
@@ -347,7 +390,7 @@ impl NodeRepr {
                     MediumLinkTag,
                 ) => Product {
                     action: action as u32,
-                    left_factor: NodeHandle(position - distance as u32),
+                    left_factor: NodeHandle(position - distance as Position),
                     right_factor: None,
                 },
                 (
@@ -362,22 +405,22 @@ impl NodeRepr {
                     SmallProductTag,
                 ) => Product {
                     action: action as u32,
-                    left_factor: NodeHandle(position - left_distance as u32),
-                    right_factor: Some(NodeHandle(position - right_distance as u32)),
+                    left_factor: NodeHandle(position - left_distance as Position),
+                    right_factor: Some(NodeHandle(position - right_distance as Position)),
                 },
                 (
                     NodeRepr {
                         product:
                             ProductRepr {
-                                upper_action,
-                                lower_action,
+                                action,
                                 left_factor,
                                 right_factor,
+                                ..
                             },
                     },
                     ProductTag,
                 ) => Product {
-                    action: (upper_action as u32) << 16 | (lower_action as u32),
+                    action,
                     left_factor,
                     right_factor: right_factor.to_option(),
                 },
@@ -399,10 +442,16 @@ impl NodeRepr {
                 },
                 (
                     NodeRepr {
-                        leaf: LeafRepr { symbol },
+                        leaf: LeafRepr { header, symbol },
                     },
                     LeafTag,
-                ) => Evaluated { symbol },
+                ) => {
+                    if header & NULLING_LEAF_FLAG != 0 {
+                        NullingLeaf { symbol }
+                    } else {
+                        Evaluated { symbol }
+                    }
+                }
                 _ => unreachable!(),
             }
         }
@@ -411,7 +460,7 @@ impl NodeRepr {
 
 impl Node {
     #[inline]
-    fn to_repr(self, position: u32) -> (NodeRepr, usize) {
+    fn to_repr(self, position: Position) -> (NodeRepr, usize) {
         let tag = self.classify(position);
         unsafe {
             let mut result = match (self, tag) {
@@ -422,7 +471,11 @@ impl Node {
                     },
                 },
                 (Sum { nonterminal, count }, SumTag) => NodeRepr {
-                    sum: SumRepr { nonterminal, count },
+                    sum: SumRepr {
+                        header: 0,
+                        nonterminal,
+                        count,
+                    },
                 },
                 (
                     Product {
@@ -473,8 +526,8 @@ impl Node {
                     ProductTag,
                 ) => NodeRepr {
                     product: ProductRepr {
-                        upper_action: (action >> 16) as u16,
-                        lower_action: action as u16,
+                        header: 0,
+                        action,
                         left_factor,
                         right_factor: right_factor.unwrap_or(NULL_HANDLE),
                     },
@@ -485,7 +538,10 @@ impl Node {
                     },
                 },
                 (NullingLeaf { symbol }, LeafTag) => NodeRepr {
-                    leaf: LeafRepr { symbol },
+                    leaf: LeafRepr {
+                        header: NULLING_LEAF_FLAG,
+                        symbol,
+                    },
                 },
                 (Evaluated { symbol }, SmallLeafTag) => NodeRepr {
                     small_leaf: SmallLeafRepr {
@@ -493,7 +549,7 @@ impl Node {
                     },
                 },
                 (Evaluated { symbol }, LeafTag) => NodeRepr {
-                    leaf: LeafRepr { symbol },
+                    leaf: LeafRepr { header: 0, symbol },
                 },
                 _ => unreachable!(),
             };
@@ -503,7 +559,7 @@ impl Node {
     }
 
     #[inline]
-    pub(super) fn classify(self, position: u32) -> Tag {
+    pub(super) fn classify(self, position: Position) -> Tag {
         match self {
             Product {
                 left_factor,
@@ -563,16 +619,8 @@ impl Node {
     }
 }
 
-#[inline]
-unsafe fn unwrap_unchecked<T>(opt: Option<T>) -> T {
-    match opt {
-        Some(val) => val,
-        None => hint::unreachable_unchecked(),
-    }
-}
-
 #[inline]
 unsafe fn get_and_erase_tag(field: u16) -> (Tag, u16) {
-    let tag = unwrap_unchecked(Tag::from_u16(field));
+    let tag = invariant::unwrap(Tag::from_u16(field), "nodes have a valid tag");
     (tag, field & !tag.mask())
 }
@@ -5,8 +5,9 @@ use bit_vec::BitVec;
 use cfg::symbol::Symbol;
 
 use forest::compact_bocage::node::Node::*;
-use forest::compact_bocage::node::{Iter, Node, Tag};
+use forest::compact_bocage::node::{Iter, Tag};
 use forest::node_handle::NodeHandle;
+use forest::span::Span;
 use forest::CompactBocage;
 use grammar::InternalGrammar;
 
@@ -20,7 +21,7 @@ impl<G> CompactBocage<G> {
             graph_iter: self.graph.iter_from(NodeHandle(0)),
             liveness: &self.gc.liveness,
             factor_stack: vec![],
-            factor_traversal: vec![],
+            unfoldings: vec![],
         }
     }
 }
@@ -32,8 +33,8 @@ pub struct Traverse<'f, G> {
     liveness: &'f BitVec,
     // Space for unrolling factors
     factor_stack: Vec<(Symbol, NodeHandle)>,
-    // Scratch space for traversal
-    factor_traversal: Vec<NodeHandle>,
+    // Unfoldings of the current product that are not passed on yet, in reverse order
+    unfoldings: Vec<Vec<(Symbol, NodeHandle)>>,
 }
 
 impl<'f, G> Traverse<'f, G>
@@ -57,12 +58,14 @@ where
                     if self.bocage.is_transparent(action) {
                         continue;
                     }
-                    let products = iter.take(1);
                     return Some(TraversalHandle {
                         iter,
+                        span: self.bocage.span(iter.handle),
                         symbol: self.bocage.grammar.borrow().get_lhs(action),
                         item: SumHandle(Products {
-                            products,
+                            products: iter,
+                            count: 1,
+                            action: 0,
                             traverse: self,
                         }),
                     });
@@ -71,17 +74,24 @@ where
                     nonterminal: symbol,
                     count,
                 } => {
-                    let products = self.graph_iter.take(count as usize);
+                    let products = self.graph_iter;
                     for _ in 0..count {
                         let p = self.graph_iter.handle;
                         let n = self.graph_iter.next();
                         println!("next_node product @{:?} {:?}", p, n);
                     }
+                    if self.bocage.is_transparent_node(iter.handle) {
+                        // Its summands are unfolded into products of external rules.
+                        continue;
+                    }
                     return Some(TraversalHandle {
                         iter,
+                        span: self.bocage.span(iter.handle),
                         symbol,
                         item: SumHandle(Products {
                             products,
+                            count,
+                            action: 0,
                             traverse: self,
                         }),
                     });
@@ -89,6 +99,7 @@ where
                 NullingLeaf { symbol } => {
                     return Some(TraversalHandle {
                         iter,
+                        span: self.bocage.span(iter.handle),
                         symbol,
                         item: NullingHandle,
                     });
@@ -96,8 +107,13 @@ where
                 Evaluated { symbol, .. } => {
                     return Some(TraversalHandle {
                         iter,
+                        span: self.bocage.span(iter.handle),
                         symbol,
-                        item: LeafHandle,
+                        item: LeafHandle(
+                            self.bocage
+                                .leaf_value(iter.handle)
+                                .expect("a leaf for a token has a value"),
+                        ),
                     });
                 }
             }
@@ -105,45 +121,50 @@ where
         None
     }
 
-    fn unfold_factors(&mut self, left: NodeHandle, right: Option<NodeHandle>) {
+    // Passes factors of a product on in place, unless the product has a selection or
+    // transparent factors. Returns whether the factors were passed on.
+    fn direct_factors(
+        &mut self,
+        product: NodeHandle,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+    ) -> bool {
+        let bocage = self.bocage;
+        if bocage.gc.selections.contains_key(&product)
+            || bocage.is_transparent_node(left)
+            || matches!(right, Some(right) if bocage.is_transparent_node(right))
+        {
+            return false;
+        }
         self.factor_stack.clear();
-        self.enqueue_for_unfold(left, right);
-        while let Some(node) = self.pop_for_unfold() {
-            match node {
-                (
-                    Product {
-                        left_factor,
-                        right_factor,
-                        ..
-                    },
-                    _,
-                ) => {
-                    self.enqueue_for_unfold(left_factor, right_factor);
-                }
-                (Evaluated { symbol }, handle) => {
-                    self.factor_stack.push((symbol, handle));
-                }
-                _ => unreachable!(),
-            }
+        for handle in iter::once(left).chain(right) {
+            let symbol = bocage.factor_symbol(bocage.graph.get(handle));
+            self.factor_stack.push((symbol, handle));
         }
+        true
     }
 
-    fn enqueue_for_unfold(&mut self, left: NodeHandle, right: Option<NodeHandle>) {
-        if let Some(right) = right {
-            self.factor_traversal.push(right);
+    fn unfold_factors(
+        &mut self,
+        product: NodeHandle,
+        left: NodeHandle,
+        right: Option<NodeHandle>,
+    ) {
+        let selection = self.bocage.gc.selections.get(&product);
+        for factors in self.bocage.live_unfoldings(left, right).into_iter().rev() {
+            let factors = match selection {
+                Some(selection) => selection.iter().map(|&idx| factors[idx]).collect(),
+                None => factors,
+            };
+            self.unfoldings.push(factors);
         }
-        self.factor_traversal.push(left);
-    }
-
-    fn pop_for_unfold(&mut self) -> Option<(Node, NodeHandle)> {
-        self.factor_traversal
-            .pop()
-            .map(|handle| (self.bocage.graph.get(handle), handle))
     }
 }
 
 pub struct TraversalHandle<'f, 't, G> {
     pub(crate) iter: Iter<'f>,
+    /// The input range covered by this node, if spans are recorded.
+    pub span: Option<Span>,
     pub symbol: Symbol,
     pub item: HandleVariant<'f, 't, G>,
 }
@@ -151,11 +172,14 @@ pub struct TraversalHandle<'f, 't, G> {
 pub enum HandleVariant<'f, 't, G> {
     SumHandle(Products<'f, 't, G>),
     NullingHandle,
-    LeafHandle,
+    LeafHandle(u32),
 }
 
 pub struct Products<'f, 't, G> {
-    products: iter::Take<Iter<'f>>,
+    products: Iter<'f>,
+    count: u32,
+    // Action of the product that is unfolded
+    action: u32,
     traverse: &'t mut Traverse<'f, G>,
 }
 
@@ -169,7 +193,23 @@ where
     G: Borrow<InternalGrammar>,
 {
     pub fn next_product<'p>(&'p mut self) -> Option<ProductHandle> {
-        while let Some(node) = self.products.next() {
+        loop {
+            // A product may be unfolded in more than one way.
+            if let Some(factors) = self.traverse.unfoldings.pop() {
+                self.traverse.factor_stack = factors;
+                return Some(ProductHandle {
+                    action: self.action,
+                    factors: &self.traverse.factor_stack[..],
+                });
+            }
+            if self.count == 0 {
+                return None;
+            }
+            self.count -= 1;
+            let (handle, node) = self.products.next_with_handle().unwrap();
+            if !self.traverse.liveness[handle.usize()] {
+                continue;
+            }
             match node {
                 Product {
                     left_factor,
@@ -183,17 +223,20 @@ where
                         .borrow()
                         .external_origin(action);
                     if let Some(action) = origin {
-                        self.traverse.unfold_factors(left_factor, right_factor);
-                        return Some(ProductHandle {
-                            action,
-                            factors: &self.traverse.factor_stack[..],
-                        });
+                        self.action = action;
+                        if self.traverse.direct_factors(handle, left_factor, right_factor) {
+                            return Some(ProductHandle {
+                                action,
+                                factors: &self.traverse.factor_stack[..],
+                            });
+                        }
+                        self.traverse
+                            .unfold_factors(handle, left_factor, right_factor);
                     }
                 }
                 _ => unreachable!(),
             }
         }
-        None
     }
 }
 
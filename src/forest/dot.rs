@@ -0,0 +1,86 @@
+//! Output of forests in the Graphviz DOT format.
+
+use std::io::{self, Write};
+
+use cfg::Symbol;
+
+use forest::node_handle::NodeHandle;
+use grammar::InternalGrammar;
+
+/// Writes nodes and edges of a forest graph. Nodes are identified by their handles.
+pub(crate) struct DotWriter<'w, W: 'w> {
+    out: &'w mut W,
+}
+
+impl<'w, W: Write> DotWriter<'w, W> {
+    pub(crate) fn new(out: &'w mut W) -> io::Result<Self> {
+        writeln!(out, "digraph forest {{")?;
+        writeln!(out, "    node [fontname=\"monospace\"];")?;
+        Ok(DotWriter { out })
+    }
+
+    /// Ambiguous sums are highlighted.
+    pub(crate) fn sum(&mut self, node: NodeHandle, label: &str, ambiguous: bool) -> io::Result<()> {
+        let style = if ambiguous {
+            ", style=filled, fillcolor=\"#ff9999\", penwidth=2"
+        } else {
+            ""
+        };
+        writeln!(
+            self.out,
+            "    n{} [shape=ellipse, label=\"{}\"{}];",
+            node.0,
+            escape(label),
+            style
+        )
+    }
+
+    /// Products without an external rule are intermediate.
+    pub(crate) fn product(&mut self, node: NodeHandle, rule: Option<u32>) -> io::Result<()> {
+        match rule {
+            Some(rule) => writeln!(
+                self.out,
+                "    n{} [shape=box, label=\"rule {}\"];",
+                node.0, rule
+            ),
+            None => writeln!(self.out, "    n{} [shape=point];", node.0),
+        }
+    }
+
+    pub(crate) fn leaf(&mut self, node: NodeHandle, label: &str) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "    n{} [shape=plaintext, label=\"{}\"];",
+            node.0,
+            escape(label)
+        )
+    }
+
+    pub(crate) fn summand_edge(&mut self, sum: NodeHandle, summand: NodeHandle) -> io::Result<()> {
+        writeln!(self.out, "    n{} -> n{} [style=dashed];", sum.0, summand.0)
+    }
+
+    pub(crate) fn factor_edge(
+        &mut self,
+        product: NodeHandle,
+        factor: NodeHandle,
+    ) -> io::Result<()> {
+        writeln!(self.out, "    n{} -> n{};", product.0, factor.0)
+    }
+
+    pub(crate) fn finish(self) -> io::Result<()> {
+        writeln!(self.out, "}}")
+    }
+}
+
+/// Returns the name of an external symbol, or its ID when the grammar has no name for it.
+pub(crate) fn symbol_label(grammar: &InternalGrammar, symbol: Symbol) -> String {
+    match grammar.symbol_name(symbol) {
+        Some(name) => name.to_string(),
+        None => format!("#{}", symbol.usize()),
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
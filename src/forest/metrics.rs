@@ -0,0 +1,106 @@
+use cfg::symbol::Symbol;
+
+use forest::{CollectGarbage, Forest};
+use item::{CompletedItem, Origin};
+
+/// Counts of nodes that were given to a forest.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ForestMetrics {
+    /// The number of sums, including sums of a single summand.
+    pub sums: usize,
+    /// The number of sums with more than one summand.
+    pub ambiguous_sums: usize,
+    /// The number of summands of all sums.
+    pub summands: usize,
+    /// The number of leaves.
+    pub leaves: usize,
+}
+
+/// A forest that counts nodes and forwards them to an inner forest. Counting takes a few
+/// additions per node.
+#[derive(Clone, Debug, Default)]
+pub struct MetricsForest<F> {
+    pub forest: F,
+    metrics: ForestMetrics,
+    // Summands of the current sum.
+    current_summands: usize,
+}
+
+impl<F> MetricsForest<F> {
+    pub fn new(forest: F) -> Self {
+        MetricsForest {
+            forest,
+            metrics: ForestMetrics::default(),
+            current_summands: 0,
+        }
+    }
+
+    /// Returns counts of nodes given so far.
+    pub fn metrics(&self) -> &ForestMetrics {
+        &self.metrics
+    }
+
+    /// Returns the inner forest together with the counts.
+    pub fn into_inner(self) -> (F, ForestMetrics) {
+        (self.forest, self.metrics)
+    }
+}
+
+impl<F> Forest for MetricsForest<F>
+where
+    F: Forest,
+{
+    type NodeRef = F::NodeRef;
+    type LeafValue = F::LeafValue;
+
+    const FOREST_BYTES_PER_RECOGNIZER_BYTE: usize = F::FOREST_BYTES_PER_RECOGNIZER_BYTE;
+
+    #[inline]
+    fn begin_sum(&mut self) {
+        self.current_summands = 0;
+        self.forest.begin_sum();
+    }
+
+    #[inline]
+    fn push_summand(&mut self, item: CompletedItem<Self::NodeRef>) {
+        self.current_summands += 1;
+        self.forest.push_summand(item);
+    }
+
+    #[inline]
+    fn sum(&mut self, lhs_sym: Symbol, origin: Origin) -> Self::NodeRef {
+        self.metrics.sums += 1;
+        self.metrics.summands += self.current_summands;
+        if self.current_summands > 1 {
+            self.metrics.ambiguous_sums += 1;
+        }
+        self.forest.sum(lhs_sym, origin)
+    }
+
+    #[inline]
+    fn leaf(&mut self, token: Symbol, pos: Origin, value: Self::LeafValue) -> Self::NodeRef {
+        self.metrics.leaves += 1;
+        self.forest.leaf(token, pos, value)
+    }
+
+    #[inline]
+    fn nulling(&self, token: Symbol) -> Self::NodeRef {
+        self.forest.nulling(token)
+    }
+}
+
+impl<F> CollectGarbage for MetricsForest<F>
+where
+    F: CollectGarbage,
+{
+    fn mark_and_sweep<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = Self::NodeRef>,
+    {
+        self.forest.mark_and_sweep(roots);
+    }
+
+    fn relocate(&self, node: Self::NodeRef) -> Option<Self::NodeRef> {
+        self.forest.relocate(node)
+    }
+}
@@ -1,16 +1,30 @@
+pub mod arena;
 pub mod bocage;
 pub mod compact_bocage;
+pub mod dot;
+pub mod metrics;
 pub mod node_handle;
 pub mod null_forest;
-
+pub mod order;
+pub mod span;
+pub mod tee;
+pub mod tree_count;
+mod unfold;
+
+pub use self::arena::ArenaForest;
+pub use self::bocage::frozen::FrozenBocage;
 pub use self::bocage::Bocage;
 pub use self::compact_bocage::CompactBocage;
+pub use self::metrics::MetricsForest;
 pub use self::null_forest::NullForest;
+pub use self::span::Span;
+pub use self::tee::Tee;
+pub use self::tree_count::TreeCount;
 
 use cfg::Symbol;
 use std::fmt;
 
-use item::CompletedItem;
+use item::{CompletedItem, Origin};
 
 pub trait Forest {
     /// Reference to a node.
@@ -23,9 +37,25 @@ pub trait Forest {
 
     fn push_summand(&mut self, item: CompletedItem<Self::NodeRef>);
 
-    fn sum(&mut self, lhs_sym: Symbol, origin: u32) -> Self::NodeRef;
+    /// Creates a node for summands. The node starts at the `origin` location and ends
+    /// at the current location.
+    fn sum(&mut self, lhs_sym: Symbol, origin: Origin) -> Self::NodeRef;
 
-    fn leaf(&mut self, token: Symbol, pos: u32, value: Self::LeafValue) -> Self::NodeRef;
+    /// Creates a node for a token that ends at the `pos` location.
+    fn leaf(&mut self, token: Symbol, pos: Origin, value: Self::LeafValue) -> Self::NodeRef;
 
+    /// Returns the node for a nulling symbol. Nulling symbols are not remapped along with
+    /// the grammar, so nulling leaves of all forests hold external symbols.
     fn nulling(&self, token: Symbol) -> Self::NodeRef;
 }
+
+/// A forest that can discard nodes which are no longer referenced.
+pub trait CollectGarbage: Forest {
+    /// Keeps nodes that are reachable from the given roots, and discards all other nodes.
+    fn mark_and_sweep<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = Self::NodeRef>;
+
+    /// Returns the new reference to a node that was kept by the latest collection.
+    fn relocate(&self, node: Self::NodeRef) -> Option<Self::NodeRef>;
+}
@@ -1,18 +1,20 @@
 use cfg::Symbol;
 
+use item::Position;
+
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
-pub struct NodeHandle(pub(crate) u32);
+pub struct NodeHandle(pub(crate) Position);
 
-pub(super) const NULL_HANDLE: NodeHandle = NodeHandle(0xFFFF_FFFF);
+pub(super) const NULL_HANDLE: NodeHandle = NodeHandle(!0);
 
 impl NodeHandle {
     #[inline]
     pub(super) fn nulling(symbol: Symbol) -> Self {
-        NodeHandle(symbol.usize() as u32)
+        NodeHandle(symbol.usize() as Position)
     }
 
     #[inline]
-    pub(super) fn usize(self) -> usize {
+    pub(crate) fn usize(self) -> usize {
         self.0 as usize
     }
 
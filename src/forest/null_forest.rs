@@ -1,7 +1,7 @@
 use cfg::symbol::Symbol;
 
-use forest::Forest;
-use item::CompletedItem;
+use forest::{CollectGarbage, Forest};
+use item::{CompletedItem, Origin};
 
 /// An empty forest.
 pub struct NullForest;
@@ -13,7 +13,7 @@ impl Forest for NullForest {
     const FOREST_BYTES_PER_RECOGNIZER_BYTE: usize = 0;
 
     #[inline(always)]
-    fn leaf(&mut self, _: Symbol, _: u32, _: ()) {}
+    fn leaf(&mut self, _: Symbol, _: Origin, _: ()) {}
     #[inline(always)]
     fn nulling(&self, _: Symbol) {}
     #[inline(always)]
@@ -21,7 +21,21 @@ impl Forest for NullForest {
     #[inline(always)]
     fn push_summand(&mut self, _item: CompletedItem<Self::NodeRef>) {}
     #[inline(always)]
-    fn sum(&mut self, _lhs_sym: Symbol, _origin: u32) -> Self::NodeRef {
+    fn sum(&mut self, _lhs_sym: Symbol, _origin: Origin) -> Self::NodeRef {
         ()
     }
 }
+
+impl CollectGarbage for NullForest {
+    #[inline(always)]
+    fn mark_and_sweep<I>(&mut self, _roots: I)
+    where
+        I: IntoIterator<Item = ()>,
+    {
+    }
+
+    #[inline(always)]
+    fn relocate(&self, _node: ()) -> Option<()> {
+        Some(())
+    }
+}
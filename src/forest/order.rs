@@ -0,0 +1,156 @@
+//! Orders that work with summands of either bocage.
+//!
+//! Each bocage has its own `Order` trait, because summands are stored differently. The
+//! orders here see summands through the `Summand` trait.
+
+use forest::bocage::node::{self as bocage_node, CompactNode};
+use forest::compact_bocage::node as compact_node;
+use grammar::InternalGrammar;
+use item::Position;
+
+/// A summand as seen by an order.
+pub trait Summand {
+    /// Returns the action of a product, or `None` for other nodes.
+    fn action(&self) -> Option<u32>;
+}
+
+impl Summand for CompactNode {
+    fn action(&self) -> Option<u32> {
+        match self.expand() {
+            bocage_node::Node::Product { action, .. } => Some(action),
+            _ => None,
+        }
+    }
+}
+
+impl Summand for compact_node::Node {
+    fn action(&self) -> Option<u32> {
+        match *self {
+            compact_node::Product { action, .. } => Some(action),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps the alternative with the highest rank. Ranks are given per external rule.
+/// Among alternatives of equal rank, the first one is kept.
+pub struct RuleRankOrder<'g> {
+    grammar: &'g InternalGrammar,
+    ranks: Vec<i32>,
+}
+
+impl<'g> RuleRankOrder<'g> {
+    /// Creates an order with ranks indexed by external rule id. Rules that have
+    /// no rank and internal rules have rank 0.
+    pub fn new(grammar: &'g InternalGrammar, ranks: Vec<i32>) -> Self {
+        RuleRankOrder { grammar, ranks }
+    }
+
+    pub(crate) fn select<S: Summand>(&self, alternatives: &[S]) -> Option<Vec<usize>> {
+        select_max(alternatives.iter().map(|alt| self.rank(alt)))
+    }
+
+    fn rank<S: Summand>(&self, summand: &S) -> i32 {
+        summand
+            .action()
+            .and_then(|action| self.grammar.external_origin(action))
+            .and_then(|origin| self.ranks.get(origin as usize).cloned())
+            .unwrap_or(0)
+    }
+}
+
+/// Keeps the alternative where the first factor spans the most input (longest match),
+/// or the least input (shortest match). Among alternatives of equal match length, the
+/// first one is kept.
+///
+/// Match lengths are taken from spans, so the forest must record them with
+/// `enable_spans` before parsing. Without spans, all alternatives are kept.
+pub struct MatchOrder {
+    longest: bool,
+}
+
+impl MatchOrder {
+    pub fn longest() -> Self {
+        MatchOrder { longest: true }
+    }
+
+    pub fn shortest() -> Self {
+        MatchOrder { longest: false }
+    }
+
+    pub(crate) fn select<F>(&self, count: usize, first_factor_len: F) -> Option<Vec<usize>>
+    where
+        F: Fn(usize) -> Option<Position>,
+    {
+        let lengths = (0..count)
+            .map(first_factor_len)
+            .collect::<Option<Vec<_>>>()?;
+        let longest = self.longest;
+        select_max(lengths.into_iter().map(|len| {
+            // Bitwise negation reverses the order of unsigned integers.
+            if longest {
+                len
+            } else {
+                !len
+            }
+        }))
+    }
+}
+
+/// Keeps the first or the last alternative.
+pub struct AlternativeOrder {
+    last: bool,
+}
+
+impl AlternativeOrder {
+    pub fn first() -> Self {
+        AlternativeOrder { last: false }
+    }
+
+    pub fn last() -> Self {
+        AlternativeOrder { last: true }
+    }
+
+    pub(crate) fn select<S: Summand>(&self, alternatives: &[S]) -> Option<Vec<usize>> {
+        if self.last {
+            Some(vec![alternatives.len() - 1])
+        } else {
+            Some(vec![0])
+        }
+    }
+}
+
+// Returns the index of the first alternative with the greatest key.
+fn select_max<K: Ord, I: Iterator<Item = K>>(keys: I) -> Option<Vec<usize>> {
+    let mut best: Option<(usize, K)> = None;
+    for (i, key) in keys.enumerate() {
+        let better = match best {
+            Some((_, ref best_key)) => key > *best_key,
+            None => true,
+        };
+        if better {
+            best = Some((i, key));
+        }
+    }
+    best.map(|(i, _)| vec![i])
+}
+
+// Checks whether the alternative or factor at the given index is kept. `None` keeps all.
+pub(crate) fn is_selected(selection: Option<&[usize]>, idx: usize) -> bool {
+    match selection {
+        Some(selection) => selection.contains(&idx),
+        None => true,
+    }
+}
+
+// Checks indices that an order selected among `len` alternatives or factors.
+pub(crate) fn check_selection(selection: &[usize], len: usize) {
+    for &idx in selection {
+        assert!(
+            idx < len,
+            "the order selected index {} out of {} alternatives or factors",
+            idx,
+            len
+        );
+    }
+}
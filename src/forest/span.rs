@@ -0,0 +1,86 @@
+use std::mem;
+
+use forest::node_handle::NodeHandle;
+use item::Position;
+
+/// A range of input locations that is covered by a node.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Spans of sum and leaf nodes. Forests record them only on request.
+pub(crate) struct Spans {
+    // Sorted by node, because nodes are created in order.
+    spans: Vec<(NodeHandle, Span)>,
+    // Location where the latest leaf ends. Sums that are created afterwards end here.
+    end: Position,
+}
+
+impl Spans {
+    pub(crate) fn new() -> Self {
+        Spans {
+            spans: vec![],
+            end: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn leaf(&mut self, node: NodeHandle, end: Position) {
+        self.end = end;
+        self.spans.push((
+            node,
+            Span {
+                start: end - 1,
+                end,
+            },
+        ));
+    }
+
+    #[inline]
+    pub(crate) fn sum(&mut self, node: NodeHandle, start: Position) {
+        let end = self.end;
+        self.spans.push((node, Span { start, end }));
+    }
+
+    pub(crate) fn get(&self, node: NodeHandle) -> Option<Span> {
+        self.spans
+            .binary_search_by_key(&node, |&(handle, _)| handle)
+            .ok()
+            .map(|idx| self.spans[idx].1)
+    }
+
+    /// Updates nodes after a collection. Spans of discarded nodes are removed.
+    pub(crate) fn relocate(&mut self, relocation: &[NodeHandle]) {
+        let mut kept = 0;
+        for i in 0..self.spans.len() {
+            let (node, span) = self.spans[i];
+            if let Some(node) = relocation[node.usize()].to_option() {
+                self.spans[kept] = (node, span);
+                kept += 1;
+            }
+        }
+        self.spans.truncate(kept);
+    }
+
+    /// Appends spans of another forest's nodes, which are appended to this forest. The
+    /// other forest's input starts at `offset`.
+    pub(crate) fn append<F>(&mut self, other: &Spans, relocate: F, offset: Position)
+    where
+        F: Fn(NodeHandle) -> NodeHandle,
+    {
+        self.spans.extend(other.spans.iter().map(|&(node, span)| {
+            let span = Span {
+                start: span.start + offset,
+                end: span.end + offset,
+            };
+            (relocate(node), span)
+        }));
+        self.end = other.end + offset;
+    }
+
+    pub(crate) fn memory_use(&self) -> usize {
+        self.spans.capacity() * mem::size_of::<(NodeHandle, Span)>()
+    }
+}
@@ -0,0 +1,105 @@
+use cfg::symbol::Symbol;
+
+use forest::{CollectGarbage, Forest};
+use item::{CompletedItem, Origin};
+
+/// A forest that forwards every node to two inner forests. References to nodes are pairs,
+/// so that each inner forest is given its own references.
+///
+/// For example, `Tee<NullForest, Bocage<G>>` builds a bocage, and `Tee<Bocage<G>, F>`
+/// can pass nodes to a forest that collects statistics.
+#[derive(Clone, Debug, Default)]
+pub struct Tee<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A, B> Tee<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Tee { first, second }
+    }
+
+    /// Returns both inner forests.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A, B> Forest for Tee<A, B>
+where
+    A: Forest,
+    B: Forest,
+{
+    type NodeRef = (A::NodeRef, B::NodeRef);
+    type LeafValue = (A::LeafValue, B::LeafValue);
+
+    const FOREST_BYTES_PER_RECOGNIZER_BYTE: usize =
+        A::FOREST_BYTES_PER_RECOGNIZER_BYTE + B::FOREST_BYTES_PER_RECOGNIZER_BYTE;
+
+    #[inline]
+    fn begin_sum(&mut self) {
+        self.first.begin_sum();
+        self.second.begin_sum();
+    }
+
+    #[inline]
+    fn push_summand(&mut self, item: CompletedItem<Self::NodeRef>) {
+        self.first.push_summand(CompletedItem {
+            dot: item.dot,
+            origin: item.origin,
+            left_node: item.left_node.0,
+            right_node: item.right_node.map(|node| node.0),
+        });
+        self.second.push_summand(CompletedItem {
+            dot: item.dot,
+            origin: item.origin,
+            left_node: item.left_node.1,
+            right_node: item.right_node.map(|node| node.1),
+        });
+    }
+
+    #[inline]
+    fn sum(&mut self, lhs_sym: Symbol, origin: Origin) -> Self::NodeRef {
+        (
+            self.first.sum(lhs_sym, origin),
+            self.second.sum(lhs_sym, origin),
+        )
+    }
+
+    #[inline]
+    fn leaf(&mut self, token: Symbol, pos: Origin, value: Self::LeafValue) -> Self::NodeRef {
+        (
+            self.first.leaf(token, pos, value.0),
+            self.second.leaf(token, pos, value.1),
+        )
+    }
+
+    #[inline]
+    fn nulling(&self, token: Symbol) -> Self::NodeRef {
+        (self.first.nulling(token), self.second.nulling(token))
+    }
+}
+
+impl<A, B> CollectGarbage for Tee<A, B>
+where
+    A: CollectGarbage,
+    B: CollectGarbage,
+{
+    fn mark_and_sweep<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = Self::NodeRef>,
+    {
+        let roots: Vec<_> = roots.into_iter().collect();
+        self.first
+            .mark_and_sweep(roots.iter().map(|&(first, _)| first));
+        self.second
+            .mark_and_sweep(roots.iter().map(|&(_, second)| second));
+    }
+
+    fn relocate(&self, node: Self::NodeRef) -> Option<Self::NodeRef> {
+        match (self.first.relocate(node.0), self.second.relocate(node.1)) {
+            (Some(first), Some(second)) => Some((first, second)),
+            _ => None,
+        }
+    }
+}
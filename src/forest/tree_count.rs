@@ -0,0 +1,108 @@
+use cfg::symbol::Symbol;
+use num::{BigUint, One, Zero};
+
+use grammar::InternalGrammar;
+
+/// A number of parse trees. Counts are computed with addition for alternatives
+/// and multiplication for factors.
+pub trait TreeCount: Clone {
+    fn zero() -> Self;
+
+    fn one() -> Self;
+
+    fn add(&self, other: &Self) -> Self;
+
+    fn mul(&self, other: &Self) -> Self;
+
+    /// Returns the count of infinitely many trees, if there is one.
+    fn unbounded() -> Option<Self> {
+        None
+    }
+}
+
+/// Saturates at `u64::MAX`, which is enough to refuse enumeration of huge forests.
+impl TreeCount for u64 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn one() -> Self {
+        1
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self.saturating_add(*other)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self.saturating_mul(*other)
+    }
+
+    fn unbounded() -> Option<Self> {
+        Some(u64::MAX)
+    }
+}
+
+impl TreeCount for BigUint {
+    fn zero() -> Self {
+        Zero::zero()
+    }
+
+    fn one() -> Self {
+        One::one()
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self * other
+    }
+}
+
+/// Counts derivations of nulling symbols with rules of the nulling grammar. Counts are
+/// indexed by symbol. Symbols that have infinitely many derivations through cyclic rules
+/// have the count `C::unbounded()`.
+pub(crate) fn count_nulling<C: TreeCount>(grammar: &InternalGrammar) -> Vec<Option<C>> {
+    let rules = grammar.nulling_rules();
+    let len = rules
+        .iter()
+        .map(|&(lhs, _)| lhs.usize() + 1)
+        .max()
+        .unwrap_or(0);
+    let mut counts = vec![C::zero(); len];
+    // Symbols whose derivations are at most as deep as the number of iterations.
+    let mut bounded = vec![false; len];
+    // A derivation can not be deeper than the number of symbols, unless it is cyclic.
+    for _ in 0..len {
+        let mut next = vec![C::zero(); len];
+        let mut next_bounded = vec![true; len];
+        for &(lhs, ref rhs) in rules {
+            let mut product = C::one();
+            for sym in rhs {
+                product = product.mul(counts.get(sym.usize()).unwrap_or(&C::zero()));
+                if !bounded.get(sym.usize()).cloned().unwrap_or(true) {
+                    next_bounded[lhs.usize()] = false;
+                }
+            }
+            next[lhs.usize()] = next[lhs.usize()].add(&product);
+        }
+        counts = next;
+        bounded = next_bounded;
+    }
+    counts
+        .into_iter()
+        .zip(bounded)
+        .map(|(count, bounded)| if bounded { Some(count) } else { C::unbounded() })
+        .collect()
+}
+
+/// Returns the number of derivations of a nulling symbol, or `None` if it is unbounded
+/// and has no count.
+pub(crate) fn nulling_count<C: TreeCount>(nulling: &[Option<C>], symbol: Symbol) -> Option<C> {
+    match nulling.get(symbol.usize()) {
+        Some(count) => count.clone(),
+        None => Some(C::one()),
+    }
+}
@@ -0,0 +1,65 @@
+//! Flattening of factors through transparent nodes.
+//!
+//! Products of internal rules are transparent: their factors belong to the product of
+//! the external rule above them. A sum of transparent products is transparent, too. It
+//! stands for a choice, so one product of an external rule may be unfolded in more than
+//! one way.
+
+use cfg::symbol::Symbol;
+
+use forest::node_handle::NodeHandle;
+
+/// What a node contributes to the factors of an external product.
+pub(crate) enum Step {
+    /// The node is a factor.
+    Factor(Symbol),
+    /// The node is transparent. Factors of one of these pairs take its place.
+    Unfold(Vec<(NodeHandle, Option<NodeHandle>)>),
+}
+
+/// Lists every way of flattening the given factors. The first list takes the first pair
+/// at every transparent node. A transparent node without pairs has no flattening.
+pub(crate) fn unfold<F>(
+    left: NodeHandle,
+    right: Option<NodeHandle>,
+    mut step: F,
+) -> Vec<Vec<(Symbol, NodeHandle)>>
+where
+    F: FnMut(NodeHandle) -> Step,
+{
+    let mut result = vec![];
+    let mut pending = vec![(vec![], pair_stack(left, right, vec![]))];
+    'unfold: while let Some((mut factors, mut stack)) = pending.pop() {
+        while let Some(handle) = stack.pop() {
+            match step(handle) {
+                Step::Factor(symbol) => {
+                    factors.push((symbol, handle));
+                }
+                Step::Unfold(pairs) => {
+                    let mut pairs = pairs.into_iter();
+                    let (left, right) = match pairs.next() {
+                        Some(pair) => pair,
+                        None => continue 'unfold,
+                    };
+                    // Other choices are unfolded after this one.
+                    for (left, right) in pairs.rev() {
+                        pending.push((factors.clone(), pair_stack(left, right, stack.clone())));
+                    }
+                    stack = pair_stack(left, right, stack);
+                }
+            }
+        }
+        result.push(factors);
+    }
+    result
+}
+
+fn pair_stack(
+    left: NodeHandle,
+    right: Option<NodeHandle>,
+    mut stack: Vec<NodeHandle>,
+) -> Vec<NodeHandle> {
+    stack.extend(right);
+    stack.push(left);
+    stack
+}
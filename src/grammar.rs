@@ -98,12 +98,6 @@ pub(in super) struct PredictionTransition {
     pub dot: Dot,
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd)]
-pub(in super) enum MaybePostdot {
-    Binary(Symbol),
-    Unary,
-}
-
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct InternalGrammar {
     start_sym: Symbol,
@@ -139,6 +133,12 @@ pub struct InternalGrammar {
     // Mapping between external and internal symbols.
     sym_maps: Mapping,
     nulling_intermediate_rules: Vec<NullingIntermediateRule>,
+    // Rules of the nulling grammar, used for counting derivations of nulling symbols.
+    #[serde(default)]
+    nulling_rules: Vec<(Symbol, Vec<Symbol>)>,
+    // Names of external symbols, used when forests are exported.
+    #[serde(default)]
+    symbol_names: Vec<Option<String>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
@@ -423,6 +423,7 @@ impl InternalGrammar {
             }
         });
         self.nulling_intermediate_rules.extend(iter_nulling_intermediate);
+        self.nulling_rules.extend(nulling.rules().map(|rule| (rule.lhs(), rule.rhs().to_vec())));
     }
 
     #[inline]
@@ -496,16 +497,13 @@ impl InternalGrammar {
     }
 
     #[inline]
-    pub(in super) fn get_rhs1(&self, dot: Dot) -> Option<Symbol> {
-        self.rhs1[dot as usize]
+    pub(in super) fn get_rhs0(&self, dot: Dot) -> Option<Symbol> {
+        self.rhs0[dot as usize]
     }
 
     #[inline]
-    pub(in super) fn get_rhs1_cmp(&self, dot: Dot) -> MaybePostdot {
-        match self.rhs1[dot as usize] {
-            None => MaybePostdot::Unary,
-            Some(rhs1) => MaybePostdot::Binary(rhs1),
-        }
+    pub(in super) fn get_rhs1(&self, dot: Dot) -> Option<Symbol> {
+        self.rhs1[dot as usize]
     }
 
     #[inline]
@@ -523,6 +521,13 @@ impl InternalGrammar {
         self.eval.get(dot as usize).cloned().unwrap()
     }
 
+    /// Finds the binary rule that carries the action of the given external rule.
+    pub(in super) fn binary_dot_with_origin(&self, external_origin: u32) -> Option<Dot> {
+        (0 .. self.num_rules()).map(|dot| dot as Dot).find(|&dot| {
+            self.eval[dot as usize] == Some(external_origin) && self.rhs1[dot as usize].is_some()
+        })
+    }
+
     pub(in super) fn eliminated_nulling_intermediate(&self) -> &[NullingIntermediateRule] {
         &*self.nulling_intermediate_rules
     }
@@ -559,6 +564,24 @@ impl InternalGrammar {
         }
     }
 
+    /// Sets the name of an external symbol. Names are used when forests are exported.
+    pub fn set_symbol_name(&mut self, symbol: Symbol, name: &str) {
+        if self.symbol_names.len() <= symbol.usize() {
+            self.symbol_names.resize(symbol.usize() + 1, None);
+        }
+        self.symbol_names[symbol.usize()] = Some(name.to_string());
+    }
+
+    /// Returns the name of an external symbol, if it has one.
+    pub fn symbol_name(&self, symbol: Symbol) -> Option<&str> {
+        self.symbol_names.get(symbol.usize()).and_then(|name| name.as_ref().map(|name| &name[..]))
+    }
+
+    #[inline]
+    pub(in super) fn nulling_rules(&self) -> &[(Symbol, Vec<Symbol>)] {
+        &self.nulling_rules[..]
+    }
+
     pub(in super) fn max_nulling_symbol(&self) -> Option<usize> {
         (0 .. self.num_rules()).filter_map(|action| {
             self.nulling(action as u32).map(|(sym, _dir)| sym.usize())
@@ -0,0 +1,82 @@
+//! Access that relies on invariants of the parser.
+//!
+//! By default, these invariants are trusted, so their violation is undefined behavior.
+//! With the `safe` feature, every access is checked, and a violation panics with
+//! the name of the invariant.
+
+use std::hint;
+use std::slice::SliceIndex;
+
+/// Marks code that an invariant makes unreachable.
+#[inline(always)]
+pub(crate) unsafe fn unreachable(invariant: &'static str) -> ! {
+    if cfg!(feature = "safe") {
+        violated(invariant)
+    } else {
+        hint::unreachable_unchecked()
+    }
+}
+
+/// Unwraps a value that an invariant guarantees to be present.
+#[inline(always)]
+pub(crate) unsafe fn unwrap<T>(opt: Option<T>, invariant: &'static str) -> T {
+    match opt {
+        Some(val) => val,
+        None => unreachable(invariant),
+    }
+}
+
+/// Indexes a slice within bounds that are guaranteed by an invariant.
+#[inline(always)]
+pub(crate) unsafe fn get<'a, T, I>(
+    slice: &'a [T],
+    index: I,
+    invariant: &'static str,
+) -> &'a I::Output
+where
+    I: SliceIndex<[T]>,
+{
+    if cfg!(feature = "safe") {
+        match slice.get(index) {
+            Some(val) => val,
+            None => violated(invariant),
+        }
+    } else {
+        slice.get_unchecked(index)
+    }
+}
+
+/// Mutably indexes a slice within bounds that are guaranteed by an invariant.
+#[inline(always)]
+pub(crate) unsafe fn get_mut<'a, T, I>(
+    slice: &'a mut [T],
+    index: I,
+    invariant: &'static str,
+) -> &'a mut I::Output
+where
+    I: SliceIndex<[T]>,
+{
+    if cfg!(feature = "safe") {
+        match slice.get_mut(index) {
+            Some(val) => val,
+            None => violated(invariant),
+        }
+    } else {
+        slice.get_unchecked_mut(index)
+    }
+}
+
+/// Checks an invariant in the `safe` mode. Otherwise, the violation is handled by
+/// the caller.
+#[inline(always)]
+pub(crate) fn check(condition: bool, invariant: &'static str) {
+    if cfg!(feature = "safe") && !condition {
+        violated(invariant)
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn violated(invariant: &'static str) -> ! {
+    panic!("invariant violated: {}", invariant)
+}
@@ -1,12 +1,61 @@
 use std::cmp::Ordering;
+use std::fmt::Debug;
+
+use cfg::Symbol;
+
+/// An input location, the ID of an Earley set, the index of a medial item or a node handle.
+/// It is 64-bit with the `wide-positions` feature.
+#[cfg(not(feature = "wide-positions"))]
+pub type Position = u32;
+#[cfg(feature = "wide-positions")]
+pub type Position = u64;
 
 pub type Dot = u32;
-pub type Origin = u32;
+pub type Origin = Position;
+
+/// An unsigned integer that stores dots, origins, symbols or indices of items. Narrower
+/// integers make the chart denser, but limit the size of the grammar or of the input.
+pub trait ItemIndex: Copy + Ord + Debug {
+    /// The greatest value that can be stored.
+    const MAX: u64;
+
+    /// Narrows a value. Returns `None` if it is greater than `MAX`.
+    fn from_usize(value: usize) -> Option<Self>;
+
+    fn to_usize(self) -> usize;
+}
+
+macro_rules! impl_item_index {
+    ($($t:ident),*) => {
+        $(
+            impl ItemIndex for $t {
+                const MAX: u64 = $t::MAX as u64;
+
+                #[inline(always)]
+                fn from_usize(value: usize) -> Option<Self> {
+                    if value as u64 <= <$t as ItemIndex>::MAX {
+                        Some(value as $t)
+                    } else {
+                        None
+                    }
+                }
+
+                #[inline(always)]
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_item_index!(u8, u16, u32, u64);
 
+/// A medial item. Its dot and origin are stored in integers of the given types.
 #[derive(Clone, Copy, Debug)]
-pub struct Item<N> {
-    pub(in super) origin: Origin,
-    pub(in super) dot: Dot,
+pub struct Item<N, D = Dot, O = Origin> {
+    pub(in super) origin: O,
+    pub(in super) dot: D,
     pub node: N,
 }
 
@@ -24,29 +73,96 @@ pub struct CompletedItem<N> {
     pub right_node: Option<N>,
 }
 
+/// A completed item that links to its medial item. The index is stored in an integer
+/// of the given type.
 #[derive(Clone, Copy, Debug)]
-pub struct CompletedItemLinked<N> {
+pub struct CompletedItemLinked<N, I = Position> {
     /// Left item idx.
-    pub idx: u32,
+    pub idx: I,
     /// Right bocage node.
     pub node: Option<N>,
 }
 
-impl<L> PartialEq for Item<L> {
+/// The LHS and RHS1 symbols of a rule, stored in integers of the given type. The RHS1
+/// of a unary rule is `MAX`, which orders it after binary rules.
+#[derive(Clone, Copy, Debug)]
+pub(in super) struct RuleSymbols<S> {
+    pub(in super) lhs: S,
+    pub(in super) rhs1: S,
+}
+
+impl<S: ItemIndex> RuleSymbols<S> {
+    /// Narrows symbols of a rule. Returns `None` if they do not fit.
+    pub(in super) fn new(lhs: Symbol, rhs1: Option<Symbol>) -> Option<Self> {
+        let rhs1 = match rhs1 {
+            Some(rhs1) if (rhs1.usize() as u64) < S::MAX => S::from_usize(rhs1.usize())?,
+            Some(_) => return None,
+            None => S::from_usize(S::MAX as usize)?,
+        };
+        Some(RuleSymbols {
+            lhs: S::from_usize(lhs.usize())?,
+            rhs1,
+        })
+    }
+
+    #[inline(always)]
+    pub(in super) fn lhs(&self) -> Symbol {
+        Symbol::from(self.lhs.to_usize())
+    }
+
+    #[inline(always)]
+    pub(in super) fn rhs1(&self) -> Option<Symbol> {
+        if self.rhs1.to_usize() as u64 == S::MAX {
+            None
+        } else {
+            Some(Symbol::from(self.rhs1.to_usize()))
+        }
+    }
+}
+
+impl<N, D, O> Item<N, D, O>
+where
+    D: ItemIndex,
+    O: ItemIndex,
+{
+    /// Narrows the origin and the dot. Returns `None` if they do not fit.
+    #[inline(always)]
+    pub(in super) fn new(origin: Origin, dot: Dot, node: N) -> Option<Self> {
+        Some(Item {
+            origin: O::from_usize(origin as usize)?,
+            dot: D::from_usize(dot as usize)?,
+            node,
+        })
+    }
+
+    /// Returns the ID of the Earley set where this item has its origin.
+    #[inline(always)]
+    pub fn origin(&self) -> Origin {
+        self.origin.to_usize() as Origin
+    }
+
+    /// Returns the dot position.
+    #[inline(always)]
+    pub fn dot(&self) -> Dot {
+        self.dot.to_usize() as Dot
+    }
+}
+
+impl<L, D: ItemIndex, O: ItemIndex> PartialEq for Item<L, D, O> {
     fn eq(&self, other: &Self) -> bool {
         (self.origin, self.dot) == (other.origin, other.dot)
     }
 }
 
-impl<L> Eq for Item<L> {}
+impl<L, D: ItemIndex, O: ItemIndex> Eq for Item<L, D, O> {}
 
-impl<L> PartialOrd for Item<L> {
+impl<L, D: ItemIndex, O: ItemIndex> PartialOrd for Item<L, D, O> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<L> Ord for Item<L> {
+impl<L, D: ItemIndex, O: ItemIndex> Ord for Item<L, D, O> {
     fn cmp(&self, other: &Self) -> Ordering {
         (self.origin, self.dot).cmp(&(other.origin, other.dot))
     }
@@ -71,13 +187,3 @@ impl<L> Ord for CompletedItem<L> {
         (self.origin, self.dot).cmp(&(other.origin, other.dot))
     }
 }
-
-impl<N> Into<Item<N>> for CompletedItem<N> {
-    fn into(self) -> Item<N> {
-        Item {
-            origin: self.origin,
-            dot: self.dot,
-            node: self.left_node,
-        }
-    }
-}
@@ -13,12 +13,21 @@ extern crate serde;
 extern crate serde_derive;
 extern crate num;
 extern crate num_derive;
+#[cfg(feature = "sample")]
+extern crate rand;
 
+pub mod ast;
+pub mod cst;
 pub mod debug;
+pub mod evaluator;
 pub mod events;
 pub mod forest;
 pub mod grammar;
+mod invariant;
 pub mod item;
 pub mod memory_use;
+pub mod metrics;
+pub mod parallel;
+pub mod policy;
 pub mod recognizer;
 pub mod binary_heap;
@@ -7,7 +7,8 @@ use forest::node_handle::NodeHandle;
 use forest::{Bocage, CompactBocage, Forest, NullForest};
 use grammar::InternalGrammar;
 use item::{CompletedItem, Item};
-use recognizer::Recognizer;
+use policy::{NullPerformancePolicy, PerformancePolicy};
+use recognizer::{rule_symbols, Recognizer};
 
 const ITEMS_PER_SET: usize = 16;
 
@@ -25,11 +26,7 @@ where
     type Arg = &'g InternalGrammar;
 
     fn memory_use(&self) -> usize {
-        self.forest.memory_use()
-            + self.predicted.memory_use()
-            + self.medial.memory_use()
-            + self.complete.memory_use()
-            + self.indices.memory_use()
+        self.forest.memory_use() + self.chart_memory_use()
     }
 
     fn new_with_limit(grammar: &'g InternalGrammar, memory_limit: usize) -> Self {
@@ -51,8 +48,11 @@ where
         let mut recognizer = Recognizer {
             forest: F::new_with_limit(grammar, forest_use_bytes),
             grammar,
+            policy: NullPerformancePolicy,
+            rules: rule_symbols(grammar),
             // The initial location is 0.
             earleme: 0,
+            locations: vec![0],
             // The first Earley set begins at 0 and ends at 0. The second Earley set begins at 0.
             indices: Vec::with_capacity(sets_use),
             current_medial_start: 0,
@@ -61,6 +61,8 @@ where
             medial: Vec::with_capacity(sets_use * ITEMS_PER_SET),
             complete: Vec::with_capacity(complete_use),
             lookahead_hint: None,
+            overflow: None,
+            metrics: None,
         };
         recognizer.indices.push(0);
         recognizer.indices.push(0);
@@ -85,8 +87,11 @@ where
         let mut recognizer = Recognizer {
             forest: F::new_with_limit(grammar, forest_use_bytes),
             grammar,
+            policy: NullPerformancePolicy,
+            rules: rule_symbols(grammar),
             // The initial location is 0.
             earleme: 0,
+            locations: vec![0],
             // The first Earley set begins at 0 and ends at 0. The second Earley set begins at 0.
             indices: Vec::with_capacity(tokens + 1),
             current_medial_start: 0,
@@ -95,6 +100,8 @@ where
             medial: Vec::with_capacity(tokens * ITEMS_PER_SET),
             complete: Vec::with_capacity(complete_use),
             lookahead_hint: None,
+            overflow: None,
+            metrics: None,
         };
         recognizer.indices.push(0);
         recognizer.indices.push(0);
@@ -107,11 +114,7 @@ impl<'g> MemoryUse for Recognizer<'g, NullForest> {
     type Arg = &'g InternalGrammar;
 
     fn memory_use(&self) -> usize {
-        self.forest.memory_use()
-            + self.predicted.memory_use()
-            + self.medial.memory_use()
-            + self.complete.memory_use()
-            + self.indices.memory_use()
+        self.forest.memory_use() + self.chart_memory_use()
     }
 
     fn new_with_limit(grammar: &'g InternalGrammar, memory_limit: usize) -> Self {
@@ -130,8 +133,11 @@ impl<'g> MemoryUse for Recognizer<'g, NullForest> {
         let mut recognizer = Recognizer {
             forest: NullForest,
             grammar,
+            policy: NullPerformancePolicy,
+            rules: rule_symbols(grammar),
             // The initial location is 0.
             earleme: 0,
+            locations: vec![0],
             // The first Earley set begins at 0 and ends at 0. The second Earley set begins at 0.
             indices: Vec::with_capacity(sets_use),
             current_medial_start: 0,
@@ -140,6 +146,8 @@ impl<'g> MemoryUse for Recognizer<'g, NullForest> {
             medial: Vec::with_capacity(sets_use * ITEMS_PER_SET),
             complete: Vec::with_capacity(complete_use),
             lookahead_hint: None,
+            overflow: None,
+            metrics: None,
         };
         recognizer.indices.push(0);
         recognizer.indices.push(0);
@@ -148,6 +156,21 @@ impl<'g> MemoryUse for Recognizer<'g, NullForest> {
     }
 }
 
+impl<'g, F, P> Recognizer<'g, F, P>
+where
+    F: Forest,
+    P: PerformancePolicy,
+{
+    /// Returns the memory use of the chart, without the forest.
+    pub fn chart_memory_use(&self) -> usize {
+        self.predicted.memory_use()
+            + self.medial.memory_use()
+            + self.complete.memory_use()
+            + self.indices.memory_use()
+            + self.locations.memory_use()
+    }
+}
+
 impl<T> MemoryUse for Vec<T> {
     type Arg = ();
 
@@ -205,7 +228,11 @@ impl<'g> MemoryUse for Bocage<&'g InternalGrammar> {
     type Arg = &'g InternalGrammar;
 
     fn memory_use(&self) -> usize {
-        self.graph.memory_use() + self.gc.liveness.memory_use() + self.gc.dfs.memory_use()
+        self.graph.memory_use()
+            + self.gc.liveness.memory_use()
+            + self.gc.dfs.memory_use()
+            + self.gc.relocation.memory_use()
+            + self.spans.as_ref().map_or(0, |spans| spans.memory_use())
     }
 
     fn new_with_limit(grammar: &'g InternalGrammar, memory_limit: usize) -> Self {
@@ -225,7 +252,11 @@ impl<'g> MemoryUse for CompactBocage<&'g InternalGrammar> {
     type Arg = &'g InternalGrammar;
 
     fn memory_use(&self) -> usize {
-        self.graph.vec.memory_use() + self.gc.liveness.memory_use() + self.gc.dfs.memory_use()
+        self.graph.vec.memory_use()
+            + self.gc.liveness.memory_use()
+            + self.gc.dfs.memory_use()
+            + self.gc.relocation.memory_use()
+            + self.spans.as_ref().map_or(0, |spans| spans.memory_use())
     }
 
     fn new_with_limit(grammar: &'g InternalGrammar, memory_limit: usize) -> Self {
@@ -0,0 +1,34 @@
+//! Metrics of the recognizer, which are collected on demand.
+//!
+//! Metrics are disabled by default. They are enabled with `Recognizer::enable_metrics`,
+//! and are updated at the end of every earleme. Statistics of parse forests are collected
+//! by wrapping a forest in `forest::metrics::MetricsForest`.
+
+/// Counts collected by the recognizer.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RecognizerMetrics {
+    /// The number of medial items in every Earley set that was finished by advancing
+    /// the parse, in the order of input locations.
+    pub items_per_set: Vec<usize>,
+    /// The number of completed items in the completion pass of every earleme.
+    pub completions_per_earleme: Vec<usize>,
+    /// The number of Earley sets that were removed because they became unreachable.
+    pub dropped_sets: usize,
+    /// The largest memory use of the chart, in bytes, at the end of an earleme. Memory of
+    /// the forest is not included.
+    pub peak_memory_use: usize,
+    // Completed items of the current earleme.
+    pub(crate) completions: usize,
+}
+
+impl RecognizerMetrics {
+    /// Returns the total number of completed items.
+    pub fn total_completions(&self) -> usize {
+        self.completions_per_earleme.iter().sum::<usize>() + self.completions
+    }
+
+    /// Returns the largest number of medial items in an Earley set.
+    pub fn max_items_per_set(&self) -> usize {
+        self.items_per_set.iter().cloned().max().unwrap_or(0)
+    }
+}
@@ -0,0 +1,199 @@
+//! Parallel parsing of independent chunks of input.
+//!
+//! Some inputs consist of many parts that are known to be derived independently, such as
+//! external declarations in a C translation unit. Given split points at such boundaries,
+//! every chunk is parsed on its own thread, with its own recognizer and bocage. The first
+//! chunk is derived from the left symbol of a binary join rule, and every other chunk from
+//! its right symbol. Afterwards, the chunks' forests are stitched into a single bocage.
+
+use std::error::Error;
+use std::fmt;
+use std::panic;
+use std::thread;
+
+use cfg::Symbol;
+
+use forest::node_handle::NodeHandle;
+use forest::{Bocage, Forest};
+use grammar::InternalGrammar;
+use item::{CompletedItem, Dot, Position};
+use recognizer::{OverflowError, Recognizer};
+
+/// An error that prevents a chunked parse from producing a forest.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ChunkError {
+    /// The join rule is not a binary rule of the grammar, its LHS differs from its left
+    /// symbol, or its right symbol is a terminal.
+    InvalidJoinRule,
+    /// Split points are not in strictly increasing order, or they are out of bounds.
+    InvalidSplitPoints,
+    /// The parse of a chunk was rejected at the given token.
+    Rejected { chunk: usize, token: usize },
+    /// All tokens of a chunk were accepted, but the chunk is not derived from its symbol.
+    Unfinished { chunk: usize },
+    /// The chunk is too long for the integer types of its recognizer.
+    Overflow { chunk: usize, error: OverflowError },
+}
+
+/// Parses chunks of `tokens` on separate threads. Chunks end at the given `split_points`,
+/// which are indices into `tokens`. Each token is given as a symbol and a leaf value.
+///
+/// The results are joined left to right with `join_rule`, which is the ID of an external
+/// binary rule whose LHS is its left symbol, such as
+/// `translation_unit ::= translation_unit external_declaration`. The first chunk is parsed
+/// from the left symbol, and every other chunk from the right symbol. During evaluation,
+/// the rule's action receives the values of the chunks parsed so far and of the next chunk.
+///
+/// Returns the stitched bocage together with its root node, which is derived from the
+/// join rule's LHS. Liveness is not marked. The bocage records spans, with locations
+/// that are indices into `tokens`.
+pub fn parse_chunks<'g>(
+    grammar: &'g InternalGrammar,
+    tokens: &[(Symbol, u32)],
+    split_points: &[usize],
+    join_rule: u32,
+) -> Result<(Bocage<&'g InternalGrammar>, NodeHandle), ChunkError> {
+    let join_dot = grammar
+        .binary_dot_with_origin(join_rule)
+        .ok_or(ChunkError::InvalidJoinRule)?;
+    let lhs_sym = grammar.get_lhs(join_dot);
+    let left_sym = grammar
+        .get_rhs0(join_dot)
+        .ok_or(ChunkError::InvalidJoinRule)?;
+    let right_sym = grammar
+        .get_rhs1(join_dot)
+        .ok_or(ChunkError::InvalidJoinRule)?;
+    let is_nonterminal =
+        |sym| (0..grammar.num_rules()).any(|dot| grammar.get_lhs(dot as Dot) == sym);
+    if lhs_sym != left_sym || !is_nonterminal(right_sym) {
+        return Err(ChunkError::InvalidJoinRule);
+    }
+    let mut bounds = Vec::with_capacity(split_points.len() + 2);
+    bounds.push(0);
+    bounds.extend(split_points.iter().cloned());
+    bounds.push(tokens.len());
+    // Empty chunks are not allowed.
+    if bounds.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(ChunkError::InvalidSplitPoints);
+    }
+
+    let results = thread::scope(|scope| {
+        let threads: Vec<_> = bounds
+            .windows(2)
+            .enumerate()
+            .map(|(chunk, w)| {
+                let (start, end) = (w[0], w[1]);
+                let target = if chunk == 0 { left_sym } else { right_sym };
+                scope.spawn(move || parse_chunk(grammar, &tokens[start..end], chunk, start, target))
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let mut results = results.into_iter().zip(bounds);
+    let ((mut bocage, mut root), _) = results.next().unwrap();
+    for ((chunk_bocage, chunk_root), start) in results {
+        let right = bocage.append(&chunk_bocage, chunk_root, start as Position);
+        bocage.begin_sum();
+        bocage.push_summand(CompletedItem {
+            dot: join_dot,
+            origin: 0,
+            left_node: root,
+            right_node: Some(right),
+        });
+        root = bocage.sum(lhs_sym, 0);
+    }
+    Ok((bocage, root))
+}
+
+// Parses a chunk that is derived from the `target` symbol.
+fn parse_chunk<'g>(
+    grammar: &'g InternalGrammar,
+    tokens: &[(Symbol, u32)],
+    chunk: usize,
+    offset: usize,
+    target: Symbol,
+) -> Result<(Bocage<&'g InternalGrammar>, NodeHandle), ChunkError> {
+    let mut bocage = Bocage::new(grammar);
+    bocage.enable_spans();
+    let mut recognizer = Recognizer::new(grammar, bocage);
+    // Predict the target instead of the start symbol.
+    for block in recognizer.predicted[0].iter_mut() {
+        *block = 0;
+    }
+    recognizer.predict(target);
+    let mut root = None;
+    let mut iter = tokens.iter().enumerate().peekable();
+    while let Some((i, &(symbol, value))) = iter.next() {
+        recognizer.begin_earleme();
+        recognizer.scan(symbol, value);
+        recognizer.lookahead_hint(iter.peek().map(|&(_, &(next, _))| next));
+        check_overflow(&recognizer, chunk)?;
+        if recognizer.is_exhausted() {
+            return Err(ChunkError::Rejected {
+                chunk,
+                token: offset + i,
+            });
+        }
+        root = complete_target(&mut recognizer, target);
+        recognizer.advance_without_completion();
+        check_overflow(&recognizer, chunk)?;
+    }
+    match root {
+        Some(root) => Ok((recognizer.forest, root)),
+        None => Err(ChunkError::Unfinished { chunk }),
+    }
+}
+
+// Reports the first overflow in the recognizer of a chunk. The parse of the chunk cannot
+// continue after it.
+fn check_overflow(
+    recognizer: &Recognizer<Bocage<&InternalGrammar>>,
+    chunk: usize,
+) -> Result<(), ChunkError> {
+    match recognizer.overflow() {
+        Some(error) => Err(ChunkError::Overflow { chunk, error }),
+        None => Ok(()),
+    }
+}
+
+// Runs the completion pass. Returns the node of the target that starts at the beginning
+// of the chunk, if there is one.
+fn complete_target(
+    recognizer: &mut Recognizer<Bocage<&InternalGrammar>>,
+    target: Symbol,
+) -> Option<NodeHandle> {
+    let mut root = None;
+    while let Some(mut completion) = recognizer.next_sum() {
+        let (origin, symbol) = (completion.origin(), completion.symbol());
+        let node = completion.complete_entire_sum();
+        if symbol == target && recognizer.locations[origin as usize] == 0 {
+            root = Some(node);
+        }
+    }
+    recognizer.lookahead_hint = None;
+    root
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChunkError::InvalidJoinRule => write!(f, "the join rule is not a binary rule"),
+            ChunkError::InvalidSplitPoints => write!(f, "invalid split points"),
+            ChunkError::Rejected { chunk, token } => {
+                write!(f, "chunk {} was rejected at token {}", chunk, token)
+            }
+            ChunkError::Unfinished { chunk } => write!(f, "chunk {} is unfinished", chunk),
+            ChunkError::Overflow { chunk, error } => write!(f, "chunk {}: {}", chunk, error),
+        }
+    }
+}
+
+impl Error for ChunkError {}
@@ -0,0 +1,82 @@
+//! Performance policies tune the recognizer for grammars and inputs of different sizes.
+//!
+//! A policy is a type parameter of the recognizer, so its choices are known at compile time.
+//! Every constant has a default, which is used by `NullPerformancePolicy`.
+
+use item::{ItemIndex, Origin, Position};
+
+/// Types and constants that control the recognizer's performance.
+pub trait PerformancePolicy {
+    /// The integer type that stores dots of medial items. It must hold the number of
+    /// the internal grammar's rules.
+    type Dot: ItemIndex;
+    /// The integer type that stores origins of medial items. It must hold the number of
+    /// Earley sets that are reachable at once.
+    type Origin: ItemIndex;
+    /// The integer type that stores symbols of rules, which the recognizer reads for every
+    /// item. It must hold the number of the internal grammar's symbols, and one more.
+    type Symbol: ItemIndex;
+    /// The integer type that stores indices of medial items in completed items. It must
+    /// hold the number of medial items.
+    type Index: ItemIndex;
+
+    /// Earley sets with at least this many medial items are searched with binary search.
+    /// Smaller sets are searched linearly.
+    const MEDIAL_BINARY_SEARCH_THRESHOLD: usize = 16;
+    /// The number of rows of predicted symbols that are allocated at the start. At least
+    /// one row is allocated.
+    const PREDICTED_CAPACITY: usize = 8;
+    /// The number of medial items that are allocated at the start.
+    const MEDIAL_CAPACITY: usize = 256;
+    /// The number of completed items that are allocated at the start.
+    const COMPLETE_CAPACITY: usize = 32;
+    /// Whether lookahead hints are used to filter completions. When disabled, hints
+    /// are ignored.
+    const LOOKAHEAD: bool = true;
+}
+
+/// The default policy.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NullPerformancePolicy;
+
+impl PerformancePolicy for NullPerformancePolicy {
+    type Dot = u32;
+    type Origin = Origin;
+    type Symbol = u32;
+    type Index = Position;
+}
+
+/// A policy for small grammars and short inputs, which searches Earley sets linearly and
+/// allocates less. Grammars may have up to 65536 internal rules and 65535 internal symbols,
+/// and up to 65536 Earley sets may be reachable at once.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SmallGrammarPolicy;
+
+impl PerformancePolicy for SmallGrammarPolicy {
+    type Dot = u16;
+    type Origin = u16;
+    type Symbol = u16;
+    type Index = u32;
+
+    const MEDIAL_BINARY_SEARCH_THRESHOLD: usize = 64;
+    const MEDIAL_CAPACITY: usize = 32;
+    const COMPLETE_CAPACITY: usize = 8;
+    const LOOKAHEAD: bool = false;
+}
+
+/// A policy for large grammars, which searches Earley sets with binary search early and
+/// allocates more.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LargeGrammarPolicy;
+
+impl PerformancePolicy for LargeGrammarPolicy {
+    type Dot = u32;
+    type Origin = Origin;
+    type Symbol = u32;
+    type Index = Position;
+
+    const MEDIAL_BINARY_SEARCH_THRESHOLD: usize = 8;
+    const PREDICTED_CAPACITY: usize = 64;
+    const MEDIAL_CAPACITY: usize = 4096;
+    const COMPLETE_CAPACITY: usize = 128;
+}
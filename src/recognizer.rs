@@ -1,4 +1,6 @@
 use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
 use std::ops::Range;
 
 use bit_matrix::BitMatrix;
@@ -6,26 +8,30 @@ use bit_matrix::row::BitVecSlice;
 use cfg::*;
 
 use events::{MedialItems, PredictedSymbols};
-use forest::{Forest, NullForest};
+use forest::{CollectGarbage, Forest, NullForest};
 use grammar::InternalGrammar;
-use item::{CompletedItem, CompletedItemLinked, Item, Origin};
-// use policy::{PerformancePolicy, NullPerformancePolicy};
+use item::{CompletedItem, CompletedItemLinked, Dot, Item, ItemIndex, Origin, RuleSymbols};
+use metrics::RecognizerMetrics;
+use policy::{NullPerformancePolicy, PerformancePolicy};
 
 /// The recognizer implements the Earley algorithm. It parses the given input according
 /// to the `grammar`. The parse result is constructed inside the `forest`.
 ///
 /// To save memory, it only retains those parts of the Earley table that may be useful
 /// in the future.
-pub struct Recognizer<'g, F = NullForest>
+pub struct Recognizer<'g, F = NullForest, P = NullPerformancePolicy>
 where
     F: Forest,
+    P: PerformancePolicy,
 {
     // The forest.
     pub forest: F,
     // The grammar.
     pub grammar: &'g InternalGrammar,
     // The policy.
-    // policy: P,
+    pub(super) policy: P,
+    // LHS and RHS1 symbols of rules, indexed by dots, in the policy's symbol type.
+    pub(super) rules: Vec<RuleSymbols<P::Symbol>>,
 
     // Chart's items.
 
@@ -37,7 +43,7 @@ where
     // Medial items.
     //
     // N.B. This structure could be moved into its own module.
-    pub(super) medial: Vec<Item<F::NodeRef>>,
+    pub(super) medial: Vec<Item<F::NodeRef, P::Dot, P::Origin>>,
     // Gearley's secret sauce: we have a binary heap for online sorting.
     //
     // Completed items are stored for the latest Earley set.
@@ -45,7 +51,7 @@ where
     // origin and dot. The creation of a completed item can only be caused
     // by a scan or a completion of an item that has a higher (origin, dot)
     // pair value.
-    pub(super) complete: Vec<CompletedItemLinked<F::NodeRef>>,
+    pub(super) complete: Vec<CompletedItemLinked<F::NodeRef, P::Index>>,
 
     // Chart's indices. They point to the beginning of each Earley set.
     //
@@ -58,8 +64,26 @@ where
 
     // The input location.
     pub(super) earleme: usize,
+    // Input locations of Earley sets. Sets that become unreachable are removed, so set IDs
+    // may differ from input locations.
+    pub(super) locations: Vec<Origin>,
 
     pub(super) lookahead_hint: Option<Option<Symbol>>,
+
+    // The first overflow of an integer type. The parse cannot continue after an overflow.
+    pub(super) overflow: Option<OverflowError>,
+
+    // Metrics, which are collected only when enabled.
+    pub(super) metrics: Option<Box<RecognizerMetrics>>,
+}
+
+/// An error that occurs when the input is too long for the integer types in use.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OverflowError {
+    /// There are more medial items than the policy's index type can hold.
+    MedialItems,
+    /// There are more Earley sets than the policy's origin type can hold.
+    EarleySets,
 }
 
 impl<'g, F> Recognizer<'g, F>
@@ -69,24 +93,73 @@ where
     /// Creates a new recognizer for a given grammar and forest. The recognizer has an initial
     /// Earley set that predicts the grammar's start symbol.
     pub fn new(grammar: &'g InternalGrammar, forest: F) -> Recognizer<'g, F> {
+        Recognizer::with_policy(grammar, forest, NullPerformancePolicy)
+    }
+}
+
+impl<'g, F, P> Recognizer<'g, F, P>
+where
+    F: Forest,
+    P: PerformancePolicy,
+{
+    /// Creates a new recognizer for a given grammar and forest, which is tuned by
+    /// the given policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy's dot type cannot hold every dot of the grammar, or if its symbol
+    /// type cannot hold every symbol.
+    pub fn with_policy(grammar: &'g InternalGrammar, forest: F, policy: P) -> Self {
+        assert!(
+            grammar.num_rules() as u64 <= P::Dot::MAX + 1,
+            "the grammar has too many rules for the policy's dot type"
+        );
         let mut recognizer = Recognizer {
             forest,
             grammar,
+            policy,
+            rules: rule_symbols(grammar),
             // The initial location is 0.
             earleme: 0,
+            locations: vec![0],
             // The first Earley set begins at 0 and ends at 0. The second Earley set begins at 0.
             indices: vec![0, 0],
             current_medial_start: 0,
             // Reserve some capacity for vectors.
-            predicted: BitMatrix::new(8, grammar.num_syms()),
-            medial: Vec::with_capacity(256),
-            complete: Vec::with_capacity(32),
+            predicted: BitMatrix::new(P::PREDICTED_CAPACITY.max(1), grammar.num_syms()),
+            medial: Vec::with_capacity(P::MEDIAL_CAPACITY),
+            complete: Vec::with_capacity(P::COMPLETE_CAPACITY),
             lookahead_hint: None,
+            overflow: None,
+            metrics: None,
         };
         recognizer.predict(grammar.start_sym());
         recognizer
     }
 
+    /// Returns the performance policy.
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Starts collecting metrics, which are updated at the end of every earleme. Metrics
+    /// that were already collected are kept.
+    pub fn enable_metrics(&mut self) {
+        if self.metrics.is_none() {
+            self.metrics = Some(Box::new(RecognizerMetrics::default()));
+        }
+    }
+
+    /// Stops collecting metrics. Returns the metrics that were collected.
+    pub fn disable_metrics(&mut self) -> Option<RecognizerMetrics> {
+        self.metrics.take().map(|metrics| *metrics)
+    }
+
+    /// Returns the collected metrics, if enabled.
+    pub fn metrics(&self) -> Option<&RecognizerMetrics> {
+        self.metrics.as_deref()
+    }
+
     /// Makes the current Earley set predict a given symbol.
     pub fn predict(&mut self, symbol: Symbol) {
         self.predicted[self.earleme].predict(symbol, self.grammar.predict(symbol));
@@ -100,24 +173,45 @@ where
     /// tokens, the parse can be advanced.
     pub fn scan(&mut self, symbol: Symbol, value: F::LeafValue) {
         // This method is a part of the scan pass.
+        if self.overflow.is_some() {
+            return;
+        }
         if let Some(internal) = self.grammar.to_internal(symbol) {
             let earleme = self.earleme as Origin;
             // Add a leaf node to the forest with the given value.
-            let node = self.forest.leaf(symbol, earleme + 1, value);
+            let node = self.forest.leaf(symbol, self.location() + 1, value);
             self.complete(earleme, internal, node);
         }
     }
 
+    /// Gives the next token, or the end of input, to filter completions of the following
+    /// completion pass. Ignored unless the policy uses lookahead.
     #[inline]
     pub fn lookahead_hint(&mut self, lookahead: Option<Symbol>) {
+        if !P::LOOKAHEAD {
+            return;
+        }
         let to_internal = |sym| self.grammar.to_internal(sym).unwrap();
         self.lookahead_hint = Some(lookahead.map(to_internal));
     }
 
     /// Advances the parse. Calling this method may set the finished node, which can be accessed
     /// through the `finished_node` method.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the input is too long for the integer types in use.
     pub fn end_earleme(&mut self) -> bool {
-        if self.is_exhausted() {
+        match self.try_end_earleme() {
+            Ok(advanced) => advanced,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Advances the parse like `end_earleme`, but returns an error if the input is too long
+    /// for the integer types in use.
+    pub fn try_end_earleme(&mut self) -> Result<bool, OverflowError> {
+        let advanced = if self.is_exhausted() {
             false
         } else {
             // Completion pass, which saves successful parses.
@@ -125,6 +219,53 @@ where
             // Do the rest.
             self.advance_without_completion();
             true
+        };
+        self.overflow_result(advanced)
+    }
+
+    /// Returns the first overflow of an integer type, after which the parse cannot continue.
+    /// Set by `scan` and the completion pass, as well as `advance_without_completion`.
+    pub fn overflow(&self) -> Option<OverflowError> {
+        self.overflow
+    }
+
+    /// Advances the parse, admitting only completed rules that the filter accepts. The filter
+    /// is given the external rule, the origin location and the current location of every
+    /// completed item. Items of rules that exist only in the internal grammar are admitted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the input is too long for the integer types in use.
+    pub fn end_earleme_with_filter<C>(&mut self, filter: C) -> bool
+    where
+        C: FnMut(u32, Origin, Origin) -> bool,
+    {
+        match self.try_end_earleme_with_filter(filter) {
+            Ok(advanced) => advanced,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Advances the parse like `end_earleme_with_filter`, but returns an error if the input
+    /// is too long for the integer types in use.
+    pub fn try_end_earleme_with_filter<C>(&mut self, filter: C) -> Result<bool, OverflowError>
+    where
+        C: FnMut(u32, Origin, Origin) -> bool,
+    {
+        let advanced = if self.is_exhausted() {
+            false
+        } else {
+            self.complete_all_sums_filtered(filter);
+            self.advance_without_completion();
+            true
+        };
+        self.overflow_result(advanced)
+    }
+
+    fn overflow_result(&self, advanced: bool) -> Result<bool, OverflowError> {
+        match self.overflow {
+            Some(error) => Err(error),
+            None => Ok(advanced),
         }
     }
 
@@ -135,7 +276,15 @@ where
         self.sort_medial_items();
         self.remove_unary_medial_items();
         self.remove_unreachable_sets();
+        if self.metrics.is_some() {
+            self.record_metrics();
+        }
+        let location = self.location() + 1;
+        self.locations.push(location);
         self.earleme += 1;
+        if self.earleme as u64 > P::Origin::MAX {
+            self.set_overflow(OverflowError::EarleySets);
+        }
         // `earleme` is now at least 1.
         // Prediction pass.
         self.prediction_pass();
@@ -144,6 +293,20 @@ where
         self.indices.push(self.current_medial_start);
     }
 
+    // Records counts of the finished earleme.
+    #[cold]
+    fn record_metrics(&mut self) {
+        let items = self.medial.len() - self.current_medial_start;
+        let memory_use = self.chart_memory_use();
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.items_per_set.push(items);
+            let completions = metrics.completions;
+            metrics.completions_per_earleme.push(completions);
+            metrics.completions = 0;
+            metrics.peak_memory_use = metrics.peak_memory_use.max(memory_use);
+        }
+    }
+
     /// Checks whether the recognizer is exhausted. The recognizer is exhausted when it can't accept
     /// more input.
     #[inline]
@@ -151,14 +314,34 @@ where
         self.medial.len() == self.current_medial_start && self.complete.is_empty()
     }
 
+    // Records the first overflow.
+    pub(super) fn set_overflow(&mut self, error: OverflowError) {
+        if self.overflow.is_none() {
+            self.overflow = Some(error);
+        }
+    }
+
+    // Returns the LHS symbol of a rule.
+    #[inline(always)]
+    fn lhs(&self, dot: Dot) -> Symbol {
+        self.rules[dot as usize].lhs()
+    }
+
+    // Returns the RHS1 symbol of a rule, which is postdot in its medial items.
+    #[inline(always)]
+    fn postdot(&self, dot: Dot) -> Option<Symbol> {
+        self.rules[dot as usize].rhs1()
+    }
+
     /// Sorts medial items with deduplication.
     fn sort_medial_items(&mut self) {
-        let grammar = &self.grammar;
+        let rules = &self.rules;
         // Build index by postdot
-        // These medial positions themselves are sorted by postdot symbol.
+        // These medial positions themselves are sorted by postdot symbol. Unary rules
+        // come last.
         self.medial[self.current_medial_start..].sort_unstable_by(|a, b| {
-            (grammar.get_rhs1_cmp(a.dot), a.dot, a.origin).cmp(&(
-                grammar.get_rhs1_cmp(b.dot),
+            (rules[a.dot() as usize].rhs1, a.dot, a.origin).cmp(&(
+                rules[b.dot() as usize].rhs1,
                 b.dot,
                 b.origin,
             ))
@@ -167,7 +350,7 @@ where
 
     fn remove_unary_medial_items(&mut self) {
         while let Some(&item) = self.medial.last() {
-            if self.grammar.get_rhs1(item.dot).is_some() {
+            if self.postdot(item.dot()).is_some() {
                 break;
             }
             self.medial.pop();
@@ -175,7 +358,7 @@ where
     }
 
     fn remove_unreachable_sets(&mut self) {
-        let origin = |item: &Item<F::NodeRef>| item.origin as usize;
+        let origin = |item: &Item<F::NodeRef, P::Dot, P::Origin>| item.origin() as usize;
         let max_origin = self.medial[self.current_medial_start..]
             .iter()
             .map(origin)
@@ -193,6 +376,9 @@ where
         //   ^ m = 0
         // | 0 | 1 | 2 |
         let drop = diff - 1;
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.dropped_sets += drop;
+        }
         let new_medial_start = self.indices[self.indices.len() - 1 - drop];
         self.indices.truncate(self.indices.len() - drop);
         let current_medial_length = self.medial.len() - self.current_medial_start;
@@ -202,6 +388,9 @@ where
         self.medial
             .truncate(new_medial_start as usize + current_medial_length);
         self.current_medial_start = new_medial_start as usize;
+        let location = self.location();
+        self.locations.truncate(self.earleme - drop);
+        self.locations.push(location);
         self.earleme -= drop;
         self.predicted.truncate(self.earleme + 1);
         for dst in self.predicted[self.earleme].iter_mut() {
@@ -218,7 +407,7 @@ where
         // For each medial item in the current set, predict its postdot symbol.
         let row = &mut self.predicted[self.earleme];
         for ei in iter {
-            let postdot = self.grammar.get_rhs1(ei.dot).unwrap();
+            let postdot = self.rules[ei.dot() as usize].rhs1().unwrap();
             row.predict(postdot, self.grammar.predict(postdot));
         }
     }
@@ -243,14 +432,11 @@ where
                 // to   A ::= B   C •
                 //
                 // We might link to medial items by index, here.
-                let dot = self.medial[idx].dot;
-                if !self.grammar.can_follow(self.grammar.get_lhs(dot), hint) {
+                let dot = self.medial[idx].dot();
+                if !self.grammar.can_follow(self.lhs(dot), hint) {
                     continue;
                 }
-                self.heap_push_linked(CompletedItemLinked {
-                    idx: idx as u32,
-                    node: Some(rhs_link),
-                });
+                self.link_completed_item(idx, rhs_link);
             }
         } else {
             for idx in set_range {
@@ -259,25 +445,33 @@ where
                 // to   A ::= B   C •
                 //
                 // We might link to medial items by index, here.
-                self.heap_push_linked(CompletedItemLinked {
-                    idx: idx as u32,
-                    node: Some(rhs_link),
-                });
+                self.link_completed_item(idx, rhs_link);
             }
         }
     }
 
+    // Pushes a completed item that links to a medial item, unless its index overflows.
+    fn link_completed_item(&mut self, idx: usize, rhs_link: F::NodeRef) {
+        match P::Index::from_usize(idx) {
+            Some(idx) => self.heap_push_linked(CompletedItemLinked {
+                idx,
+                node: Some(rhs_link),
+            }),
+            None => self.set_overflow(OverflowError::MedialItems),
+        }
+    }
+
     fn medial_item_set_range(&mut self, set_id: Origin, sym: Symbol) -> Range<usize> {
         // Huh, can we reduce complexity here?
         let outer_start = self.indices[set_id as usize];
         let outer_end = self.indices[set_id as usize + 1];
         let specific_set = &self.medial[outer_start..outer_end];
 
-        let inner_start = if specific_set.len() >= 16 {
-            // When the set has 16 or more items, we use binary search to narrow down the range of
+        let inner_start = if specific_set.len() >= P::MEDIAL_BINARY_SEARCH_THRESHOLD {
+            // When the set has enough items, we use binary search to narrow down the range of
             // items.
             let set_idx = specific_set.binary_search_by(|ei| {
-                (self.grammar.get_rhs1(ei.dot), Ordering::Greater).cmp(&(Some(sym), Ordering::Less))
+                (self.postdot(ei.dot()), Ordering::Greater).cmp(&(Some(sym), Ordering::Less))
             });
             match set_idx {
                 Ok(idx) | Err(idx) => idx,
@@ -285,14 +479,14 @@ where
         } else {
             specific_set
                 .iter()
-                .take_while(|ei| self.grammar.get_rhs1(ei.dot).unwrap() < sym)
+                .take_while(|ei| self.postdot(ei.dot()).unwrap() < sym)
                 .count()
         };
 
         // The range contains items that have the same RHS1 symbol.
         let inner_end = specific_set[inner_start..]
             .iter()
-            .take_while(|ei| self.grammar.get_rhs1(ei.dot) == Some(sym))
+            .take_while(|ei| self.postdot(ei.dot()) == Some(sym))
             .count();
         outer_start + inner_start..outer_start + inner_start + inner_end
     }
@@ -317,10 +511,7 @@ where
                 // We could push to `medial` as well and link from `complete` to `medial`.
 
                 if let Some(hint) = self.lookahead_hint {
-                    if !self
-                        .grammar
-                        .can_follow(self.grammar.get_lhs(trans.dot), hint)
-                    {
+                    if !self.grammar.can_follow(self.lhs(trans.dot), hint) {
                         continue;
                     }
                 }
@@ -339,10 +530,7 @@ where
         for trans in self.grammar.binary_completions(sym) {
             if self.predicted[set_id as usize].get(trans.symbol.usize()) {
                 if let Some(hint) = self.lookahead_hint {
-                    if !self
-                        .grammar
-                        .first(self.grammar.get_rhs1(trans.dot).unwrap(), hint)
-                    {
+                    if !self.grammar.first(self.postdot(trans.dot).unwrap(), hint) {
                         continue;
                     }
                 }
@@ -351,11 +539,10 @@ where
                 // to   A ::=   B • C
                 // Where C is terminal or nonterminal.
 
-                self.medial.push(Item {
-                    origin: set_id,
-                    dot: trans.dot,
-                    node: rhs_link,
-                });
+                match Item::new(set_id, trans.dot, rhs_link) {
+                    Some(item) => self.medial.push(item),
+                    None => self.set_overflow(OverflowError::EarleySets),
+                }
             }
         }
     }
@@ -363,6 +550,8 @@ where
     /// Resets the recognizer to its initial state by removing all contents.
     pub fn reset(&mut self) {
         self.earleme = 0;
+        self.locations.clear();
+        self.locations.push(0);
         self.predict(self.grammar.start_sym());
         // Indices reset to [0, 0].
         self.indices.clear();
@@ -373,6 +562,10 @@ where
         // Remove items.
         self.medial.clear();
         self.complete.clear();
+        self.overflow = None;
+        if let Some(ref mut metrics) = self.metrics {
+            **metrics = RecognizerMetrics::default();
+        }
     }
 
     // Finished node access.
@@ -393,8 +586,8 @@ where
         if self.grammar.has_trivial_derivation() && self.earleme == 0 {
             Some(self.forest.nulling(self.grammar.externalized_start_sym()))
         } else {
-            let has_dot_before_eof = |item: &&Item<_>| item.dot == self.grammar.dot_before_eof();
-            let item_node = |item: &Item<_>| item.node;
+            let has_dot_before_eof = |item: &&Item<_, _, _>| item.dot() == self.grammar.dot_before_eof();
+            let item_node = |item: &Item<_, _, _>| item.node;
             self.medial.last().filter(has_dot_before_eof).map(item_node)
         }
     }
@@ -411,7 +604,7 @@ where
     }
 
     /// Accesses medial items.
-    pub fn medial_items(&self) -> MedialItems<F::NodeRef> {
+    pub fn medial_items(&self) -> MedialItems<F::NodeRef, P::Dot, P::Origin> {
         let indices_len = self.indices.len();
         // Next-to-last index, which points to the beginning of the set before the current set.
         // The current set is empty.
@@ -428,6 +621,12 @@ where
         self.earleme
     }
 
+    /// Returns the input location of the current Earley set, which is the number of
+    /// locations advanced since the start.
+    pub fn location(&self) -> Origin {
+        self.locations[self.earleme]
+    }
+
     // Completion
 
     /// Performs the completion pass.
@@ -439,10 +638,22 @@ where
         self.lookahead_hint = None;
     }
 
+    /// Performs the completion pass, which admits only completed items that the filter
+    /// accepts. Sums without admitted items are skipped.
+    pub fn complete_all_sums_filtered<C>(&mut self, mut filter: C)
+    where
+        C: FnMut(u32, Origin, Origin) -> bool,
+    {
+        while let Some(mut completion) = self.next_sum() {
+            completion.complete_filtered(&mut filter);
+        }
+        self.lookahead_hint = None;
+    }
+
     /// Allows iteration through groups of completions that have unique symbol and origin.
-    pub fn next_sum<'r>(&'r mut self) -> Option<CompleteSum<'g, 'r, F>> {
+    pub fn next_sum<'r>(&'r mut self) -> Option<CompleteSum<'g, 'r, F, P>> {
         if let Some(ei) = self.heap_peek() {
-            let lhs_sym = self.grammar.get_lhs(ei.dot);
+            let lhs_sym = self.lhs(ei.dot);
             Some(CompleteSum {
                 origin: ei.origin,
                 lhs_sym,
@@ -454,33 +665,92 @@ where
     }
 }
 
+impl<'g, F, P> Recognizer<'g, F, P>
+where
+    F: CollectGarbage,
+    P: PerformancePolicy,
+{
+    /// Discards forest nodes that are not referenced by any item in the chart. Items are
+    /// updated with new node references, so that the parse can continue.
+    ///
+    /// References to nodes that were obtained earlier, such as finished nodes at previous
+    /// locations, are invalidated.
+    pub fn collect_garbage(&mut self) {
+        {
+            let medial_nodes = self.medial.iter().map(|item| item.node);
+            let complete_nodes = self.complete.iter().filter_map(|item| item.node);
+            self.forest.mark_and_sweep(medial_nodes.chain(complete_nodes));
+        }
+        for item in &mut self.medial {
+            item.node = self.forest.relocate(item.node).unwrap();
+        }
+        for item in &mut self.complete {
+            if let Some(ref mut node) = item.node {
+                *node = self.forest.relocate(*node).unwrap();
+            }
+        }
+    }
+}
+
 /// A group of completed items.
-pub struct CompleteSum<'g, 'r, F>
+pub struct CompleteSum<'g, 'r, F, P = NullPerformancePolicy>
 where
     F: Forest,
+    P: PerformancePolicy,
 {
     /// The origin location of this completion.
     origin: Origin,
     /// The symbol of this completion.
     lhs_sym: Symbol,
     /// The recognizer.
-    recognizer: &'r mut Recognizer<'g, F>,
+    recognizer: &'r mut Recognizer<'g, F, P>,
 }
 
-impl<'g, 'r, F> CompleteSum<'g, 'r, F>
+impl<'g, 'r, F, P> CompleteSum<'g, 'r, F, P>
 where
     F: Forest,
+    P: PerformancePolicy,
     'g: 'r,
 {
-    /// Completes all items.
-    pub fn complete_entire_sum(&mut self) {
+    /// Completes all items. Returns the node of the sum.
+    pub fn complete_entire_sum(&mut self) -> F::NodeRef {
         self.recognizer.forest.begin_sum();
         // For each item, include it in the completion.
         while let Some(item) = self.next_summand() {
             self.push_summand(item);
         }
         // Use all items for completion.
-        self.complete_sum();
+        self.complete_sum()
+    }
+
+    /// Completes items that the filter accepts. The filter is given the external rule,
+    /// the origin location and the current location of an item. Returns the node of
+    /// the sum, unless all items are rejected, in which case the sum is skipped.
+    pub fn complete_filtered<C>(&mut self, filter: &mut C) -> Option<F::NodeRef>
+    where
+        C: FnMut(u32, Origin, Origin) -> bool,
+    {
+        let origin = self.recognizer.locations[self.origin as usize];
+        let location = self.recognizer.location();
+        let mut any_admitted = false;
+        while let Some(item) = self.next_summand() {
+            let admitted = match self.recognizer.grammar.external_origin(item.dot) {
+                Some(rule) => filter(rule, origin, location),
+                None => true,
+            };
+            if admitted {
+                if !any_admitted {
+                    self.recognizer.forest.begin_sum();
+                    any_admitted = true;
+                }
+                self.push_summand(item);
+            }
+        }
+        if any_admitted {
+            Some(self.complete_sum())
+        } else {
+            None
+        }
     }
 
     /// Skips all items.
@@ -493,9 +763,12 @@ where
     #[inline]
     pub fn next_summand(&mut self) -> Option<CompletedItem<F::NodeRef>> {
         if let Some(completion) = self.recognizer.heap_peek() {
-            let completion_lhs_sym = self.recognizer.grammar.get_lhs(completion.dot);
+            let completion_lhs_sym = self.recognizer.lhs(completion.dot);
             if self.origin == completion.origin && self.lhs_sym == completion_lhs_sym {
                 self.recognizer.heap_pop();
+                if let Some(ref mut metrics) = self.recognizer.metrics {
+                    metrics.completions += 1;
+                }
                 Some(completion)
             } else {
                 None
@@ -514,7 +787,8 @@ where
     /// Uses the completion to complete items in the recognizer.
     #[inline]
     pub fn complete_sum(&mut self) -> F::NodeRef {
-        let node = self.recognizer.forest.sum(self.lhs_sym, self.origin);
+        let origin = self.recognizer.locations[self.origin as usize];
+        let node = self.recognizer.forest.sum(self.lhs_sym, origin);
         self.recognizer.complete(self.origin, self.lhs_sym, node);
         node
     }
@@ -532,6 +806,14 @@ where
     }
 }
 
+// Narrows symbols of every rule to the policy's symbol type.
+pub(super) fn rule_symbols<S: ItemIndex>(grammar: &InternalGrammar) -> Vec<RuleSymbols<S>> {
+    (0..grammar.num_rules() as Dot)
+        .map(|dot| RuleSymbols::new(grammar.get_lhs(dot), grammar.get_rhs1(dot)))
+        .collect::<Option<Vec<_>>>()
+        .expect("the grammar has too many symbols for the policy's symbol type")
+}
+
 trait Predict {
     fn predict(&mut self, sym: Symbol, source: &BitVecSlice);
 }
@@ -550,3 +832,16 @@ impl Predict for BitVecSlice {
         }
     }
 }
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OverflowError::MedialItems => write!(f, "too many medial items"),
+            OverflowError::EarleySets => {
+                write!(f, "too many Earley sets for the policy's origin type")
+            }
+        }
+    }
+}
+
+impl Error for OverflowError {}
@@ -6,16 +6,18 @@ use gearley::forest::bocage::order::NullOrder;
 use gearley::forest::compact_bocage::order::NullOrder as CompactNullOrder;
 use gearley::forest::{Bocage, CompactBocage, NullForest};
 use gearley::grammar::InternalGrammar;
+use gearley::policy::PerformancePolicy;
 use gearley::recognizer::Recognizer;
 
 pub trait Parse {
     fn parse(&mut self, tokens: &[u32]) -> bool;
 }
 
-impl<'g, G> Parse for Recognizer<'g, Bocage<G>>
+impl<'g, G, P> Parse for Recognizer<'g, Bocage<G>, P>
 where
     Self: Debug,
     G: Borrow<InternalGrammar>,
+    P: PerformancePolicy,
 {
     #[inline]
     fn parse(&mut self, tokens: &[u32]) -> bool {
@@ -38,10 +40,11 @@ where
     }
 }
 
-impl<'g, G> Parse for Recognizer<'g, CompactBocage<G>>
+impl<'g, G, P> Parse for Recognizer<'g, CompactBocage<G>, P>
 where
     Self: Debug,
     G: Borrow<InternalGrammar>,
+    P: PerformancePolicy,
 {
     #[inline]
     fn parse(&mut self, tokens: &[u32]) -> bool {
@@ -64,9 +67,10 @@ where
     }
 }
 
-impl<'g> Parse for Recognizer<'g, NullForest>
+impl<'g, P> Parse for Recognizer<'g, NullForest, P>
 where
     Self: Debug,
+    P: PerformancePolicy,
 {
     #[inline]
     fn parse(&mut self, tokens: &[u32]) -> bool {
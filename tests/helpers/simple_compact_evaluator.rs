@@ -61,7 +61,7 @@ where
                 &mut NullingHandle => {
                     (self.null)(item.symbol, &mut self.values);
                 }
-                &mut LeafHandle => {
+                &mut LeafHandle(_) => {
                     let v = (self.leaf)(item.symbol);
                     self.values.push(v);
                 }
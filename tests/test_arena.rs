@@ -0,0 +1,210 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::forest::arena::{ArenaNode, NodeId};
+use gearley::forest::ArenaForest;
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+
+type Forest<'g> = ArenaForest<String, &'g InternalGrammar>;
+
+// Scans tokens together with their text.
+fn parse<'g>(rec: &mut Recognizer<'g, Forest<'g>>, tokens: &[u32], text: &str) {
+    for (i, (&token, ch)) in tokens.iter().zip(text.chars()).enumerate() {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token), ch.to_string());
+        assert!(rec.end_earleme(), "failed to parse after {}@{}", token, i);
+    }
+}
+
+// Computes values of all trees. Leaves are valued by their text.
+fn values<F>(forest: &Forest, node: NodeId, rule: &F) -> Vec<i32>
+where
+    F: Fn(u32, &[&i32]) -> i32,
+{
+    match forest.node(node) {
+        ArenaNode::Leaf { value, .. } => {
+            let value = match value.parse() {
+                Ok(digit) => digit,
+                Err(_) => "+-*/".find(&value[..]).unwrap_or(0) as i32,
+            };
+            return vec![value];
+        }
+        ArenaNode::NullingLeaf { .. } => unreachable!(),
+        _ => {}
+    }
+    let mut result = vec![];
+    for alternative in forest.alternatives(node) {
+        let mut args: Vec<Vec<i32>> = vec![vec![]];
+        for &factor in &alternative.factors {
+            let factor_values = values(forest, factor, rule);
+            args = args
+                .iter()
+                .flat_map(|prefix| {
+                    factor_values.iter().map(move |&value| {
+                        let mut args = prefix.clone();
+                        args.push(value);
+                        args
+                    })
+                })
+                .collect();
+        }
+        for args in args {
+            let args: Vec<&i32> = args.iter().collect();
+            result.push(rule(alternative.rule, &args[..]));
+        }
+    }
+    result
+}
+
+#[test]
+fn test_arena_precedenced_arith() {
+    let _ = env_logger::try_init();
+    let text = "1+(2*3-4)/55";
+    let tokens = precedenced_arith!('1' '+' '(' '2' '*' '3' '-' '4' ')' '/' '5' '5');
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, ArenaForest::new(&cfg));
+    parse(&mut rec, tokens, text);
+    let root = rec.finished_node().unwrap();
+    assert_eq!(rec.forest.symbol(root), external.start());
+    let results = values(&rec.forest, root, &precedenced_arith::rule);
+    assert_eq!(results, vec![1 + (2 * 3 - 4) / 55]);
+}
+
+#[test]
+fn test_arena_ambiguous_arith() {
+    let _ = env_logger::try_init();
+    let text = "2-0*3+1";
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, ArenaForest::new(&cfg));
+    parse(&mut rec, tokens, text);
+    let root = rec.finished_node().unwrap();
+    match rec.forest.node(root) {
+        ArenaNode::Sum { summands, .. } => assert!(summands.count() > 1),
+        _ => panic!("the root is not ambiguous"),
+    }
+    let mut results = values(&rec.forest, root, &ambiguous_arith::rule);
+    results.sort();
+    assert_eq!(results, vec![1, 2, 3, 7, 8]);
+}
+
+#[test]
+fn test_arena_leaf_values() {
+    let _ = env_logger::try_init();
+    let text = "12";
+    let tokens = precedenced_arith!('1' '2');
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, ArenaForest::new(&cfg));
+    parse(&mut rec, tokens, text);
+    let root = rec.finished_node().unwrap();
+    // Unfold single alternatives down to the number.
+    let mut node = root;
+    loop {
+        let alternatives = rec.forest.alternatives(node);
+        assert_eq!(alternatives.len(), 1);
+        if alternatives[0].factors.len() == 2 {
+            let factors = &alternatives[0].factors;
+            assert_eq!(rec.forest.leaf_value(factors[0]).map(|s| &s[..]), Some("1"));
+            assert!(rec.forest.leaf_value(factors[1]).is_none());
+            break;
+        }
+        node = alternatives[0].factors[0];
+    }
+}
+
+#[test]
+fn test_arena_nulling_intermediate() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (start, a, b, c, d, foo) = external.sym();
+    external
+        .rule(start)
+        .rhs([a, b, c, d, foo])
+        .rule(a)
+        .rhs([])
+        .rule(b)
+        .rhs([])
+        .rule(c)
+        .rhs([])
+        .rule(d)
+        .rhs([]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, ArenaForest::new(&cfg));
+    rec.begin_earleme();
+    rec.scan(foo, "foo".to_string());
+    assert!(rec.end_earleme());
+    let root = rec.finished_node().unwrap();
+    let alternatives = rec.forest.alternatives(root);
+    assert_eq!(alternatives.len(), 1);
+    assert_eq!(alternatives[0].rule, 0);
+    let factors = &alternatives[0].factors;
+    assert_eq!(
+        &factors[..4],
+        &[
+            NodeId::Nulling(a),
+            NodeId::Nulling(b),
+            NodeId::Nulling(c),
+            NodeId::Nulling(d)
+        ]
+    );
+    assert_eq!(rec.forest.leaf_value(factors[4]).map(|s| &s[..]), Some("foo"));
+}
+
+#[test]
+fn test_arena_external_symbols() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    // The unused symbol and the order of symbols make internal symbols differ from
+    // external ones.
+    let (_unused, x, a, b, y, z, start) = external.sym();
+    external
+        .rule(start)
+        .rhs([x, y, b])
+        .rule(x)
+        .rhs([])
+        .rule(y)
+        .rhs([a])
+        .rhs([z])
+        .rule(z)
+        .rhs([a]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, ArenaForest::new(&cfg));
+    parse(&mut rec, &[a.usize() as u32, b.usize() as u32], "ab");
+    let root = rec.finished_node().unwrap();
+    assert_eq!(rec.forest.symbol(root), start);
+    match rec.forest.node(root) {
+        ArenaNode::Product { rule, .. } => assert_eq!(rule, Some(0)),
+        _ => panic!("expected a product"),
+    }
+    let alternatives = rec.forest.alternatives(root);
+    assert_eq!(alternatives.len(), 1);
+    let factors = &alternatives[0].factors;
+    let symbols: Vec<_> = factors.iter().map(|&factor| rec.forest.symbol(factor)).collect();
+    assert_eq!(symbols, vec![x, y, b]);
+    match rec.forest.node(factors[0]) {
+        ArenaNode::NullingLeaf { symbol } => assert_eq!(symbol, x),
+        _ => panic!("expected a nulling leaf"),
+    }
+    match rec.forest.node(factors[1]) {
+        ArenaNode::Sum { nonterminal, .. } => assert_eq!(nonterminal, y),
+        _ => panic!("expected a sum"),
+    }
+}
@@ -0,0 +1,147 @@
+extern crate gearley;
+#[macro_use]
+extern crate gearley_derive;
+
+use gearley::ast::{downcast, Symbol, Syntax, SyntaxGrammar, Value};
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::Bocage;
+use gearley::recognizer::Recognizer;
+
+#[derive(Syntax, Debug, PartialEq)]
+enum Expr {
+    Binary(Box<Expr>, Op, Term),
+    Single(Term),
+}
+
+#[derive(Syntax, Debug, PartialEq)]
+enum Op {
+    Plus,
+    Minus,
+}
+
+#[derive(Syntax, Debug, PartialEq)]
+struct Term {
+    digit: Digit,
+    mark: Option_,
+}
+
+#[derive(Syntax, Debug, PartialEq)]
+enum Option_ {
+    Bang(Bang),
+    Question,
+}
+
+#[derive(Syntax, Debug, PartialEq)]
+struct Bang;
+
+#[derive(Syntax, Debug, PartialEq)]
+enum Digit {
+    One,
+    Two,
+    Three,
+}
+
+// A terminal that is built from its leaf value.
+#[derive(Debug, PartialEq)]
+struct Number(u32);
+
+impl Syntax for Number {
+    fn symbol(grammar: &mut SyntaxGrammar) -> Symbol {
+        if let Some(symbol) = grammar.get::<Self>() {
+            return symbol;
+        }
+        grammar.terminal_type::<Self>("Number", |value| Box::new(Number(value)))
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        downcast(value)
+    }
+}
+
+#[derive(Syntax, Debug, PartialEq)]
+struct Sum {
+    left: Number,
+    op: Op,
+    right: Number,
+}
+
+#[test]
+fn test_derive_grammar() {
+    let syntax = SyntaxGrammar::new::<Expr>();
+    let cfg = syntax.to_internal_grammar();
+    let expr = syntax.get::<Expr>().unwrap();
+    assert_eq!(syntax.grammar().start(), expr);
+    assert_eq!(cfg.symbol_name(expr), Some("Expr"));
+    let bang = syntax.symbol_named("Bang").unwrap();
+    assert_eq!(syntax.get::<Bang>(), Some(bang));
+    assert_eq!(cfg.symbol_name(bang), Some("Bang"));
+    assert!(syntax.symbol_named("Op::Minus").is_some());
+    assert!(syntax.symbol_named("Op").is_some());
+}
+
+#[test]
+fn test_derive_evaluate() {
+    let syntax = SyntaxGrammar::new::<Expr>();
+    let cfg = syntax.to_internal_grammar();
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    let tokens = [
+        "Digit::One",
+        "Bang",
+        "Op::Plus",
+        "Digit::Two",
+        "Option_::Question",
+        "Op::Minus",
+        "Digit::Three",
+        "Bang",
+    ];
+    for (i, name) in tokens.iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(syntax.symbol_named(name).unwrap(), i as u32);
+        assert!(rec.end_earleme(), "failed to parse after {}", name);
+    }
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, NullOrder::new());
+    let expr: Expr = syntax.evaluate(&mut rec.forest, root).unwrap();
+    let term = |digit, mark| Term { digit, mark };
+    assert_eq!(
+        expr,
+        Expr::Binary(
+            Box::new(Expr::Binary(
+                Box::new(Expr::Single(term(Digit::One, Option_::Bang(Bang)))),
+                Op::Plus,
+                term(Digit::Two, Option_::Question),
+            )),
+            Op::Minus,
+            term(Digit::Three, Option_::Bang(Bang)),
+        )
+    );
+}
+
+#[test]
+fn test_derive_leaf_values() {
+    let syntax = SyntaxGrammar::new::<Sum>();
+    let cfg = syntax.to_internal_grammar();
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    let number = syntax.symbol_named("Number").unwrap();
+    let tokens = [
+        (number, 12),
+        (syntax.symbol_named("Op::Minus").unwrap(), 0),
+        (number, 5),
+    ];
+    for &(symbol, value) in &tokens {
+        rec.begin_earleme();
+        rec.scan(symbol, value);
+        assert!(rec.end_earleme());
+    }
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, NullOrder::new());
+    let sum: Sum = syntax.evaluate(&rec.forest, root).unwrap();
+    assert_eq!(
+        sum,
+        Sum {
+            left: Number(12),
+            op: Op::Minus,
+            right: Number(5),
+        }
+    );
+}
@@ -0,0 +1,240 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+extern crate num;
+extern crate rand;
+extern crate rand_pcg;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+use num::BigUint;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use gearley::evaluator::{EvaluationError, EvaluationMode, Evaluator};
+use gearley::forest::{Bocage, CompactBocage};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use helpers::Parse;
+
+// A random grammar without cycles, together with a list of tokens.
+struct RandomGrammar {
+    grammar: Grammar,
+    // Alternatives for every nonterminal. Nonterminals only refer to later nonterminals.
+    rules: Vec<(Symbol, Vec<Vec<Symbol>>)>,
+    terminals: Vec<Symbol>,
+}
+
+impl RandomGrammar {
+    fn new<R: Rng>(rng: &mut R, padding: usize, num_nonterminals: usize) -> Self {
+        let mut grammar = Grammar::new();
+        // Unused symbols make IDs of terminals large.
+        for _ in 0..padding {
+            let _: Symbol = grammar.sym();
+        }
+        let terminals: Vec<Symbol> = (0..8).map(|_| grammar.sym()).collect();
+        let start = grammar.sym();
+        let nonterminals: Vec<Symbol> = (0..num_nonterminals).map(|_| grammar.sym()).collect();
+        let mut rules = vec![];
+        for (i, &lhs) in nonterminals.iter().enumerate() {
+            let num_alternatives = rng.gen_range(1, 4);
+            let mut alternatives: Vec<Vec<Symbol>> = (0..num_alternatives)
+                .map(|_| {
+                    let len = rng.gen_range(1, 4);
+                    (0..len)
+                        .map(|j| {
+                            // Alternatives of the first nonterminal end with a terminal.
+                            let last_of_first = i == 0 && j + 1 == len;
+                            if i + 1 < num_nonterminals && !last_of_first && rng.gen_bool(0.5) {
+                                nonterminals[rng.gen_range(i + 1, num_nonterminals)]
+                            } else {
+                                terminals[rng.gen_range(0, terminals.len())]
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            // Other nonterminals may be nullable. The first one is not, so that the list
+            // of it has no cycles.
+            if i > 0 && rng.gen_bool(0.25) {
+                alternatives.push(vec![]);
+            }
+            for rhs in &alternatives {
+                grammar.rule(lhs).rhs(&rhs[..]);
+            }
+            rules.push((lhs, alternatives));
+        }
+        // A list of the first nonterminal.
+        grammar
+            .rule(start)
+            .rhs(&[start, nonterminals[0]])
+            .rhs(&[nonterminals[0]]);
+        grammar.set_start(start);
+        RandomGrammar {
+            grammar,
+            rules,
+            terminals,
+        }
+    }
+
+    // Derives tokens from the first nonterminal `repeat` times.
+    fn sentence<R: Rng>(&self, rng: &mut R, repeat: usize) -> Vec<u32> {
+        let first = self.rules[0].0;
+        let mut tokens = vec![];
+        for _ in 0..repeat {
+            let mut stack = vec![first];
+            while let Some(symbol) = stack.pop() {
+                if self.terminals.contains(&symbol) {
+                    tokens.push(symbol.usize() as u32);
+                } else {
+                    let idx = self.rules.iter().position(|&(lhs, _)| lhs == symbol).unwrap();
+                    let alternatives = &self.rules[idx].1;
+                    let rhs = &alternatives[rng.gen_range(0, alternatives.len())];
+                    stack.extend(rhs.iter().rev().cloned());
+                }
+            }
+        }
+        tokens
+    }
+}
+
+// Token values are their positions, so leaves of both forests must keep them.
+fn leaf(symbol: Symbol, value: u32) -> Result<u64, EvaluationError> {
+    Ok(((value as u64) << 32) | symbol.usize() as u64)
+}
+
+fn rule(rule: u32, args: &[&u64]) -> Result<u64, EvaluationError> {
+    let mut hash = rule as u64 + 1;
+    for &&arg in args {
+        hash = hash.wrapping_mul(0x100_0000_01b3).wrapping_add(arg);
+    }
+    Ok(hash)
+}
+
+fn null(symbol: Symbol, values: &mut Vec<u64>) -> Result<(), EvaluationError> {
+    values.push(!(symbol.usize() as u64));
+    Ok(())
+}
+
+// Parses with both forests. Checks that they have the same trees and values.
+fn assert_same_evaluation(cfg: &InternalGrammar, tokens: &[u32]) {
+    let mut rec = Recognizer::new(cfg, Bocage::new(cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let count: BigUint = rec.forest.count_trees(root).unwrap();
+    let mut evaluator = Evaluator::with_mode(leaf, rule, null, EvaluationMode::FirstTree);
+    let values = evaluator.evaluate(&mut rec.forest.traverse(), root);
+
+    let mut compact_rec = Recognizer::new(cfg, CompactBocage::new(cfg));
+    assert!(compact_rec.parse(tokens));
+    let compact_root = compact_rec.finished_node().unwrap();
+    let compact_count: BigUint = compact_rec.forest.count_trees(compact_root).unwrap();
+    let mut evaluator = Evaluator::with_mode(leaf, rule, null, EvaluationMode::FirstTree);
+    let compact_values =
+        evaluator.evaluate_compact(&mut compact_rec.forest.traverse(), compact_root);
+
+    assert_eq!(count, compact_count);
+    assert!(values.is_ok());
+    assert_eq!(values, compact_values);
+}
+
+#[test]
+fn test_random_grammars() {
+    let _ = env_logger::try_init();
+    let mut rng = Pcg32::seed_from_u64(44);
+    for &(padding, num_nonterminals, repeat) in &[
+        (0, 10, 50),
+        (0, 100, 20),
+        (300, 40, 200),
+        (5000, 400, 20),
+        (9000, 40, 400),
+    ] {
+        for _ in 0..3 {
+            let random = RandomGrammar::new(&mut rng, padding, num_nonterminals);
+            let cfg = InternalGrammar::from_grammar(&random.grammar);
+            let tokens = random.sentence(&mut rng, repeat);
+            eprintln!("case {} {} {}", padding, num_nonterminals, tokens.len());
+            assert_same_evaluation(&cfg, &tokens[..]);
+        }
+    }
+}
+
+#[test]
+fn test_large_actions() {
+    let _ = env_logger::try_init();
+    // Rules that come last have actions that do not fit in small products.
+    let mut grammar = Grammar::new();
+    let (start, list, item, unused, a, b) = grammar.sym();
+    let unused_terminals: Vec<Symbol> = (0..300).map(|_| grammar.sym()).collect();
+    for &first in &unused_terminals {
+        for &second in &unused_terminals {
+            grammar.rule(unused).rhs(&[first, second]);
+        }
+    }
+    grammar
+        .rule(start)
+        .rhs(&[list])
+        .rhs(&[unused])
+        .rule(list)
+        .rhs(&[list, item])
+        .rhs(&[item])
+        .rule(item)
+        .rhs(&[a, b])
+        .rhs(&[a, item, b]);
+    grammar.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&grammar);
+    let mut tokens = vec![];
+    for depth in 0..100 {
+        for _ in 0..depth % 7 + 1 {
+            tokens.push(a.usize() as u32);
+        }
+        for _ in 0..depth % 7 + 1 {
+            tokens.push(b.usize() as u32);
+        }
+    }
+    assert_same_evaluation(&cfg, &tokens[..]);
+}
+
+#[test]
+fn test_large_sums() {
+    let _ = env_logger::try_init();
+    // A sum with more alternatives than a small sum can count.
+    let mut grammar = Grammar::new();
+    let (start, token) = grammar.sym();
+    for _ in 0..10_000 {
+        let alternative: Symbol = grammar.sym();
+        grammar.rule(start).rhs(&[alternative, token]);
+        grammar.rule(alternative).rhs(&[token]);
+    }
+    grammar.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&grammar);
+    let tokens = [token.usize() as u32; 2];
+    assert_same_evaluation(&cfg, &tokens[..]);
+
+    let mut rec = Recognizer::new(&cfg, CompactBocage::new(&cfg));
+    assert!(rec.parse(&tokens[..]));
+    let root = rec.finished_node().unwrap();
+    let count: BigUint = rec.forest.count_trees(root).unwrap();
+    assert_eq!(count, BigUint::from(10_000u32));
+    let mut bocage_rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(bocage_rec.parse(&tokens[..]));
+    let bocage_root = bocage_rec.finished_node().unwrap();
+    let mut evaluator = Evaluator::new(leaf, rule, null);
+    let mut values = evaluator
+        .evaluate_compact(&mut rec.forest.traverse(), root)
+        .unwrap();
+    let mut bocage_values = evaluator
+        .evaluate(&mut bocage_rec.forest.traverse(), bocage_root)
+        .unwrap();
+    values.sort();
+    bocage_values.sort();
+    assert_eq!(values.len(), 10_000);
+    assert_eq!(values, bocage_values);
+}
@@ -0,0 +1,79 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::Symbol;
+
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::bocage::trees::ParseTree;
+use gearley::forest::Bocage;
+use gearley::grammar::InternalGrammar;
+use gearley::item::Origin;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+
+fn evaluate(tree: &ParseTree) -> i32 {
+    match *tree {
+        ParseTree::Node {
+            action,
+            ref children,
+            ..
+        } => {
+            let values: Vec<i32> = children.iter().map(evaluate).collect();
+            let args: Vec<&i32> = values.iter().collect();
+            ambiguous_arith::rule(action, &args[..])
+        }
+        ParseTree::Leaf { symbol, .. } => ambiguous_arith::leaf(symbol),
+        ParseTree::Nulling { .. } => unreachable!(),
+    }
+}
+
+fn parse_with_filter<P>(cfg: &InternalGrammar, mut filter: P) -> Option<Vec<i32>>
+where
+    P: FnMut(u32, Origin, Origin) -> bool,
+{
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let mut rec = Recognizer::new(cfg, Bocage::new(cfg));
+    for (i, &token) in tokens.iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token as u32), i as u32);
+        assert!(rec.end_earleme_with_filter(&mut filter));
+    }
+    let root = rec.finished_node()?;
+    rec.forest.mark_alive(root, NullOrder::new());
+    let mut results: Vec<i32> = rec.forest.trees(root).map(|tree| evaluate(&tree)).collect();
+    results.sort();
+    Some(results)
+}
+
+#[test]
+fn test_completion_filter() {
+    let _ = env_logger::try_init();
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    assert_eq!(
+        parse_with_filter(&cfg, |_, _, _| true),
+        Some(vec![1, 2, 3, 7, 8])
+    );
+    let mut rejected = 0;
+    // Rejects `0*3+1` as an expression.
+    let filter = |rule, origin, location| {
+        let admitted = !(rule == 0 && origin == 2 && location == 6);
+        if !admitted {
+            rejected += 1;
+        }
+        admitted
+    };
+    assert_eq!(parse_with_filter(&cfg, filter), Some(vec![3, 7, 8]));
+    assert_eq!(rejected, 2);
+    // Rejects every binary expression that is completed at the last location.
+    let filter = |rule, _, location| !(rule == 0 && location == 6);
+    assert_eq!(parse_with_filter(&cfg, filter), None);
+}
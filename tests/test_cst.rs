@@ -0,0 +1,100 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::cst::{CstBuilder, SyntaxElement, SyntaxNode, TextRange};
+use gearley::forest::Bocage;
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+#[test]
+fn test_cst() {
+    let _ = env_logger::try_init();
+    let tokens = precedenced_arith!('1' '+' '(' '2' '3' '*' '4' ')');
+    // Tokens keep their whitespace, so the tree is lossless.
+    let texts = ["1 ", "+ ", "(", "2", "3", " * ", "4", ")"];
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut builder = CstBuilder::new(|_symbol, value| texts[value as usize].to_string());
+    let green = builder.build(&rec.forest, root);
+    assert_eq!(green.text(), "1 + (23 * 4)");
+
+    let (sum, factor, number) = (Symbol::from(0u32), Symbol::from(2u32), Symbol::from(3u32));
+    let root = SyntaxNode::new_root(green);
+    assert_eq!(root.kind(), sum);
+    assert_eq!(root.text_range(), TextRange { start: 0, end: 12 });
+    let kinds: Vec<_> = root.children_with_tokens().map(|child| child.kind()).collect();
+    assert_eq!(kinds, vec![sum, Symbol::from(4u32), Symbol::from(1u32)]);
+
+    let tokens = root.descendant_tokens();
+    let ranges: Vec<_> = tokens
+        .iter()
+        .map(|token| (token.text(), token.text_range().start, token.text_range().end))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            ("1 ", 0, 2),
+            ("+ ", 2, 4),
+            ("(", 4, 5),
+            ("2", 5, 6),
+            ("3", 6, 7),
+            (" * ", 7, 10),
+            ("4", 10, 11),
+            (")", 11, 12),
+        ]
+    );
+    // The digit `2` is in the number `23`, which is in the parenthesized factor.
+    let ancestors: Vec<_> = tokens[3].parent().ancestors().collect();
+    assert_eq!(ancestors[0].kind(), number);
+    assert_eq!(ancestors[0].text(), "23");
+    assert_eq!(ancestors[0].text_range(), TextRange { start: 5, end: 7 });
+    let parenthesized = ancestors
+        .iter()
+        .find(|node| node.kind() == factor && node.text().starts_with('('))
+        .unwrap();
+    assert_eq!(parenthesized.text_range(), TextRange { start: 4, end: 12 });
+    match parenthesized.children_with_tokens().nth(1).unwrap() {
+        SyntaxElement::Node(node) => assert_eq!(node.text(), "23 * 4"),
+        SyntaxElement::Token(_) => panic!("expected a node"),
+    }
+    assert!(ancestors.last().unwrap().parent().is_none());
+}
+
+#[test]
+fn test_cst_nulling() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    // The unused symbol makes internal symbols differ from external ones.
+    let (start, _unused, x, a, b) = external.sym();
+    external.rule(start).rhs([x, a, b])
+            .rule(x).rhs([]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(&[a.usize() as u32, b.usize() as u32]));
+    let root = rec.finished_node().unwrap();
+    let mut builder = CstBuilder::new(|symbol, _value| format!("{}", symbol.usize()));
+    let green = builder.build(&rec.forest, root);
+    assert_eq!(green.text(), "34");
+
+    let root = SyntaxNode::new_root(green);
+    assert_eq!(root.kind(), start);
+    let kinds: Vec<_> = root.children_with_tokens().map(|child| child.kind()).collect();
+    assert_eq!(kinds, vec![x, a, b]);
+}
@@ -0,0 +1,104 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::forest::{Bocage, CompactBocage};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+const NAMES: &'static [&'static str] = &["expr", "op", "num", "plus", "minus", "mul", "div"];
+
+macro_rules! test_dot {
+    ($Bocage:ident) => {{
+        let _ = env_logger::try_init();
+        let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+        let external = ambiguous_arith::grammar();
+        let mut cfg = InternalGrammar::from_grammar(&external);
+        for (i, name) in NAMES.iter().enumerate() {
+            cfg.set_symbol_name(Symbol::from(i), name);
+        }
+        let mut rec = Recognizer::new(&cfg, $Bocage::new(&cfg));
+        assert!(rec.parse(tokens));
+        let root = rec.finished_node().unwrap();
+        let mut out = vec![];
+        rec.forest.write_dot(root, &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.starts_with("digraph forest {\n"));
+        assert!(dot.ends_with("}\n"));
+        // The root is ambiguous.
+        assert!(dot.contains(&format!(
+            "n{} [shape=ellipse, label=\"expr\", style=filled",
+            root_id(&dot)
+        )));
+        assert!(dot.contains("label=\"rule 0\""));
+        // Symbols without names are labeled with their IDs.
+        assert!(dot.contains("label=\"#7"));
+        dot
+    }};
+}
+
+macro_rules! test_dot_nulling {
+    ($Bocage:ident) => {{
+        let _ = env_logger::try_init();
+        let mut external = Grammar::new();
+        // The unused symbol makes internal symbols differ from external ones.
+        let (start, _unused, x, a, b) = external.sym();
+        external.rule(start).rhs([x, a, b])
+                .rule(x).rhs([]);
+        external.set_start(start);
+        let mut cfg = InternalGrammar::from_grammar(&external);
+        for &(symbol, name) in &[(start, "start"), (x, "x"), (a, "a"), (b, "b")] {
+            cfg.set_symbol_name(symbol, name);
+        }
+        let mut rec = Recognizer::new(&cfg, $Bocage::new(&cfg));
+        assert!(rec.parse(&[a.usize() as u32, b.usize() as u32]));
+        let root = rec.finished_node().unwrap();
+        let mut out = vec![];
+        rec.forest.write_dot(root, &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains("label=\"rule 0\""));
+        assert!(dot.contains("label=\"a"));
+        assert!(dot.contains("label=\"x = ()\""));
+    }};
+}
+
+// Finds the ID of the first node, which is the root.
+fn root_id(dot: &str) -> &str {
+    let line = dot.lines().nth(2).unwrap().trim();
+    &line[1..line.find(' ').unwrap()]
+}
+
+#[test]
+fn test_dot() {
+    let dot = test_dot!(Bocage);
+    // Leaves carry their values.
+    assert!(dot.contains("label=\"minus = 1\""));
+}
+
+#[test]
+fn test_dot_compact() {
+    let dot = test_dot!(CompactBocage);
+    assert!(dot.contains("label=\"minus\""));
+}
+
+#[test]
+fn test_dot_nulling() {
+    test_dot_nulling!(Bocage);
+}
+
+#[test]
+fn test_dot_nulling_compact() {
+    test_dot_nulling!(CompactBocage);
+}
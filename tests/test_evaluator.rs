@@ -0,0 +1,179 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use std::cell::Cell;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::evaluator::{EvaluationError, EvaluationMode, Evaluator};
+use gearley::forest::{Bocage, CompactBocage};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+fn leaf(sym: Symbol, _: u32) -> Result<i32, EvaluationError> {
+    Ok(ambiguous_arith::leaf(sym))
+}
+
+fn rule(rule: u32, args: &[&i32]) -> Result<i32, EvaluationError> {
+    Ok(ambiguous_arith::rule(rule, args))
+}
+
+fn null(symbol: Symbol, _: &mut Vec<i32>) -> Result<(), EvaluationError> {
+    Err(EvaluationError::InvalidNulling { symbol })
+}
+
+macro_rules! test_evaluate {
+    ($Bocage:ident, $evaluate:ident) => {
+        let _ = env_logger::try_init();
+        let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+        let external = ambiguous_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        for &(mode, ref expected) in &[
+            (EvaluationMode::AllTrees, vec![2, 1, 3, 7, 8]),
+            (EvaluationMode::FirstTree, vec![2]),
+        ] {
+            let bocage = $Bocage::new(&cfg);
+            let mut rec = Recognizer::new(&cfg, bocage);
+            assert!(rec.parse(tokens));
+            let root = rec.finished_node().unwrap();
+            let mut evaluator = Evaluator::with_mode(leaf, rule, null, mode);
+            let mut traversal = rec.forest.traverse();
+            let results = evaluator.$evaluate(&mut traversal, root);
+            assert_eq!(results.as_ref(), Ok(expected));
+        }
+    };
+}
+
+#[test]
+fn test_evaluate() {
+    test_evaluate!(Bocage, evaluate);
+}
+
+#[test]
+fn test_evaluate_compact() {
+    test_evaluate!(CompactBocage, evaluate_compact);
+}
+
+#[test]
+fn test_evaluation_error() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let bocage = Bocage::new(&cfg);
+    let mut rec = Recognizer::new(&cfg, bocage);
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut evaluator = Evaluator::new(
+        leaf,
+        |rule: u32, args: &[&i32]| {
+            if rule == 0 && *args[1] == 2 {
+                // Multiplication is not supported.
+                Err(EvaluationError::InvalidRule { rule })
+            } else {
+                Ok(ambiguous_arith::rule(rule, args))
+            }
+        },
+        null,
+    );
+    let mut traversal = rec.forest.traverse();
+    let result = evaluator.evaluate(&mut traversal, root);
+    assert_eq!(result, Err(EvaluationError::InvalidRule { rule: 0 }));
+}
+
+macro_rules! test_evaluation_after_error {
+    ($Bocage:ident, $evaluate:ident) => {
+        let _ = env_logger::try_init();
+        let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+        let external = ambiguous_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        let calls = Cell::new(0);
+        let failing_call = Cell::new(0);
+        let mut evaluator = Evaluator::new(
+            leaf,
+            |rule: u32, args: &[&i32]| {
+                calls.set(calls.get() + 1);
+                if calls.get() == failing_call.get() {
+                    Err(EvaluationError::InvalidRule { rule })
+                } else {
+                    Ok(ambiguous_arith::rule(rule, args))
+                }
+            },
+            null,
+        );
+        let mut results = vec![];
+        for _ in 0..3 {
+            let mut rec = Recognizer::new(&cfg, $Bocage::new(&cfg));
+            assert!(rec.parse(tokens));
+            let root = rec.finished_node().unwrap();
+            calls.set(0);
+            results.push(evaluator.$evaluate(&mut rec.forest.traverse(), root));
+            // The last call is for the root, after its other values are computed.
+            failing_call.set(if results.len() == 1 { calls.get() } else { 0 });
+        }
+        let expected = vec![2, 1, 3, 7, 8];
+        assert_eq!(results[0], Ok(expected.clone()));
+        assert_eq!(results[1], Err(EvaluationError::InvalidRule { rule: 0 }));
+        assert_eq!(results[2], Ok(expected));
+    };
+}
+
+#[test]
+fn test_evaluation_after_error() {
+    test_evaluation_after_error!(Bocage, evaluate);
+}
+
+#[test]
+fn test_evaluation_after_error_compact() {
+    test_evaluation_after_error!(CompactBocage, evaluate_compact);
+}
+
+macro_rules! test_leaf_values {
+    ($Bocage:ident, $evaluate:ident) => {
+        let _ = env_logger::try_init();
+        let mut external = Grammar::new();
+        let (sum, num) = external.sym();
+        external.rule(sum).rhs([sum, num])
+                .rule(sum).rhs([num]);
+        external.set_start(sum);
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut rec = Recognizer::new(&cfg, $Bocage::new(&cfg));
+        for &value in &[3, 40, 500] {
+            rec.begin_earleme();
+            rec.scan(num, value);
+            assert!(rec.end_earleme());
+        }
+        let root = rec.finished_node().unwrap();
+        rec.forest.mark_alive(root, NullOrder::new());
+        let mut evaluator = Evaluator::new(
+            |_, value| Ok(value as i32),
+            |rule, args: &[&i32]| Ok(if rule == 0 { *args[0] + *args[1] } else { *args[0] }),
+            null,
+        );
+        let results = evaluator.$evaluate(&mut rec.forest.traverse(), root);
+        assert_eq!(results, Ok(vec![543]));
+    };
+}
+
+#[test]
+fn test_leaf_values() {
+    use gearley::forest::bocage::order::NullOrder;
+    test_leaf_values!(Bocage, evaluate);
+}
+
+#[test]
+fn test_leaf_values_compact() {
+    use gearley::forest::compact_bocage::order::NullOrder;
+    test_leaf_values!(CompactBocage, evaluate_compact);
+}
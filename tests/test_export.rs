@@ -0,0 +1,124 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::Bocage;
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+#[test]
+fn test_export_tree() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (start, pair, item, open, close) = external.sym();
+    external.rule(start).rhs([open, pair, close])
+            .rule(pair).rhs([item, item])
+            .rule(item).rhs([open]);
+    external.set_start(start);
+    let mut cfg = InternalGrammar::from_grammar(&external);
+    cfg.set_symbol_name(start, "start");
+    cfg.set_symbol_name(pair, "pair");
+    cfg.set_symbol_name(open, "(");
+    let mut bocage = Bocage::new(&cfg);
+    bocage.enable_spans();
+    let mut rec = Recognizer::new(&cfg, bocage);
+    for (i, &token) in [open, open, open, close].iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(token, i as u32 * 10);
+        assert!(rec.end_earleme());
+    }
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, NullOrder::new());
+    let tree = rec.forest.trees(root).next().unwrap();
+
+    let mut json = vec![];
+    rec.forest.write_tree_json(&tree, &mut json).unwrap();
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        concat!(
+            r#"{"rule":0,"symbol":0,"name":"start","span":[0,4],"children":["#,
+            r#"{"symbol":3,"name":"(","value":0,"span":[0,1]},"#,
+            r#"{"rule":1,"symbol":1,"name":"pair","span":[1,3],"children":["#,
+            r#"{"rule":2,"symbol":2,"span":[1,2],"children":["#,
+            r#"{"symbol":3,"name":"(","value":10,"span":[1,2]}]},"#,
+            r#"{"rule":2,"symbol":2,"span":[2,3],"children":["#,
+            r#"{"symbol":3,"name":"(","value":20,"span":[2,3]}]}]},"#,
+            r#"{"symbol":4,"value":30,"span":[3,4]}]}"#,
+        )
+    );
+
+    let mut sexp = vec![];
+    rec.forest.write_tree_sexp(&tree, &mut sexp).unwrap();
+    assert_eq!(
+        String::from_utf8(sexp).unwrap(),
+        concat!(
+            r#"(start :rule 0 :span (0 4) ("(" :value 0 :span (0 1)) "#,
+            r#"(pair :rule 1 :span (1 3) "#,
+            r##"("#2" :rule 2 :span (1 2) ("(" :value 10 :span (1 2))) "##,
+            r##"("#2" :rule 2 :span (2 3) ("(" :value 20 :span (2 3)))) "##,
+            r##"("#4" :value 30 :span (3 4)))"##,
+        )
+    );
+}
+
+#[test]
+fn test_export_forest() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let mut cfg = InternalGrammar::from_grammar(&external);
+    cfg.set_symbol_name(Symbol::from(0u32), "expr");
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut json = vec![];
+    rec.forest.write_forest_json(root, &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with(r#"{"root":"#));
+    assert!(json.contains(r#""kind":"sum","symbol":0,"name":"expr","summands":["#));
+    assert!(json.contains(r#""kind":"product","rule":0,"factors":["#));
+    assert!(json.contains(r#""kind":"leaf","symbol":4,"value":1}"#));
+    assert!(json.ends_with("]}"));
+}
+
+#[test]
+fn test_export_nulling() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    // The unused symbol makes internal symbols differ from external ones.
+    let (start, _unused, x, a, b) = external.sym();
+    external.rule(start).rhs([x, a, b])
+            .rule(x).rhs([]);
+    external.set_start(start);
+    let mut cfg = InternalGrammar::from_grammar(&external);
+    cfg.set_symbol_name(x, "x");
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(&[a.usize() as u32, b.usize() as u32]));
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, NullOrder::new());
+
+    let mut json = vec![];
+    rec.forest.write_forest_json(root, &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains(r#""kind":"nulling","symbol":2,"name":"x""#));
+
+    let tree = rec.forest.trees(root).next().unwrap();
+    let mut json = vec![];
+    rec.forest.write_tree_json(&tree, &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains(r#"{"symbol":2,"name":"x","nulling":true}"#));
+    assert!(json.contains(r#"{"symbol":3,"value":0}"#));
+}
@@ -0,0 +1,115 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use std::thread;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::evaluator::{EvaluationError, EvaluationMode, ParallelEvaluator};
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::{Bocage, FrozenBocage};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+fn leaf(sym: Symbol, _: u32) -> Result<i32, EvaluationError> {
+    Ok(ambiguous_arith::leaf(sym))
+}
+
+fn rule(rule: u32, args: &[&i32]) -> Result<i32, EvaluationError> {
+    Ok(ambiguous_arith::rule(rule, args))
+}
+
+fn null(symbol: Symbol, _: &mut Vec<i32>) -> Result<(), EvaluationError> {
+    Err(EvaluationError::InvalidNulling { symbol })
+}
+
+fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+#[test]
+fn test_evaluate_frozen() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    for &(mode, ref expected) in &[
+        (EvaluationMode::AllTrees, vec![2, 1, 3, 7, 8]),
+        (EvaluationMode::FirstTree, vec![2]),
+    ] {
+        let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+        assert!(rec.parse(tokens));
+        let root = rec.finished_node().unwrap();
+        let frozen = rec.forest.freeze();
+        assert_send_sync(&frozen);
+        let evaluator = ParallelEvaluator::with_mode(leaf, rule, null, mode);
+        let results = evaluator.evaluate(&frozen, root);
+        assert_eq!(results.as_ref(), Ok(expected));
+    }
+}
+
+#[test]
+fn test_evaluate_shared() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (start, item, a, b) = external.sym();
+    external.rule(start).rhs([start, item])
+                        .rhs([item])
+            .rule(item).rhs([a, b]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    for i in 0..4000 {
+        rec.begin_earleme();
+        rec.scan(if i % 2 == 0 { a } else { b }, i);
+        assert!(rec.end_earleme());
+    }
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, NullOrder::new());
+    let frozen: FrozenBocage<_> = rec.forest.freeze();
+    let rule = |rule: u32, args: &[&u64]| -> Result<u64, EvaluationError> {
+        match rule {
+            0 => Ok(*args[0] + *args[1]),
+            1 => Ok(*args[0]),
+            2 => Ok(*args[0] + *args[1]),
+            _ => Err(EvaluationError::InvalidRule { rule }),
+        }
+    };
+    let mut evaluator = ParallelEvaluator::new(
+        // Token values are positions.
+        |_: Symbol, value: u32| Ok(value as u64),
+        rule,
+        |symbol, _: &mut Vec<u64>| Err(EvaluationError::InvalidNulling { symbol }),
+    );
+    evaluator.set_threads(4);
+    // The frozen bocage is evaluated on several threads at once.
+    let results: Vec<_> = thread::scope(|scope| {
+        let threads: Vec<_> = (0..2)
+            .map(|_| scope.spawn(|| evaluator.evaluate(&frozen, root)))
+            .collect();
+        threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+    });
+    let sum = 3999 * 4000 / 2;
+    assert_eq!(results, vec![Ok(vec![sum]), Ok(vec![sum])]);
+}
+
+#[test]
+#[should_panic(expected = "liveness must be marked before freezing")]
+fn test_freeze_unmarked() {
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    rec.begin_earleme();
+    rec.scan(Symbol::from(ambiguous_arith!('2')[0] as u32), 0);
+    assert!(rec.end_earleme());
+    rec.forest.freeze();
+}
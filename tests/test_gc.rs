@@ -0,0 +1,121 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::Symbol;
+
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::compact_bocage::order::NullOrder as CompactNullOrder;
+use gearley::forest::{Bocage, CompactBocage, CollectGarbage};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::{SimpleCompactEvaluator, SimpleEvaluator};
+
+const SUM_TOKENS: &'static [u32] = precedenced_arith!(
+    '1' '+' '(' '2' '*' '3' '-' '4' ')' '/'
+    '(' '5' '5' ')' '-' '(' '5' '4' ')' '*'
+    '5' '5' '+' '6' '2' '-' '1' '3' '-' '('
+    '(' '3' '6' ')' ')'
+);
+
+fn parse<F>(rec: &mut Recognizer<F>, tokens: &[u32])
+where
+    F: CollectGarbage<LeafValue = u32>,
+{
+    for (i, &token) in tokens.iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token), i as u32);
+        assert!(rec.end_earleme(), "failed to parse after {}@{}", token, i);
+    }
+}
+
+// Collects garbage after every token. Checks that the graph never grows through a collection.
+// Returns whether some collection shrank it.
+macro_rules! parse_with_gc {
+    ($rec:expr, $tokens:expr) => {{
+        let mut shrunk = false;
+        // Without lookahead, more nodes become garbage.
+        for (i, &token) in $tokens.iter().enumerate() {
+            $rec.begin_earleme();
+            $rec.scan(Symbol::from(token as u32), i as u32);
+            assert!($rec.end_earleme(), "failed to parse after {}@{}", token, i);
+            let len = $rec.forest.len();
+            $rec.collect_garbage();
+            assert!($rec.forest.len() <= len);
+            shrunk |= $rec.forest.len() < len;
+        }
+        shrunk
+    }};
+}
+
+macro_rules! test_collect_garbage {
+    ($Bocage:ident, $SimpleEvaluator:ident, $NullOrder:ident) => {
+        let _ = env_logger::try_init();
+        let external = precedenced_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut results = vec![];
+        let mut lens = vec![];
+        for &collect in &[false, true] {
+            let mut evaluator = $SimpleEvaluator::new(
+                precedenced_arith::leaf,
+                precedenced_arith::rule,
+                |_, _: &mut Vec<i32>| unreachable!(),
+            );
+            let bocage = $Bocage::new(&cfg);
+            let mut rec = Recognizer::new(&cfg, bocage);
+            if collect {
+                assert!(parse_with_gc!(rec, SUM_TOKENS), "no collection shrank the graph");
+            } else {
+                parse(&mut rec, SUM_TOKENS);
+            }
+            lens.push(rec.forest.len());
+            let root = rec.finished_node().unwrap();
+            rec.forest.mark_alive(root, $NullOrder::new());
+            let mut traversal = rec.forest.traverse();
+            results.push(evaluator.traverse(&mut traversal, root));
+        }
+        assert!(lens[1] < lens[0]);
+        // Relocated handles lead to the same values.
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[1], &[1 + (2 * 3 - 4) / 55 - 54 * 55 + 62 - 13 - 36]);
+    };
+}
+
+#[test]
+fn test_collect_garbage() {
+    test_collect_garbage!(Bocage, SimpleEvaluator, NullOrder);
+}
+
+#[test]
+fn test_collect_garbage_compact() {
+    test_collect_garbage!(CompactBocage, SimpleCompactEvaluator, CompactNullOrder);
+}
+
+#[test]
+fn test_collect_garbage_ambiguous() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut evaluator = SimpleEvaluator::new(
+        ambiguous_arith::leaf,
+        ambiguous_arith::rule,
+        |_, _: &mut Vec<i32>| unreachable!(),
+    );
+    let bocage = Bocage::new(&cfg);
+    let mut rec = Recognizer::new(&cfg, bocage);
+    parse_with_gc!(rec, tokens);
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, NullOrder::new());
+    let mut traversal = rec.forest.traverse();
+    let results = evaluator.traverse(&mut traversal, root);
+    assert_eq!(results, vec![2, 1, 3, 7, 8]);
+}
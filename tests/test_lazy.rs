@@ -0,0 +1,158 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::Symbol;
+
+use gearley::evaluator::{EvaluationError, EvaluationMode, Evaluator, LazyEvaluator};
+use gearley::forest::{Bocage, Span};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+fn null(symbol: Symbol, _: &mut Vec<i32>) -> Result<(), EvaluationError> {
+    Err(EvaluationError::InvalidNulling { symbol })
+}
+
+#[test]
+fn test_lazy_evaluate() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let leaf = |sym, _| Ok(ambiguous_arith::leaf(sym));
+    let rule = |rule, args: &[&i32]| Ok(ambiguous_arith::rule(rule, args));
+    for &(mode, ref expected) in &[
+        (EvaluationMode::AllTrees, vec![2, 1, 3, 7, 8]),
+        (EvaluationMode::FirstTree, vec![2]),
+    ] {
+        let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+        assert!(rec.parse(tokens));
+        let root = rec.finished_node().unwrap();
+        {
+            let mut lazy = LazyEvaluator::with_mode(&rec.forest, leaf, rule, null, mode);
+            assert_eq!(lazy.values(root), None);
+            assert_eq!(lazy.evaluate(root), Ok(&expected[..]));
+            assert_eq!(lazy.values(root), Some(&expected[..]));
+        }
+        // The bocage is not changed by lazy evaluation.
+        let mut evaluator = Evaluator::with_mode(leaf, rule, null, mode);
+        let mut traversal = rec.forest.traverse();
+        assert_eq!(evaluator.evaluate(&mut traversal, root).as_ref(), Ok(expected));
+    }
+}
+
+#[test]
+fn test_lazy_evaluate_region() {
+    let _ = env_logger::try_init();
+    let tokens = precedenced_arith!('1' '+' '(' '2' '*' '3' '-' '4' ')' '/' '5' '5');
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut bocage = Bocage::new(&cfg);
+    bocage.enable_spans();
+    let mut rec = Recognizer::new(&cfg, bocage);
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut lazy = LazyEvaluator::new(
+        &rec.forest,
+        |sym, _| Ok(precedenced_arith::leaf(sym)),
+        |rule, args: &[&i32]| Ok(precedenced_arith::rule(rule, args)),
+        null,
+    );
+    // Descend to the node within parentheses.
+    let region = Span { start: 3, end: 8 };
+    let mut node = root;
+    while rec.forest.span(node) != Some(region) {
+        node = lazy
+            .children(node)
+            .into_iter()
+            .find(|&child| match rec.forest.span(child) {
+                Some(span) => span.start <= region.start && region.end <= span.end,
+                None => false,
+            })
+            .unwrap();
+    }
+    assert_eq!(lazy.evaluate(node), Ok(&[2 * 3 - 4][..]));
+    assert_eq!(lazy.values(root), None);
+    assert_eq!(lazy.evaluate(root), Ok(&[1 + (2 * 3 - 4) / 55][..]));
+    lazy.clear();
+    assert_eq!(lazy.values(node), None);
+}
+
+#[test]
+fn test_lazy_evaluate_dead_node() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    // Nodes of another parse are not alive in this bocage.
+    let mut other = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(other.parse(ambiguous_arith!('2' '-' '0' '*' '3' '+' '1')));
+    let foreign = other.finished_node().unwrap();
+    let mut lazy = LazyEvaluator::new(
+        &rec.forest,
+        |sym, _| Ok(ambiguous_arith::leaf(sym)),
+        |rule, args: &[&i32]| Ok(ambiguous_arith::rule(rule, args)),
+        null,
+    );
+    assert_eq!(lazy.evaluate(foreign), Err(EvaluationError::MissingRoot));
+    assert_eq!(lazy.evaluate(root), Ok(&[2][..]));
+}
+
+#[test]
+fn test_lazy_leaf_values() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut leaf_values = vec![];
+    {
+        let mut lazy = LazyEvaluator::new(
+            &rec.forest,
+            |sym, value| {
+                leaf_values.push(value);
+                Ok(ambiguous_arith::leaf(sym))
+            },
+            |rule, args: &[&i32]| Ok(ambiguous_arith::rule(rule, args)),
+            null,
+        );
+        assert_eq!(lazy.evaluate(root), Ok(&[2][..]));
+    }
+    leaf_values.sort();
+    assert_eq!(leaf_values, vec![0, 1, 2]);
+}
+
+#[test]
+fn test_lazy_evaluate_evaluated_forest() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let leaf = |sym, _| Ok(ambiguous_arith::leaf(sym));
+    let rule = |rule, args: &[&i32]| Ok(ambiguous_arith::rule(rule, args));
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut evaluator = Evaluator::new(leaf, rule, null);
+    assert_eq!(
+        evaluator.evaluate(&mut rec.forest.traverse(), root),
+        Ok(vec![2])
+    );
+    // Traversal overwrote nodes with indices of their values.
+    let mut lazy = LazyEvaluator::new(&rec.forest, leaf, rule, null);
+    assert_eq!(lazy.evaluate(root), Err(EvaluationError::EvaluatedForest));
+}
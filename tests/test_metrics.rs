@@ -0,0 +1,116 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::forest::{Bocage, Forest, MetricsForest, NullForest};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+
+fn parse<F>(rec: &mut Recognizer<F>, tokens: &[u32])
+where
+    F: Forest<LeafValue = u32>,
+{
+    for (i, &token) in tokens.iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token), i as u32);
+        assert!(rec.end_earleme(), "failed to parse after {}@{}", token, i);
+    }
+}
+
+#[test]
+fn test_recognizer_metrics() {
+    let _ = env_logger::try_init();
+    let tokens = precedenced_arith!('1' '+' '(' '2' '*' '3' '-' '4' ')' '/' '5');
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.metrics().is_none());
+    rec.enable_metrics();
+    parse(&mut rec, tokens);
+    assert!(rec.is_finished());
+    {
+        let metrics = rec.metrics().unwrap();
+        assert_eq!(metrics.items_per_set.len(), tokens.len());
+        assert_eq!(metrics.completions_per_earleme.len(), tokens.len());
+        assert!(metrics.max_items_per_set() > 0);
+        assert_eq!(
+            metrics.total_completions(),
+            metrics.completions_per_earleme.iter().sum::<usize>()
+        );
+        assert!(metrics.peak_memory_use > 0);
+    }
+    rec.reset();
+    assert_eq!(rec.metrics().unwrap().items_per_set, vec![]);
+    let metrics = rec.disable_metrics().unwrap();
+    assert_eq!(metrics.total_completions(), 0);
+    assert!(rec.metrics().is_none());
+}
+
+#[test]
+fn test_dropped_sets() {
+    let _ = env_logger::try_init();
+    // Items of the left-recursive list start after `a`, so later sets become unreachable.
+    let mut external = Grammar::new();
+    let (start, list, a, b) = external.sym();
+    external
+        .rule(start)
+        .rhs([a, list])
+        .rule(list)
+        .rhs([list, b])
+        .rhs([b]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut tokens = vec![a.usize() as u32];
+    for _ in 0..10 {
+        tokens.push(b.usize() as u32);
+    }
+    let mut rec = Recognizer::new(&cfg, NullForest);
+    rec.enable_metrics();
+    for &token in &tokens {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token), ());
+        assert!(rec.end_earleme());
+    }
+    assert!(rec.is_finished());
+    let metrics = rec.metrics().unwrap();
+    assert_eq!(metrics.items_per_set.len(), tokens.len());
+    assert!(metrics.dropped_sets > 0);
+    assert_eq!(metrics.dropped_sets, tokens.len() - rec.earleme());
+}
+
+#[test]
+fn test_forest_metrics() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, MetricsForest::new(Bocage::new(&cfg)));
+    parse(&mut rec, tokens);
+    assert!(rec.is_finished());
+    let metrics = *rec.forest.metrics();
+    assert_eq!(metrics.leaves, tokens.len());
+    assert!(metrics.ambiguous_sums > 0);
+    assert!(metrics.summands > metrics.sums);
+
+    let tokens = precedenced_arith!('1' '+' '(' '2' '*' '3' '-' '4' ')' '/' '5');
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, MetricsForest::new(Bocage::new(&cfg)));
+    parse(&mut rec, tokens);
+    assert!(rec.is_finished());
+    let (_bocage, metrics) = rec.forest.into_inner();
+    assert_eq!(metrics.leaves, tokens.len());
+    assert_eq!(metrics.ambiguous_sums, 0);
+    assert_eq!(metrics.summands, metrics.sums);
+}
@@ -55,8 +55,14 @@ fn test_trivial_grammar_compact() {
 
 macro_rules! test_grammar_with_nulling_intermediate {
     ($Bocage:ident, $SimpleEvaluator:ident) => {
+        test_grammar_with_nulling_intermediate!($Bocage, $SimpleEvaluator, 0)
+    };
+    ($Bocage:ident, $SimpleEvaluator:ident, $unused:expr) => {
         let _ = env_logger::try_init();
         let mut external = Grammar::new();
+        for _ in 0..$unused {
+            external.next_sym();
+        }
         let (start, a, b, c, d, foo) = external.sym();
         external
             .rule(start)
@@ -113,3 +119,16 @@ fn test_grammar_with_nulling_intermediate() {
 fn test_grammar_with_nulling_intermediate_compact() {
     test_grammar_with_nulling_intermediate!(CompactBocage, SimpleCompactEvaluator);
 }
+
+// The first intermediate symbol has a lower number than the intermediate symbol on its
+// RHS, so its product refers to a node that follows it. Nulling leaves of symbols this
+// high take more cells in the compact bocage.
+#[test]
+fn test_grammar_with_wide_nulling_intermediate() {
+    test_grammar_with_nulling_intermediate!(Bocage, SimpleEvaluator, 5000);
+}
+
+#[test]
+fn test_grammar_with_wide_nulling_intermediate_compact() {
+    test_grammar_with_nulling_intermediate!(CompactBocage, SimpleCompactEvaluator, 5000);
+}
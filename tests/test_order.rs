@@ -0,0 +1,279 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::forest::node_handle::NodeHandle;
+use gearley::forest::{Bocage, CompactBocage};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::{SimpleCompactEvaluator, SimpleEvaluator};
+
+// Keeps the last and the first factor of rule 0, in that order.
+struct SwapOrder;
+
+impl gearley::forest::bocage::order::Order for SwapOrder {
+    fn product(&mut self, action: u32, factors: &[(Symbol, NodeHandle)]) -> Option<Vec<usize>> {
+        if action == 0 {
+            Some(vec![factors.len() - 1, 0])
+        } else {
+            None
+        }
+    }
+}
+
+impl gearley::forest::compact_bocage::order::Order for SwapOrder {
+    fn product(&mut self, action: u32, factors: &[(Symbol, NodeHandle)]) -> Option<Vec<usize>> {
+        if action == 0 {
+            Some(vec![factors.len() - 1, 0])
+        } else {
+            None
+        }
+    }
+}
+
+macro_rules! test_product_order {
+    ($Bocage:ident, $SimpleEvaluator:ident) => {
+        let _ = env_logger::try_init();
+        let mut external = Grammar::new();
+        let (start, mid, a, b, c) = external.sym();
+        external.rule(start).rhs([a, mid, b, c])
+                .rule(mid).rhs([a]);
+        external.set_start(start);
+        let cfg = InternalGrammar::from_grammar(&external);
+        let bocage = $Bocage::new(&cfg);
+        let mut rec = Recognizer::new(&cfg, bocage);
+        for (i, &token) in [a, a, b, c].iter().enumerate() {
+            rec.begin_earleme();
+            rec.scan(token, i as u32);
+            assert!(rec.end_earleme());
+        }
+        let root = rec.finished_node().unwrap();
+        rec.forest.mark_alive(root, SwapOrder);
+        let mut evaluator = $SimpleEvaluator::new(
+            |sym: Symbol| {
+                if sym == a { 1 } else if sym == b { 2 } else { 3 }
+            },
+            |rule: u32, args: &[&i32]| {
+                // The factor for `mid` is left out, so it is never evaluated.
+                assert_eq!(rule, 0);
+                assert_eq!(args.len(), 2);
+                *args[0] * 10 + *args[1]
+            },
+            |_, _: &mut Vec<i32>| unreachable!(),
+        );
+        let mut traversal = rec.forest.traverse();
+        let results = evaluator.traverse(&mut traversal, root);
+        assert_eq!(results, vec![31]);
+    };
+}
+
+#[test]
+fn test_product_order() {
+    test_product_order!(Bocage, SimpleEvaluator);
+}
+
+#[test]
+fn test_product_order_compact() {
+    test_product_order!(CompactBocage, SimpleCompactEvaluator);
+}
+
+// The split between `a` tokens of both `mid` symbols is ambiguous within rule 0.
+macro_rules! test_product_order_ambiguous {
+    ($Bocage:ident, $SimpleEvaluator:ident) => {
+        let _ = env_logger::try_init();
+        let mut external = Grammar::new();
+        let (start, mid, a, c) = external.sym();
+        external.rule(start).rhs([mid, mid, c])
+                .rule(mid).rhs([a])
+                .rule(mid).rhs([a, a]);
+        external.set_start(start);
+        let cfg = InternalGrammar::from_grammar(&external);
+        let bocage = $Bocage::new(&cfg);
+        let mut rec = Recognizer::new(&cfg, bocage);
+        for (i, &token) in [a, a, a, c].iter().enumerate() {
+            rec.begin_earleme();
+            rec.scan(token, i as u32);
+            assert!(rec.end_earleme());
+        }
+        let root = rec.finished_node().unwrap();
+        rec.forest.mark_alive(root, SwapOrder);
+        let mut evaluator = $SimpleEvaluator::new(
+            |sym: Symbol| if sym == a { 1 } else { 3 },
+            |rule: u32, args: &[&i32]| match rule {
+                0 | 2 => *args[0] * 10 + *args[1],
+                _ => *args[0],
+            },
+            |_, _: &mut Vec<i32>| unreachable!(),
+        );
+        let mut traversal = rec.forest.traverse();
+        let mut results = evaluator.traverse(&mut traversal, root);
+        results.sort();
+        assert_eq!(results, vec![3 * 10 + 1, 3 * 10 + 11]);
+    };
+}
+
+#[test]
+fn test_product_order_ambiguous() {
+    test_product_order_ambiguous!(Bocage, SimpleEvaluator);
+}
+
+#[test]
+fn test_product_order_ambiguous_compact() {
+    test_product_order_ambiguous!(CompactBocage, SimpleCompactEvaluator);
+}
+
+macro_rules! ambiguous_with_order {
+    ($Bocage:ident, $SimpleEvaluator:ident, $order:expr) => {
+        ambiguous_with_order!($Bocage, $SimpleEvaluator, $order, false)
+    };
+    ($Bocage:ident, $SimpleEvaluator:ident, $order:expr, $spans:expr) => {{
+        let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+        let external = ambiguous_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut evaluator = $SimpleEvaluator::new(
+            ambiguous_arith::leaf,
+            ambiguous_arith::rule,
+            |_, _: &mut Vec<i32>| unreachable!(),
+        );
+        let mut bocage = $Bocage::new(&cfg);
+        if $spans {
+            bocage.enable_spans();
+        }
+        let mut rec = Recognizer::new(&cfg, bocage);
+        for (i, &token) in tokens.iter().enumerate() {
+            rec.begin_earleme();
+            rec.scan(Symbol::from(token as u32), i as u32);
+            assert!(rec.end_earleme());
+        }
+        let root = rec.finished_node().unwrap();
+        rec.forest.mark_alive(root, $order);
+        let mut traversal = rec.forest.traverse();
+        evaluator.traverse(&mut traversal, root)
+    }};
+}
+
+#[test]
+fn test_alternative_order() {
+    let _ = env_logger::try_init();
+    use gearley::forest::bocage::order::AlternativeOrder;
+    use gearley::forest::compact_bocage::order::AlternativeOrder as CompactAlternativeOrder;
+    let first = ambiguous_with_order!(Bocage, SimpleEvaluator, AlternativeOrder::first());
+    let last = ambiguous_with_order!(Bocage, SimpleEvaluator, AlternativeOrder::last());
+    assert_eq!(first.len(), 1);
+    assert_eq!(last.len(), 1);
+    assert!(first != last);
+    let compact_first = ambiguous_with_order!(
+        CompactBocage,
+        SimpleCompactEvaluator,
+        CompactAlternativeOrder::first()
+    );
+    let compact_last = ambiguous_with_order!(
+        CompactBocage,
+        SimpleCompactEvaluator,
+        CompactAlternativeOrder::last()
+    );
+    assert_eq!(compact_first, first);
+    assert_eq!(compact_last, last);
+}
+
+#[test]
+fn test_match_order() {
+    let _ = env_logger::try_init();
+    use gearley::forest::bocage::order::MatchOrder;
+    use gearley::forest::compact_bocage::order::MatchOrder as CompactMatchOrder;
+    // Longest match associates to the left, shortest match to the right.
+    let longest = ambiguous_with_order!(Bocage, SimpleEvaluator, MatchOrder::longest(), true);
+    assert_eq!(longest, vec![((2 - 0) * 3) + 1]);
+    let shortest = ambiguous_with_order!(Bocage, SimpleEvaluator, MatchOrder::shortest(), true);
+    assert_eq!(shortest, vec![2 - (0 * (3 + 1))]);
+    let longest = ambiguous_with_order!(
+        CompactBocage,
+        SimpleCompactEvaluator,
+        CompactMatchOrder::longest(),
+        true
+    );
+    assert_eq!(longest, vec![((2 - 0) * 3) + 1]);
+    let shortest = ambiguous_with_order!(
+        CompactBocage,
+        SimpleCompactEvaluator,
+        CompactMatchOrder::shortest(),
+        true
+    );
+    assert_eq!(shortest, vec![2 - (0 * (3 + 1))]);
+}
+
+#[test]
+fn test_match_order_without_spans() {
+    let _ = env_logger::try_init();
+    use gearley::forest::bocage::order::MatchOrder;
+    use gearley::forest::compact_bocage::order::MatchOrder as CompactMatchOrder;
+    // Match lengths are unknown, so all five trees are kept.
+    let longest = ambiguous_with_order!(Bocage, SimpleEvaluator, MatchOrder::longest());
+    assert_eq!(longest.len(), 5);
+    let shortest = ambiguous_with_order!(
+        CompactBocage,
+        SimpleCompactEvaluator,
+        CompactMatchOrder::shortest()
+    );
+    assert_eq!(shortest.len(), 5);
+}
+
+macro_rules! test_rule_rank_order {
+    ($Bocage:ident, $SimpleEvaluator:ident, $RuleRankOrder:ident) => {
+        let _ = env_logger::try_init();
+        let mut external = Grammar::new();
+        let (start, x, a, b) = external.sym();
+        external.rule(start).rhs([a, b])
+                            .rhs([x])
+                .rule(x).rhs([a, b]);
+        external.set_start(start);
+        let cfg = InternalGrammar::from_grammar(&external);
+        for &(ref ranks, expected) in &[(vec![0, 1], 2), (vec![1, 0], 1)] {
+            let bocage = $Bocage::new(&cfg);
+            let mut rec = Recognizer::new(&cfg, bocage);
+            for (i, &token) in [a, b].iter().enumerate() {
+                rec.begin_earleme();
+                rec.scan(token, i as u32);
+                assert!(rec.end_earleme());
+            }
+            let root = rec.finished_node().unwrap();
+            rec.forest.mark_alive(root, $RuleRankOrder::new(&cfg, ranks.clone()));
+            let mut evaluator = $SimpleEvaluator::new(
+                |_: Symbol| 0,
+                |rule: u32, args: &[&i32]| match rule {
+                    0 => 1,
+                    1 => *args[0],
+                    2 => 2,
+                    _ => unreachable!(),
+                },
+                |_, _: &mut Vec<i32>| unreachable!(),
+            );
+            let mut traversal = rec.forest.traverse();
+            let results = evaluator.traverse(&mut traversal, root);
+            assert_eq!(results, vec![expected]);
+        }
+    };
+}
+
+#[test]
+fn test_rule_rank_order() {
+    use gearley::forest::bocage::order::RuleRankOrder;
+    test_rule_rank_order!(Bocage, SimpleEvaluator, RuleRankOrder);
+}
+
+#[test]
+fn test_rule_rank_order_compact() {
+    use gearley::forest::compact_bocage::order::RuleRankOrder;
+    test_rule_rank_order!(CompactBocage, SimpleCompactEvaluator, RuleRankOrder);
+}
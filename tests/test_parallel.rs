@@ -0,0 +1,133 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::bocage::traverse::LeafHandle;
+use gearley::forest::Span;
+use gearley::grammar::InternalGrammar;
+use gearley::item::Position;
+use gearley::parallel::{parse_chunks, ChunkError};
+
+use helpers::SimpleEvaluator;
+
+fn grammar() -> (Grammar, Symbol, Symbol) {
+    let mut external = Grammar::new();
+    let (start, item, a, b) = external.sym();
+    external.rule(start).rhs([start, item])
+                        .rhs([item])
+            .rule(item).rhs([a, b]);
+    external.set_start(start);
+    (external, a, b)
+}
+
+#[test]
+fn test_parse_chunks() {
+    let _ = env_logger::try_init();
+    let (external, a, b) = grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let tokens: Vec<_> = (0..8).map(|i| (if i % 2 == 0 { a } else { b }, i)).collect();
+    let (mut bocage, root) = parse_chunks(&cfg, &tokens[..], &[2, 4, 6], 0).unwrap();
+    bocage.mark_alive(root, NullOrder::new());
+    let mut evaluator = SimpleEvaluator::new(
+        |_: Symbol| 0,
+        |rule: u32, args: &[&i32]| {
+            match rule {
+                0 => *args[0] + *args[1],
+                1 => *args[0],
+                2 => 1,
+                _ => unreachable!()
+            }
+        },
+        |_, _: &mut Vec<i32>| unreachable!()
+    );
+    let mut traversal = bocage.traverse();
+    let results = evaluator.traverse(&mut traversal, root);
+    assert_eq!(results, vec![4]);
+}
+
+#[test]
+fn test_parse_chunks_spans() {
+    let _ = env_logger::try_init();
+    let (external, a, b) = grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let tokens: Vec<_> = (0..8).map(|i| (if i % 2 == 0 { a } else { b }, i)).collect();
+    let (mut bocage, root) = parse_chunks(&cfg, &tokens[..], &[2, 4, 6], 0).unwrap();
+    assert_eq!(bocage.span(root), Some(Span { start: 0, end: 8 }));
+    bocage.mark_alive(root, NullOrder::new());
+    // Spans of later chunks start where their chunk starts.
+    let mut leaf_spans = vec![];
+    let mut traversal = bocage.traverse();
+    while let Some(node) = traversal.next_node() {
+        if let LeafHandle(_) = node.item {
+            leaf_spans.push(node.span.unwrap());
+        }
+    }
+    leaf_spans.sort_by_key(|span| span.start);
+    let expected: Vec<_> = (0..8)
+        .map(|i: Position| Span { start: i, end: i + 1 })
+        .collect();
+    assert_eq!(leaf_spans, expected);
+}
+
+#[test]
+fn test_parse_chunks_unfinished() {
+    let _ = env_logger::try_init();
+    let (external, a, b) = grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let tokens = [(a, 0), (b, 1), (a, 2), (b, 3)];
+    let result = parse_chunks(&cfg, &tokens[..], &[3], 0);
+    assert_eq!(result.err(), Some(ChunkError::Unfinished { chunk: 0 }));
+    let result = parse_chunks(&cfg, &tokens[..], &[3, 1], 0);
+    assert_eq!(result.err(), Some(ChunkError::InvalidSplitPoints));
+    let result = parse_chunks(&cfg, &tokens[..], &[2, 2], 0);
+    assert_eq!(result.err(), Some(ChunkError::InvalidSplitPoints));
+    let result = parse_chunks(&cfg, &tokens[..], &[2], 1);
+    assert_eq!(result.err(), Some(ChunkError::InvalidJoinRule));
+    // The LHS of `item ::= a b` differs from its left symbol.
+    let result = parse_chunks(&cfg, &tokens[..], &[2], 2);
+    assert_eq!(result.err(), Some(ChunkError::InvalidJoinRule));
+}
+
+#[test]
+fn test_parse_chunks_join_action() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (list, item, a, b) = external.sym();
+    external.rule(list).rhs([list, item])
+                       .rhs([item])
+            .rule(item).rhs([a, b])
+                       .rhs([b, a]);
+    external.set_start(list);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let tokens = [(a, 0), (b, 1), (a, 2), (b, 3), (b, 4), (a, 5), (a, 6), (b, 7)];
+    // Every chunk after the first is a single item.
+    let (mut bocage, root) = parse_chunks(&cfg, &tokens[..], &[4, 6], 0).unwrap();
+    bocage.mark_alive(root, NullOrder::new());
+    let mut evaluator = SimpleEvaluator::new(
+        |_: Symbol| 0,
+        |rule: u32, args: &[&i32]| {
+            match rule {
+                0 => *args[0] * 10 + *args[1],
+                1 => *args[0] + 100,
+                2 => 1,
+                3 => 2,
+                _ => unreachable!()
+            }
+        },
+        |_, _: &mut Vec<i32>| unreachable!()
+    );
+    let mut traversal = bocage.traverse();
+    let results = evaluator.traverse(&mut traversal, root);
+    assert_eq!(results, vec![((101 * 10 + 1) * 10 + 2) * 10 + 1]);
+
+    let result = parse_chunks(&cfg, &tokens[..], &[2], 0);
+    assert_eq!(result.err(), Some(ChunkError::Rejected { chunk: 1, token: 4 }));
+}
@@ -0,0 +1,248 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use std::mem;
+
+use cfg::Symbol;
+
+use gearley::evaluator::{EvaluationError, EvaluationMode, Evaluator};
+use gearley::forest::node_handle::NodeHandle;
+use gearley::forest::{Bocage, NullForest};
+use gearley::grammar::InternalGrammar;
+use gearley::item::Item;
+use gearley::policy::*;
+use gearley::recognizer::{OverflowError, Recognizer};
+
+use grammars::*;
+use helpers::Parse;
+
+// Always searches Earley sets with binary search.
+struct BinarySearchPolicy;
+
+impl PerformancePolicy for BinarySearchPolicy {
+    type Dot = u32;
+    type Origin = u32;
+    type Symbol = u32;
+    type Index = u32;
+
+    const MEDIAL_BINARY_SEARCH_THRESHOLD: usize = 0;
+    const PREDICTED_CAPACITY: usize = 0;
+    const MEDIAL_CAPACITY: usize = 0;
+    const COMPLETE_CAPACITY: usize = 0;
+}
+
+// Stores medial items in two bytes, plus their nodes.
+struct DenseItemsPolicy;
+
+impl PerformancePolicy for DenseItemsPolicy {
+    type Dot = u8;
+    type Origin = u8;
+    type Symbol = u8;
+    type Index = u32;
+}
+
+// Links completed items to at most 256 medial items.
+struct FewItemsPolicy;
+
+impl PerformancePolicy for FewItemsPolicy {
+    type Dot = u32;
+    type Origin = u32;
+    type Symbol = u32;
+    type Index = u8;
+}
+
+// Stores symbols of rules in one byte.
+struct FewSymbolsPolicy;
+
+impl PerformancePolicy for FewSymbolsPolicy {
+    type Dot = u32;
+    type Origin = u32;
+    type Symbol = u8;
+    type Index = u32;
+}
+
+fn null(symbol: Symbol, _: &mut Vec<i32>) -> Result<(), EvaluationError> {
+    Err(EvaluationError::InvalidNulling { symbol })
+}
+
+macro_rules! test_policy {
+    ($policy:expr) => {
+        let _ = env_logger::try_init();
+        let external = ambiguous_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut rec = Recognizer::with_policy(&cfg, Bocage::new(&cfg), $policy);
+        assert!(rec.parse(ambiguous_arith!('2' '-' '0' '*' '3' '+' '1')));
+        let root = rec.finished_node().unwrap();
+        let mut evaluator = Evaluator::with_mode(
+            |sym, _| Ok(ambiguous_arith::leaf(sym)),
+            |rule, args: &[&i32]| Ok(ambiguous_arith::rule(rule, args)),
+            null,
+            EvaluationMode::AllTrees,
+        );
+        let results = evaluator.evaluate(&mut rec.forest.traverse(), root);
+        assert_eq!(results, Ok(vec![2, 1, 3, 7, 8]));
+
+        let external = precedenced_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut rec = Recognizer::with_policy(&cfg, Bocage::new(&cfg), $policy);
+        assert!(rec.parse(precedenced_arith!('1' '+' '(' '2' '*' '3' '-' '4' ')' '/' '5')));
+        let root = rec.finished_node().unwrap();
+        let mut evaluator = Evaluator::with_mode(
+            |sym, _| Ok(precedenced_arith::leaf(sym)),
+            |rule, args: &[&i32]| Ok(precedenced_arith::rule(rule, args)),
+            null,
+            EvaluationMode::AllTrees,
+        );
+        let results = evaluator.evaluate(&mut rec.forest.traverse(), root);
+        assert_eq!(results, Ok(vec![1]));
+
+        let mut rec = Recognizer::with_policy(&cfg, NullForest, $policy);
+        assert!(!rec.parse(precedenced_arith!('1' '+' '(' '2')));
+    };
+}
+
+#[test]
+fn test_null_policy() {
+    test_policy!(NullPerformancePolicy);
+}
+
+#[test]
+fn test_small_grammar_policy() {
+    // The dot and the origin take as much space as the node.
+    assert_eq!(
+        mem::size_of::<Item<NodeHandle, u16, u16>>(),
+        2 * mem::size_of::<NodeHandle>()
+    );
+    test_policy!(SmallGrammarPolicy);
+}
+
+#[test]
+fn test_large_grammar_policy() {
+    test_policy!(LargeGrammarPolicy);
+}
+
+#[test]
+fn test_binary_search_policy() {
+    test_policy!(BinarySearchPolicy);
+}
+
+#[test]
+fn test_dense_items_policy() {
+    assert!(mem::size_of::<Item<NodeHandle, u8, u8>>() < mem::size_of::<Item<NodeHandle>>());
+    test_policy!(DenseItemsPolicy);
+}
+
+#[test]
+#[should_panic(expected = "too many Earley sets")]
+fn test_dense_items_policy_overflow() {
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut tokens = vec![];
+    for _ in 0..300 {
+        tokens.extend_from_slice(precedenced_arith!('('));
+    }
+    let mut rec = Recognizer::with_policy(&cfg, NullForest, DenseItemsPolicy);
+    rec.parse(&tokens[..]);
+}
+
+#[test]
+fn test_dense_items_policy_overflow_error() {
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let token = Symbol::from(precedenced_arith!('(')[0] as u32);
+    let mut rec = Recognizer::with_policy(&cfg, NullForest, DenseItemsPolicy);
+    let mut result = Ok(true);
+    for _ in 0..300 {
+        rec.begin_earleme();
+        rec.scan(token, ());
+        result = rec.try_end_earleme();
+        if result.is_err() {
+            break;
+        }
+    }
+    assert_eq!(result, Err(OverflowError::EarleySets));
+    assert_eq!(rec.earleme(), 256);
+    assert_eq!(rec.overflow(), Some(OverflowError::EarleySets));
+    rec.reset();
+    assert_eq!(rec.overflow(), None);
+}
+
+#[test]
+fn test_few_items_policy_overflow_error() {
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut tokens = vec![];
+    for _ in 0..300 {
+        tokens.extend_from_slice(precedenced_arith!('('));
+    }
+    tokens.extend_from_slice(precedenced_arith!('1'));
+    for _ in 0..300 {
+        tokens.extend_from_slice(precedenced_arith!(')'));
+    }
+    let mut rec = Recognizer::with_policy(&cfg, NullForest, FewItemsPolicy);
+    let mut result = Ok(true);
+    for &token in &tokens {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token as u32), ());
+        result = rec.try_end_earleme();
+        if result.is_err() {
+            break;
+        }
+    }
+    assert_eq!(result, Err(OverflowError::MedialItems));
+    assert_eq!(rec.overflow(), Some(OverflowError::MedialItems));
+}
+
+#[test]
+#[should_panic(expected = "too many symbols")]
+fn test_few_symbols_policy_overflow() {
+    let mut external = cfg::earley::Grammar::new();
+    let start = external.sym();
+    let terminals: Vec<Symbol> = (0..300).map(|_| external.sym()).collect();
+    for &terminal in &terminals {
+        external.rule(start).rhs([terminal]);
+    }
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    Recognizer::with_policy(&cfg, NullForest, FewSymbolsPolicy);
+}
+
+#[test]
+fn test_dense_items_policy_filter_overflow_error() {
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let token = Symbol::from(precedenced_arith!('(')[0] as u32);
+    let mut rec = Recognizer::with_policy(&cfg, NullForest, DenseItemsPolicy);
+    let mut result = Ok(true);
+    for _ in 0..300 {
+        rec.begin_earleme();
+        rec.scan(token, ());
+        result = rec.try_end_earleme_with_filter(|_, _, _| true);
+        if result.is_err() {
+            break;
+        }
+    }
+    assert_eq!(result, Err(OverflowError::EarleySets));
+    assert_eq!(rec.earleme(), 256);
+}
+
+#[test]
+#[should_panic(expected = "too many Earley sets")]
+fn test_dense_items_policy_filter_overflow() {
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let token = Symbol::from(precedenced_arith!('(')[0] as u32);
+    let mut rec = Recognizer::with_policy(&cfg, NullForest, DenseItemsPolicy);
+    for _ in 0..300 {
+        rec.begin_earleme();
+        rec.scan(token, ());
+        rec.end_earleme_with_filter(|_, _, _| true);
+    }
+}
@@ -0,0 +1,107 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use gearley::forest::bocage::trees::ParseTree;
+use gearley::forest::{Bocage, Span};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+fn evaluate(tree: &ParseTree) -> i32 {
+    match *tree {
+        ParseTree::Node {
+            action,
+            ref children,
+            ..
+        } => {
+            let values: Vec<i32> = children.iter().map(evaluate).collect();
+            let args: Vec<&i32> = values.iter().collect();
+            ambiguous_arith::rule(action, &args[..])
+        }
+        ParseTree::Leaf { symbol, .. } => ambiguous_arith::leaf(symbol),
+        ParseTree::Nulling { .. } => unreachable!(),
+    }
+}
+
+// Values of nodes are their rules along with their arithmetic values.
+fn value(rule: u32, args: &[&(Option<u32>, i32)]) -> (Option<u32>, i32) {
+    let values: Vec<&i32> = args.iter().map(|&&(_, ref value)| value).collect();
+    (Some(rule), ambiguous_arith::rule(rule, &values[..]))
+}
+
+#[test]
+fn test_prune() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut bocage = Bocage::new(&cfg);
+    bocage.enable_spans();
+    let mut rec = Recognizer::new(&cfg, bocage);
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut spans = vec![];
+    let mut root_values = vec![];
+    // Subtraction must not have a binary expression on its right.
+    let derivable = rec.forest.prune(
+        root,
+        |symbol, _| (None, ambiguous_arith::leaf(symbol)),
+        |_| unreachable!(),
+        |rule, span, args| {
+            let span = span.unwrap();
+            spans.push(span);
+            match (rule, args) {
+                (0, &[_, &(Some(3), _), &(Some(0), _)]) => None,
+                _ => {
+                    let value = value(rule, args);
+                    if span == (Span { start: 0, end: 7 }) {
+                        root_values.push(value.1);
+                    }
+                    Some(value)
+                }
+            }
+        },
+    );
+    assert!(derivable);
+    assert!(spans.contains(&Span { start: 0, end: 7 }));
+    // Kept alternatives of the root are evaluated from values of their factors.
+    root_values.sort();
+    assert_eq!(root_values, vec![7, 8]);
+    let mut results: Vec<i32> = rec.forest.trees(root).map(|tree| evaluate(&tree)).collect();
+    results.sort();
+    assert_eq!(results, vec![7, 8]);
+}
+
+#[test]
+fn test_prune_all() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    // Multiplication is rejected, so nothing is left.
+    let derivable = rec.forest.prune(
+        root,
+        |symbol, _| (None, ambiguous_arith::leaf(symbol)),
+        |_| unreachable!(),
+        |rule, span, args| {
+            assert!(span.is_none());
+            match args {
+                &[_, &(Some(4), _), _] => None,
+                _ => Some(value(rule, args)),
+            }
+        },
+    );
+    assert!(!derivable);
+}
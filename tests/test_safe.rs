@@ -0,0 +1,38 @@
+#![cfg(feature = "safe")]
+
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::Symbol;
+
+use gearley::forest::{Bocage, CompactBocage, Forest};
+use gearley::grammar::InternalGrammar;
+
+use grammars::*;
+
+#[test]
+#[should_panic(expected = "invariant violated: a sum has summands")]
+fn test_sum_without_summands() {
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut bocage = Bocage::new(&cfg);
+    bocage.begin_sum();
+    bocage.sum(Symbol::from(0u32), 0);
+}
+
+#[test]
+#[should_panic(expected = "invariant violated: a sum has summands")]
+fn test_sum_without_summands_compact() {
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut bocage = CompactBocage::new(&cfg);
+    bocage.begin_sum();
+    bocage.sum(Symbol::from(0u32), 0);
+}
@@ -0,0 +1,189 @@
+#![cfg(feature = "sample")]
+
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+extern crate rand;
+extern crate rand_pcg;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use std::collections::BTreeMap;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+use gearley::forest::bocage::order::{NullOrder, Order};
+use gearley::forest::bocage::trees::ParseTree;
+use gearley::forest::node_handle::NodeHandle;
+use gearley::forest::Bocage;
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+fn evaluate(tree: &ParseTree) -> i32 {
+    match *tree {
+        ParseTree::Node {
+            action,
+            ref children,
+            ..
+        } => {
+            let values: Vec<i32> = children.iter().map(evaluate).collect();
+            let args: Vec<&i32> = values.iter().collect();
+            ambiguous_arith::rule(action, &args[..])
+        }
+        ParseTree::Leaf { symbol, .. } => ambiguous_arith::leaf(symbol),
+        ParseTree::Nulling { .. } => unreachable!(),
+    }
+}
+
+// Keeps only the first factor of the start rule.
+struct FirstFactorOrder;
+
+impl Order for FirstFactorOrder {
+    fn product(&mut self, action: u32, _factors: &[(Symbol, NodeHandle)]) -> Option<Vec<usize>> {
+        if action == 0 {
+            Some(vec![0])
+        } else {
+            None
+        }
+    }
+}
+
+fn histogram(samples: &[i32]) -> BTreeMap<i32, u32> {
+    let mut histogram = BTreeMap::new();
+    for &value in samples {
+        *histogram.entry(value).or_insert(0) += 1;
+    }
+    histogram
+}
+
+#[test]
+fn test_sample_uniform() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let sampler = rec.forest.sampler(root);
+    assert_eq!(sampler.total_weight(), 5.0);
+    let mut rng = Pcg32::seed_from_u64(42);
+    let samples: Vec<i32> = (0..1000).map(|_| evaluate(&sampler.sample(&mut rng))).collect();
+    let histogram = histogram(&samples[..]);
+    assert_eq!(histogram.keys().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 7, 8]);
+    for &count in histogram.values() {
+        assert!(count > 150 && count < 250, "{:?}", histogram);
+    }
+    // Samples are reproducible.
+    let mut rng = Pcg32::seed_from_u64(42);
+    let again: Vec<i32> = (0..1000).map(|_| evaluate(&sampler.sample(&mut rng))).collect();
+    assert_eq!(samples, again);
+}
+
+#[test]
+fn test_sample_weighted() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (start, a, b, t) = external.sym();
+    external.rule(start).rhs([a])
+                        .rhs([b])
+            .rule(a).rhs([t])
+            .rule(b).rhs([t]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    rec.begin_earleme();
+    rec.scan(t, 0);
+    assert!(rec.end_earleme());
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, NullOrder::new());
+    // The derivation through `b` is three times heavier.
+    let sampler = rec.forest.weighted_sampler(root, &[1.0, 3.0]);
+    assert_eq!(sampler.total_weight(), 4.0);
+    let mut rng = Pcg32::seed_from_u64(7);
+    let mut through_b = 0;
+    for _ in 0..1000 {
+        match sampler.sample(&mut rng) {
+            ParseTree::Node { action: 1, .. } => through_b += 1,
+            ParseTree::Node { action: 0, .. } => {}
+            other => panic!("unexpected tree {:?}", other),
+        }
+    }
+    assert!(through_b > 700 && through_b < 800, "{}", through_b);
+}
+
+#[test]
+fn test_sample_selected_factors() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (start, x, y, a, b, t, u) = external.sym();
+    external.rule(start).rhs([x, y])
+            .rule(x).rhs([t])
+            .rule(y).rhs([a])
+                    .rhs([b])
+            .rule(a).rhs([u])
+            .rule(b).rhs([u]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    for (i, &token) in [t, u].iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(token, i as u32);
+        assert!(rec.end_earleme());
+    }
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, FirstFactorOrder);
+    // The ambiguous factor for `y` is left out, so a single derivation remains.
+    let sampler = rec.forest.sampler(root);
+    assert_eq!(sampler.total_weight(), 1.0);
+    let mut rng = Pcg32::seed_from_u64(1);
+    match sampler.sample(&mut rng) {
+        ParseTree::Node { ref children, .. } => assert_eq!(children.len(), 1),
+        other => panic!("unexpected tree {:?}", other),
+    }
+}
+
+#[test]
+fn test_sample_uniform_nulling() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (start, x, y, z, a) = external.sym();
+    external.rule(start).rhs([x, a])
+                        .rhs([z, a])
+            .rule(x).rhs([])
+                    .rhs([y])
+            .rule(y).rhs([])
+            .rule(z).rhs([]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    rec.begin_earleme();
+    rec.scan(a, 0);
+    assert!(rec.end_earleme());
+    let root = rec.finished_node().unwrap();
+    assert_eq!(rec.forest.count_trees::<u64>(root), Some(3));
+    rec.forest.mark_alive(root, NullOrder::new());
+    // `x` has two nulling derivations, so the first rule is drawn twice as often.
+    let sampler = rec.forest.sampler(root);
+    assert_eq!(sampler.total_weight(), 3.0);
+    let mut rng = Pcg32::seed_from_u64(3);
+    let mut through_x = 0;
+    for _ in 0..1000 {
+        match sampler.sample(&mut rng) {
+            ParseTree::Node { action: 0, .. } => through_x += 1,
+            ParseTree::Node { action: 1, .. } => {}
+            other => panic!("unexpected tree {:?}", other),
+        }
+    }
+    assert!(through_x > 620 && through_x < 720, "{}", through_x);
+}
@@ -0,0 +1,87 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::Symbol;
+
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::compact_bocage::order::NullOrder as CompactNullOrder;
+use gearley::forest::{Bocage, CompactBocage, Span};
+use gearley::grammar::InternalGrammar;
+use gearley::item::Position;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+
+const TOKENS: &'static [u32] = precedenced_arith!(
+    '1' '+' '(' '2' '*' '3' '-' '4' ')' '/'
+    '(' '5' '5' ')' '-' '(' '5' '4' ')' '*'
+    '5' '5' '+' '6' '2' '-' '1' '3' '-' '('
+    '(' '3' '6' ')' ')'
+);
+
+macro_rules! test_spans {
+    ($Bocage:ident, $NullOrder:ident, $traverse_module:ident) => {
+        use gearley::forest::$traverse_module::traverse::{LeafHandle, SumHandle};
+
+        let _ = env_logger::try_init();
+        let external = precedenced_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut bocage = $Bocage::new(&cfg);
+        bocage.enable_spans();
+        let mut rec = Recognizer::new(&cfg, bocage);
+        for (i, &token) in TOKENS.iter().enumerate() {
+            rec.begin_earleme();
+            rec.scan(Symbol::from(token), i as u32);
+            assert!(rec.end_earleme());
+            // Spans are kept through collections.
+            rec.collect_garbage();
+        }
+        assert_eq!(rec.location() as usize, TOKENS.len());
+        // Some Earley sets were removed, so set IDs differ from locations.
+        assert!(rec.earleme() < TOKENS.len());
+        let root = rec.finished_node().unwrap();
+        let root_span = Span {
+            start: 0,
+            end: TOKENS.len() as Position,
+        };
+        assert_eq!(rec.forest.span(root), Some(root_span));
+        rec.forest.mark_alive(root, $NullOrder::new());
+        let mut leaf_spans = vec![];
+        let mut sum_spans = vec![];
+        let mut traversal = rec.forest.traverse();
+        while let Some(item) = traversal.next_node() {
+            match item.item {
+                LeafHandle { .. } => leaf_spans.push(item.span.unwrap()),
+                SumHandle(_) => sum_spans.push(item.span.unwrap()),
+                _ => {}
+            }
+        }
+        let expected_leaf_spans: Vec<_> = (0..TOKENS.len() as Position)
+            .map(|i| Span {
+                start: i,
+                end: i + 1,
+            })
+            .collect();
+        assert_eq!(leaf_spans, expected_leaf_spans);
+        // The parenthesized `(2*3-4)`.
+        assert!(sum_spans.contains(&Span { start: 2, end: 9 }));
+        assert!(sum_spans.contains(&root_span));
+    };
+}
+
+#[test]
+fn test_spans() {
+    test_spans!(Bocage, NullOrder, bocage);
+}
+
+#[test]
+fn test_spans_compact() {
+    test_spans!(CompactBocage, CompactNullOrder, compact_bocage);
+}
@@ -0,0 +1,86 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::Symbol;
+
+use gearley::forest::bocage::order::NullOrder;
+use gearley::forest::compact_bocage::order::NullOrder as CompactNullOrder;
+use gearley::forest::{Bocage, CompactBocage, NullForest, Tee};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::{SimpleCompactEvaluator, SimpleEvaluator};
+
+const SUM_TOKENS: &'static [u32] = precedenced_arith!(
+    '1' '+' '(' '2' '*' '3' '-' '4' ')' '/'
+    '(' '5' '5' ')' '-' '(' '5' '4' ')' '*'
+    '5' '5' '+' '6' '2' '-' '1' '3' '-' '('
+    '(' '3' '6' ')' ')'
+);
+
+const SUM_RESULT: i32 = 1 + (2 * 3 - 4) / 55 - 54 * 55 + 62 - 13 - 36;
+
+#[test]
+fn test_tee_null_forest_and_bocage() {
+    let _ = env_logger::try_init();
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Tee::new(NullForest, Bocage::new(&cfg)));
+    for (i, &token) in SUM_TOKENS.iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token), ((), i as u32));
+        assert!(rec.end_earleme(), "failed to parse after {}@{}", token, i);
+    }
+    let ((), root) = rec.finished_node().unwrap();
+    let (_, mut bocage) = rec.forest.into_inner();
+    bocage.mark_alive(root, NullOrder::new());
+    let mut evaluator = SimpleEvaluator::new(
+        precedenced_arith::leaf,
+        precedenced_arith::rule,
+        |_, _: &mut Vec<i32>| unreachable!(),
+    );
+    let results = evaluator.traverse(&mut bocage.traverse(), root);
+    assert_eq!(results, &[SUM_RESULT]);
+}
+
+#[test]
+fn test_tee_two_forests_with_gc() {
+    let _ = env_logger::try_init();
+    let external = precedenced_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let forest = Tee::new(Bocage::new(&cfg), CompactBocage::new(&cfg));
+    let mut rec = Recognizer::new(&cfg, forest);
+    for (i, &token) in SUM_TOKENS.iter().enumerate() {
+        rec.begin_earleme();
+        rec.scan(Symbol::from(token), (i as u32, i as u32));
+        assert!(rec.end_earleme(), "failed to parse after {}@{}", token, i);
+        rec.collect_garbage();
+    }
+    let (root, compact_root) = rec.finished_node().unwrap();
+
+    rec.forest.first.mark_alive(root, NullOrder::new());
+    let mut evaluator = SimpleEvaluator::new(
+        precedenced_arith::leaf,
+        precedenced_arith::rule,
+        |_, _: &mut Vec<i32>| unreachable!(),
+    );
+    let results = evaluator.traverse(&mut rec.forest.first.traverse(), root);
+    assert_eq!(results, &[SUM_RESULT]);
+
+    rec.forest.second.mark_alive(compact_root, CompactNullOrder::new());
+    let mut evaluator = SimpleCompactEvaluator::new(
+        precedenced_arith::leaf,
+        precedenced_arith::rule,
+        |_, _: &mut Vec<i32>| unreachable!(),
+    );
+    let results = evaluator.traverse(&mut rec.forest.second.traverse(), compact_root);
+    assert_eq!(results, &[SUM_RESULT]);
+}
@@ -0,0 +1,154 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+extern crate num;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+use cfg::Symbol;
+use num::BigUint;
+
+use gearley::forest::{Bocage, CompactBocage};
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+
+// Catalan numbers count binary trees.
+fn catalan(n: u32) -> BigUint {
+    let mut result = BigUint::from(1u32);
+    for k in 0..n {
+        result = result * BigUint::from(2 * (2 * k + 1)) / BigUint::from(k + 2);
+    }
+    result
+}
+
+fn sum_tokens(operators: usize) -> Vec<u32> {
+    let mut tokens = ambiguous_arith!('1').to_vec();
+    for _ in 0..operators {
+        tokens.extend_from_slice(ambiguous_arith!('+' '1'));
+    }
+    tokens
+}
+
+macro_rules! test_count_trees {
+    ($Bocage:ident) => {
+        let _ = env_logger::try_init();
+        let external = ambiguous_arith::grammar();
+        let cfg = InternalGrammar::from_grammar(&external);
+        for &operators in &[3, 10, 40] {
+            let tokens = sum_tokens(operators);
+            let mut rec = Recognizer::new(&cfg, $Bocage::new(&cfg));
+            for (i, &token) in tokens.iter().enumerate() {
+                rec.begin_earleme();
+                rec.scan(Symbol::from(token), i as u32);
+                assert!(rec.end_earleme());
+            }
+            let root = rec.finished_node().unwrap();
+            let count: BigUint = rec.forest.count_trees(root).unwrap();
+            assert_eq!(count, catalan(operators as u32));
+            let saturated: u64 = rec.forest.count_trees(root).unwrap();
+            if operators == 40 {
+                assert_eq!(saturated, u64::max_value());
+            } else {
+                assert_eq!(BigUint::from(saturated), count);
+            }
+        }
+    };
+}
+
+#[test]
+fn test_count_trees() {
+    test_count_trees!(Bocage);
+}
+
+#[test]
+fn test_count_trees_compact() {
+    test_count_trees!(CompactBocage);
+}
+
+#[test]
+fn test_count_trees_nulling() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    let (start, x, a) = external.sym();
+    external.rule(start).rhs([x, x])
+            .rule(x).rhs([a])
+                    .rhs([]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    rec.begin_earleme();
+    rec.scan(a, 0);
+    assert!(rec.end_earleme());
+    let root = rec.finished_node().unwrap();
+    // `a` is derived from either `x`.
+    assert_eq!(rec.forest.count_trees::<u64>(root), Some(2));
+}
+
+// `x` has two nulling derivations.
+macro_rules! test_count_trees_ambiguous_nulling {
+    ($Bocage:ident) => {
+        let _ = env_logger::try_init();
+        let mut external = Grammar::new();
+        let (start, x, y, a) = external.sym();
+        external.rule(start).rhs([x, a])
+                .rule(x).rhs([])
+                        .rhs([y])
+                .rule(y).rhs([]);
+        external.set_start(start);
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut rec = Recognizer::new(&cfg, $Bocage::new(&cfg));
+        rec.begin_earleme();
+        rec.scan(a, 0);
+        assert!(rec.end_earleme());
+        let root = rec.finished_node().unwrap();
+        assert_eq!(rec.forest.count_trees::<u64>(root), Some(2));
+    };
+}
+
+#[test]
+fn test_count_trees_ambiguous_nulling() {
+    test_count_trees_ambiguous_nulling!(Bocage);
+}
+
+#[test]
+fn test_count_trees_ambiguous_nulling_compact() {
+    test_count_trees_ambiguous_nulling!(CompactBocage);
+}
+
+// `x` derives itself, so it has infinitely many nulling derivations.
+macro_rules! test_count_trees_cyclic_nulling {
+    ($Bocage:ident) => {
+        let _ = env_logger::try_init();
+        let mut external = Grammar::new();
+        let (start, x, a) = external.sym();
+        external.rule(start).rhs([x, a])
+                .rule(x).rhs([])
+                        .rhs([x]);
+        external.set_start(start);
+        let cfg = InternalGrammar::from_grammar(&external);
+        let mut rec = Recognizer::new(&cfg, $Bocage::new(&cfg));
+        rec.begin_earleme();
+        rec.scan(a, 0);
+        assert!(rec.end_earleme());
+        let root = rec.finished_node().unwrap();
+        assert_eq!(rec.forest.count_trees::<u64>(root), Some(u64::max_value()));
+        assert_eq!(rec.forest.count_trees::<BigUint>(root), None);
+    };
+}
+
+#[test]
+fn test_count_trees_cyclic_nulling() {
+    test_count_trees_cyclic_nulling!(Bocage);
+}
+
+#[test]
+fn test_count_trees_cyclic_nulling_compact() {
+    test_count_trees_cyclic_nulling!(CompactBocage);
+}
@@ -0,0 +1,114 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate cfg;
+extern crate gearley;
+
+#[macro_use]
+mod grammars;
+mod helpers;
+
+use cfg::earley::Grammar;
+
+use gearley::forest::bocage::order::MatchOrder;
+use gearley::forest::bocage::trees::ParseTree;
+use gearley::forest::Bocage;
+use gearley::grammar::InternalGrammar;
+use gearley::recognizer::Recognizer;
+
+use grammars::*;
+use helpers::Parse;
+
+fn evaluate(tree: &ParseTree) -> i32 {
+    match *tree {
+        ParseTree::Node {
+            action,
+            ref children,
+            ..
+        } => {
+            let values: Vec<i32> = children.iter().map(evaluate).collect();
+            let args: Vec<&i32> = values.iter().collect();
+            ambiguous_arith::rule(action, &args[..])
+        }
+        ParseTree::Leaf { symbol, .. } => ambiguous_arith::leaf(symbol),
+        ParseTree::Nulling { .. } => unreachable!(),
+    }
+}
+
+fn leaf_values(tree: &ParseTree, values: &mut Vec<u32>) {
+    match *tree {
+        ParseTree::Node { ref children, .. } => {
+            for child in children {
+                leaf_values(child, values);
+            }
+        }
+        ParseTree::Leaf { value, .. } => values.push(value),
+        ParseTree::Nulling { .. } => {}
+    }
+}
+
+#[test]
+fn test_trees() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    let mut results: Vec<i32> = rec.forest.trees(root).map(|tree| evaluate(&tree)).collect();
+    results.sort();
+    assert_eq!(results, vec![1, 2, 3, 7, 8]);
+
+    let first = rec.forest.trees(root).next().unwrap();
+    let mut values = vec![];
+    leaf_values(&first, &mut values);
+    assert_eq!(values, (0..tokens.len() as u32).collect::<Vec<_>>());
+    assert_eq!(rec.forest.trees(root).take(2).count(), 2);
+}
+
+#[test]
+fn test_trees_with_order() {
+    let _ = env_logger::try_init();
+    let tokens = ambiguous_arith!('2' '-' '0' '*' '3' '+' '1');
+    let external = ambiguous_arith::grammar();
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut bocage = Bocage::new(&cfg);
+    bocage.enable_spans();
+    let mut rec = Recognizer::new(&cfg, bocage);
+    assert!(rec.parse(tokens));
+    let root = rec.finished_node().unwrap();
+    rec.forest.mark_alive(root, MatchOrder::longest());
+    let results: Vec<i32> = rec.forest.trees(root).map(|tree| evaluate(&tree)).collect();
+    assert_eq!(results, vec![7]);
+}
+
+#[test]
+fn test_trees_nulling_external_symbol() {
+    let _ = env_logger::try_init();
+    let mut external = Grammar::new();
+    // The unused symbol makes internal symbols differ from external ones.
+    let (start, _unused, x, a, b) = external.sym();
+    external.rule(start).rhs([x, a, b])
+            .rule(x).rhs([]);
+    external.set_start(start);
+    let cfg = InternalGrammar::from_grammar(&external);
+    let mut rec = Recognizer::new(&cfg, Bocage::new(&cfg));
+    assert!(rec.parse(&[a.usize() as u32, b.usize() as u32]));
+    let root = rec.finished_node().unwrap();
+    let tree = rec.forest.trees(root).next().unwrap();
+    match tree {
+        ParseTree::Node { lhs, children, .. } => {
+            assert_eq!(lhs, start);
+            let symbols: Vec<_> = children
+                .iter()
+                .map(|child| match *child {
+                    ParseTree::Nulling { symbol } | ParseTree::Leaf { symbol, .. } => symbol,
+                    ParseTree::Node { lhs, .. } => lhs,
+                })
+                .collect();
+            assert_eq!(symbols, vec![x, a, b]);
+        }
+        _ => panic!("expected a node"),
+    }
+}